@@ -0,0 +1,237 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::format;
+use crate::map::EnvMap;
+
+/// Sets the variables in `map` which are not already present in the environment.
+///
+/// Existing environment variables are left untouched, even if they are empty. The returned
+/// report lists the keys which were set and those which were skipped, so applications can
+/// log why a file value did not take effect. Variables which the environment cannot hold, with
+/// a key which is empty or contains `=` or NUL, or a value which contains NUL, are not set and
+/// are listed as [`rejected`](AppliedReport::rejected).
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::EnvMap;
+///
+/// std::env::set_var("APPLY_MISSING_DOC_PORT", "9090");
+/// let mut map = EnvMap::new();
+/// map.insert("APPLY_MISSING_DOC_PORT", "8080");
+/// map.insert("APPLY_MISSING_DOC_HOST", "localhost");
+///
+/// let report = dotenvy::apply_missing(&map);
+/// assert_eq!(report.applied(), ["APPLY_MISSING_DOC_HOST"]);
+/// for key in report.skipped() {
+///     println!("{} is already set, ignoring the file value", key);
+/// }
+/// ```
+pub fn apply_missing(map: &EnvMap) -> AppliedReport {
+    let mut report = AppliedReport::default();
+    for (key, value) in map.iter() {
+        if !format::is_settable(key, value) {
+            report.push_rejected(key);
+        } else if env::var_os(key).is_some() {
+            report.push_skipped(key);
+        } else {
+            env::set_var(key, value);
+            report.push_applied(key);
+        }
+    }
+    report
+}
+
+/// Sets all variables in `map`, overriding existing environment variables.
+///
+/// Variables which the environment cannot hold are rejected, as in [`apply_missing`].
+pub(crate) fn apply_all(map: &EnvMap) -> AppliedReport {
+    let mut report = AppliedReport::default();
+    for (key, value) in map.iter() {
+        if format::is_settable(key, value) {
+            env::set_var(key, value);
+            report.push_applied(key);
+        } else {
+            report.push_rejected(key);
+        }
+    }
+    report
+}
+
+/// The keys set and skipped when applying variables to the environment.
+///
+/// Keys are listed in the order they were applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedReport {
+    applied: Vec<String>,
+    skipped: Vec<String>,
+    rejected: Vec<String>,
+}
+
+impl AppliedReport {
+    /// Returns the keys which were set.
+    pub fn applied(&self) -> &[String] {
+        &self.applied
+    }
+
+    /// Returns the keys which were left alone because they were already set.
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    /// Returns the keys which were not set because the environment cannot hold the variable.
+    pub fn rejected(&self) -> &[String] {
+        &self.rejected
+    }
+
+    /// Returns `true` if `key` was set.
+    pub fn is_applied(&self, key: &str) -> bool {
+        self.applied.iter().any(|applied| applied == key)
+    }
+
+    /// Returns `true` if `key` was skipped because it was already set.
+    pub fn is_skipped(&self, key: &str) -> bool {
+        self.skipped.iter().any(|skipped| skipped == key)
+    }
+
+    pub(crate) fn push_applied(&mut self, key: &str) {
+        self.applied.push(key.to_owned());
+    }
+
+    pub(crate) fn push_skipped(&mut self, key: &str) {
+        self.skipped.push(key.to_owned());
+    }
+
+    pub(crate) fn push_rejected(&mut self, key: &str) {
+        self.rejected.push(key.to_owned());
+    }
+}
+
+/// The file loaded by one of the reporting entry points, such as [`dotenv_report`], and the
+/// keys it set and skipped.
+///
+/// [`dotenv_report`]: crate::dotenv_report
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let report = dotenvy::dotenv_report()?;
+/// println!(
+///     "loaded {} variables from {}",
+///     report.applied().len(),
+///     report.path().display()
+/// );
+/// for key in report.skipped() {
+///     println!("{} is already set, ignoring the file value", key);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadReport {
+    path: PathBuf,
+    keys: AppliedReport,
+}
+
+impl LoadReport {
+    pub(crate) fn new(path: PathBuf, keys: AppliedReport) -> Self {
+        LoadReport { path, keys }
+    }
+
+    /// Returns the path of the loaded file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the keys which were set.
+    pub fn applied(&self) -> &[String] {
+        self.keys.applied()
+    }
+
+    /// Returns the keys which were left alone because they were already set.
+    pub fn skipped(&self) -> &[String] {
+        self.keys.skipped()
+    }
+
+    /// Returns the keys which were not set because the environment cannot hold the variable.
+    pub fn rejected(&self) -> &[String] {
+        self.keys.rejected()
+    }
+
+    /// Returns `true` if `key` was set.
+    pub fn is_applied(&self, key: &str) -> bool {
+        self.keys.is_applied(key)
+    }
+
+    /// Returns `true` if `key` was skipped because it was already set.
+    pub fn is_skipped(&self, key: &str) -> bool {
+        self.keys.is_skipped(key)
+    }
+
+    /// Returns the keys as an [`AppliedReport`].
+    pub fn keys(&self) -> &AppliedReport {
+        &self.keys
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_missing() {
+        env::set_var("DOTENVY_APPLY_TEST_EXISTING", "old");
+        env::set_var("DOTENVY_APPLY_TEST_EMPTY", "");
+        let map: EnvMap = vec![
+            ("DOTENVY_APPLY_TEST_EXISTING", "new"),
+            ("DOTENVY_APPLY_TEST_MISSING", "new"),
+            ("DOTENVY_APPLY_TEST_EMPTY", "new"),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = apply_missing(&map);
+        assert_eq!(report.applied(), ["DOTENVY_APPLY_TEST_MISSING"]);
+        assert_eq!(
+            report.skipped(),
+            ["DOTENVY_APPLY_TEST_EXISTING", "DOTENVY_APPLY_TEST_EMPTY"]
+        );
+        assert!(report.is_skipped("DOTENVY_APPLY_TEST_EMPTY"));
+        assert!(!report.is_applied("DOTENVY_APPLY_TEST_EMPTY"));
+        assert_eq!(env::var("DOTENVY_APPLY_TEST_EXISTING").unwrap(), "old");
+        assert_eq!(env::var("DOTENVY_APPLY_TEST_MISSING").unwrap(), "new");
+        assert_eq!(env::var("DOTENVY_APPLY_TEST_EMPTY").unwrap(), "");
+    }
+
+    #[test]
+    fn test_apply_all() {
+        env::set_var("DOTENVY_APPLY_ALL_TEST", "old");
+        let map: EnvMap = vec![("DOTENVY_APPLY_ALL_TEST", "new")]
+            .into_iter()
+            .collect();
+        let report = apply_all(&map);
+        assert_eq!(report.applied(), ["DOTENVY_APPLY_ALL_TEST"]);
+        assert!(report.skipped().is_empty());
+        assert_eq!(env::var("DOTENVY_APPLY_ALL_TEST").unwrap(), "new");
+    }
+
+    #[test]
+    fn test_apply_rejects_unsettable_variables() {
+        let mut map = EnvMap::new();
+        map.insert("", "1");
+        map.insert("DOTENVY_APPLY_REJECT=A", "1");
+        map.insert("DOTENVY_APPLY_REJECT_NUL", "a\0b");
+        map.insert("DOTENVY_APPLY_REJECT_OK", "1");
+        let rejected = ["", "DOTENVY_APPLY_REJECT=A", "DOTENVY_APPLY_REJECT_NUL"];
+
+        let report = apply_missing(&map);
+        assert_eq!(report.applied(), ["DOTENVY_APPLY_REJECT_OK"]);
+        assert_eq!(report.rejected(), rejected);
+        let report = apply_all(&map);
+        assert_eq!(report.applied(), ["DOTENVY_APPLY_REJECT_OK"]);
+        assert_eq!(report.rejected(), rejected);
+        assert!(env::var_os("DOTENVY_APPLY_REJECT_NUL").is_none());
+    }
+}
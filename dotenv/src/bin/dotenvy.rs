@@ -1,6 +1,12 @@
 use clap::Arg;
+use dotenvy::{Document, Dotenv, EnvFormat, EnvLoader, IncrementalParser, Layers};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
 use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
 
 macro_rules! die {
     ($fmt:expr) => ({
@@ -20,13 +26,372 @@ fn make_command(name: &str, args: Vec<&str>) -> process::Command {
         command.arg(arg);
     }
 
-    return command;
+    command
+}
+
+fn format_file(path: &Path, check: bool) {
+    let input = fs::read_to_string(path)
+        .unwrap_or_else(|e| die!("error: failed to read {}: {}", path.display(), e));
+    let mut document = Document::parse(&input)
+        .unwrap_or_else(|e| die!("error: failed to parse {}: {}", path.display(), e));
+    document.normalize();
+    let output = document.to_string();
+
+    if output == input {
+        return;
+    }
+    if check {
+        die!("{} is not formatted", path.display());
+    }
+    fs::write(path, output)
+        .unwrap_or_else(|e| die!("error: failed to write {}: {}", path.display(), e));
+}
+
+fn loader(file: Option<&str>) -> EnvLoader {
+    EnvLoader::new()
+        .filename(file.unwrap_or(".env"))
+        .on_hand_modified(|path| {
+            eprintln!(
+                "dotenvy: warning: {} was generated, but has been modified by hand since",
+                path.display()
+            )
+        })
+}
+
+fn explain(loader: EnvLoader, keys: Vec<&str>) {
+    let path = loader
+        .resolve_path()
+        .unwrap_or_else(|e| die!("error: failed to find .env file: {}", e));
+    let source = path.display().to_string();
+    let resolved = Layers::new()
+        .loader(loader)
+        .process_env()
+        .resolve()
+        .unwrap_or_else(|e| match std::env::current_dir() {
+            Ok(dir) => die!(
+                "error: failed to load environment: {}",
+                e.display_relative(&dir)
+            ),
+            Err(_) => die!("error: failed to load environment: {}", e),
+        });
+
+    if keys.is_empty() {
+        for var in resolved.iter() {
+            if var.definitions().iter().any(|d| d.source() == source) {
+                println!("{}", var);
+            }
+        }
+        return;
+    }
+    for key in keys {
+        match resolved.get(key) {
+            Some(var) => println!("{}", var),
+            None => println!("{} is not set", key),
+        }
+    }
+}
+
+/// Runs `command` with the variables of the file, restarting it whenever the file changes.
+///
+/// The file is parsed again for each run rather than loaded into this process, so variables
+/// removed from the file are not passed on. Variables set before `dotenvy` started still take
+/// precedence.
+fn watch(loader: EnvLoader, mut command: process::Command) -> ! {
+    let mut watcher = loader
+        .watcher()
+        .unwrap_or_else(|e| die!("error: failed to find .env file: {}", e));
+    let path = watcher
+        .paths()
+        .next()
+        .expect("the loader's file")
+        .to_owned();
+    let inherited: HashSet<_> = env::vars_os().map(|(key, _)| key).collect();
+    let configure = |command: &mut process::Command, map: dotenvy::EnvMap| {
+        for (key, value) in map.iter() {
+            if !inherited.contains(std::ffi::OsStr::new(key)) {
+                command.env(key, value);
+            }
+        }
+    };
+
+    // only the statements which changed are parsed again on reload
+    let mut parser = IncrementalParser::new();
+    let map = parser
+        .parse_path(&path)
+        .unwrap_or_else(|e| die!("error: failed to load environment: {}", e));
+    configure(&mut command, map);
+    loop {
+        let mut child = command.spawn().unwrap_or_else(|e| die!("fatal: {}", e));
+        let mut running = true;
+        loop {
+            thread::sleep(watcher.poll_interval());
+            if running {
+                if let Ok(Some(status)) = child.try_wait() {
+                    running = false;
+                    eprintln!(
+                        "dotenvy: command exited with {}, waiting for changes to {}",
+                        status,
+                        path.display()
+                    );
+                }
+            }
+            if watcher.poll().is_empty() {
+                continue;
+            }
+            match parser.parse_path(&path) {
+                Ok(map) => {
+                    command.env_clear().envs(env::vars_os());
+                    configure(&mut command, map);
+                    break;
+                }
+                Err(e) => eprintln!("dotenvy: not restarting, failed to load environment: {}", e),
+            }
+        }
+        // harmless if the command already exited
+        let _ = child.kill();
+        let _ = child.wait();
+        eprintln!("dotenvy: {} changed, restarting", path.display());
+    }
+}
+
+/// The shells supported by `dotenvy hook`.
+#[derive(Clone, Copy)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    fn from_name(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    fn quote(self, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("'{}'", value.replace('\'', r"'\''")),
+            Shell::Fish => format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'")),
+        }
+    }
+
+    fn set(self, key: &str, value: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export {}={};", key, self.quote(value)),
+            Shell::Fish => format!("set -gx {} {};", key, self.quote(value)),
+        }
+    }
+
+    fn unset(self, key: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("unset {};", key),
+            Shell::Fish => format!("set -e {};", key),
+        }
+    }
+}
+
+/// The variable holding the path of the file loaded by the hook.
+const HOOK_FILE_VAR: &str = "DOTENVY_FILE";
+/// The variable holding the space separated keys set by the hook.
+const HOOK_KEYS_VAR: &str = "DOTENVY_KEYS";
+
+/// Prints shell code which runs `dotenvy export` before each prompt.
+fn hook(shell: Shell, file: Option<&str>) {
+    let exe = env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "dotenvy".to_string());
+    let mut export = shell.quote(&exe);
+    if let Some(file) = file {
+        export = format!("{} --file {}", export, shell.quote(file));
+    }
+    match shell {
+        Shell::Bash => print!(
+            r#"_dotenvy_hook() {{
+  eval "$({} export {})"
+}}
+if [[ ";${{PROMPT_COMMAND:-}};" != *";_dotenvy_hook;"* ]]; then
+  PROMPT_COMMAND="_dotenvy_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+fi
+"#,
+            export,
+            shell.name()
+        ),
+        Shell::Zsh => print!(
+            r#"_dotenvy_hook() {{
+  eval "$({} export {})"
+}}
+typeset -ag precmd_functions
+if (( ! ${{precmd_functions[(I)_dotenvy_hook]}} )); then
+  precmd_functions=(_dotenvy_hook $precmd_functions)
+fi
+"#,
+            export,
+            shell.name()
+        ),
+        Shell::Fish => print!(
+            r#"function __dotenvy_hook --on-event fish_prompt
+    {} export {} | source
+end
+"#,
+            export,
+            shell.name()
+        ),
+    }
+}
+
+/// Returns the directory holding the files approved with `dotenvy allow`.
+fn allow_dir() -> PathBuf {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_else(|| die!("error: neither XDG_DATA_HOME nor HOME is set"));
+    data_dir.join("dotenvy").join("allow")
+}
+
+/// Returns the path of the record approving the file at `path`.
+///
+/// The record holds the path and the approved contents, so any change to the file requires
+/// approving it again.
+fn allow_record(path: &Path) -> PathBuf {
+    // FNV-1a, only used to name the record, which is compared in full
+    let hash = path
+        .display()
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    allow_dir().join(format!("{:016x}", hash))
+}
+
+fn allow_contents(path: &Path, contents: &str) -> String {
+    format!("{}\n{}", path.display(), contents)
+}
+
+/// Approves the current contents of the file at `path` for `dotenvy hook`, or revokes the
+/// approval.
+fn allow(path: &Path, allowed: bool) {
+    let record = allow_record(path);
+    if !allowed {
+        match fs::remove_file(&record) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                die!("error: failed to remove {}: {}", record.display(), e)
+            }
+            _ => return,
+        }
+    }
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|e| die!("error: failed to read {}: {}", path.display(), e));
+    fs::create_dir_all(allow_dir())
+        .and_then(|_| fs::write(&record, allow_contents(path, &contents)))
+        .unwrap_or_else(|e| die!("error: failed to write {}: {}", record.display(), e));
+}
+
+/// Returns `true` if the contents of the file at `path` were approved with `dotenvy allow`.
+fn is_allowed(path: &Path, contents: &str) -> bool {
+    fs::read_to_string(allow_record(path))
+        .map_or(false, |record| record == allow_contents(path, contents))
+}
+
+/// Returns `true` if `key` can be used as a shell variable name.
+fn is_shell_name(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Prints shell code which unsets the variables set for the previously loaded file, and sets
+/// those of the nearest file, if any.
+///
+/// Only files approved with `dotenvy allow` are loaded, since any file in the current
+/// directory or its parents, such as one in a freshly cloned repository, would otherwise
+/// control the shell's environment. Variables which were set before the hook ran are left
+/// alone, and keys which are not valid shell variable names, such as `A.B`, are skipped.
+fn export(shell: Shell, loader: EnvLoader) {
+    let previous = env::var(HOOK_KEYS_VAR).unwrap_or_default();
+    let previous: HashSet<&str> = previous.split_whitespace().collect();
+    let map = match loader.resolve_path() {
+        Ok(path) => {
+            let contents = fs::read_to_string(&path)
+                .unwrap_or_else(|e| die!("dotenvy: failed to read {}: {}", path.display(), e));
+            if is_allowed(&path, &contents) {
+                match Dotenv::new().parse(&mut contents.as_bytes()) {
+                    Ok(map) => Some((path, map)),
+                    Err(e) => die!("dotenvy: failed to load {}: {}", path.display(), e),
+                }
+            } else {
+                eprintln!(
+                    "dotenvy: {} is blocked, run `dotenvy allow` to approve its contents",
+                    path.display()
+                );
+                None
+            }
+        }
+        Err(e) if e.not_found() => None,
+        Err(e) => die!("dotenvy: failed to find .env file: {}", e),
+    };
+
+    let mut output = Vec::new();
+    let mut keys = Vec::new();
+    if let Some((path, map)) = &map {
+        let reloaded = env::var_os(HOOK_FILE_VAR).as_deref() == Some(path.as_os_str());
+        for (key, value) in map.iter() {
+            if !is_shell_name(key) || key == HOOK_FILE_VAR || key == HOOK_KEYS_VAR {
+                if !reloaded {
+                    eprintln!("dotenvy: skipping {}, which cannot be exported", key);
+                }
+                continue;
+            }
+            let current = env::var(key).ok();
+            if current.is_some() && !previous.contains(key) {
+                continue;
+            }
+            keys.push(key);
+            if current.as_deref() != Some(value) {
+                output.push(shell.set(key, value));
+            }
+        }
+        let path = path.display().to_string();
+        if env::var(HOOK_FILE_VAR).ok().as_deref() != Some(&path) {
+            output.push(shell.set(HOOK_FILE_VAR, &path));
+        }
+        let joined = keys.join(" ");
+        if env::var(HOOK_KEYS_VAR).ok().as_deref() != Some(&joined) {
+            output.push(shell.set(HOOK_KEYS_VAR, &joined));
+        }
+    } else if env::var_os(HOOK_FILE_VAR).is_some() {
+        output.push(shell.unset(HOOK_FILE_VAR));
+        output.push(shell.unset(HOOK_KEYS_VAR));
+    }
+    let mut unloaded: Vec<_> = previous
+        .difference(&keys.into_iter().collect())
+        .copied()
+        .collect();
+    unloaded.sort_unstable();
+    output.extend(unloaded.into_iter().map(|key| shell.unset(key)));
+    if !output.is_empty() {
+        println!("{}", output.join("\n"));
+    }
 }
 
 fn main() {
     let matches = clap::Command::new("dotenvy")
         .about("Run a command using the environment in a .env file")
-        .override_usage("dotenvy <COMMAND> [ARGS]...")
+        .override_usage(
+            "dotenvy [--watch] <COMMAND> [ARGS]...\n    dotenvy fmt [--check]\n    dotenvy explain [KEY]...\n    dotenvy hook <SHELL>\n    dotenvy allow [--revoke]",
+        )
         .allow_external_subcommands(true)
         .arg_required_else_help(true)
         .arg(
@@ -36,13 +401,112 @@ fn main() {
                 .takes_value(true)
                 .help("Use a specific .env file (defaults to .env)"),
         )
+        .arg(
+            Arg::new("watch")
+                .short('w')
+                .long("watch")
+                .help("Restart the command whenever the .env file changes"),
+        )
+        .subcommand(
+            clap::Command::new("fmt")
+                .about("Format the .env file in place")
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Fail if the file is not formatted, instead of formatting it"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("explain")
+                .about("Show where each variable's value comes from")
+                .arg(
+                    Arg::new("KEY")
+                        .multiple_values(true)
+                        .help("Variables to explain (defaults to those in the .env file)"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("hook")
+                .about(
+                    "Print shell code which loads the nearest approved .env file before each prompt",
+                )
+                .arg(
+                    Arg::new("SHELL")
+                        .required(true)
+                        .possible_values(["bash", "zsh", "fish"]),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("allow")
+                .about("Approve the current contents of the .env file for `hook`")
+                .arg(
+                    Arg::new("revoke")
+                        .long("revoke")
+                        .help("Revoke the approval instead"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("export")
+                .about("Print shell code which loads the nearest .env file, used by `hook`")
+                .hide(true)
+                .arg(
+                    Arg::new("SHELL")
+                        .required(true)
+                        .possible_values(["bash", "zsh", "fish"]),
+                ),
+        )
         .get_matches();
 
-    match matches.value_of("FILE") {
-        None => dotenvy::dotenv(),
-        Some(file) => dotenvy::from_filename(file),
+    if let Some(("fmt", fmt_matches)) = matches.subcommand() {
+        let loader = loader(matches.value_of("FILE"));
+        let path = loader
+            .resolve_path()
+            .unwrap_or_else(|e| die!("error: failed to find .env file: {}", e));
+        format_file(&path, fmt_matches.is_present("check"));
+        return;
+    }
+
+    if let Some(("explain", explain_matches)) = matches.subcommand() {
+        let loader = loader(matches.value_of("FILE"));
+        let keys = explain_matches
+            .values_of("KEY")
+            .map(|v| v.collect())
+            .unwrap_or_default();
+        explain(loader, keys);
+        return;
+    }
+
+    if let Some(("allow", allow_matches)) = matches.subcommand() {
+        let loader = loader(matches.value_of("FILE"));
+        let path = loader
+            .resolve_path()
+            .unwrap_or_else(|e| die!("error: failed to find .env file: {}", e));
+        allow(&path, !allow_matches.is_present("revoke"));
+        return;
+    }
+
+    if let Some((name @ ("hook" | "export"), shell_matches)) = matches.subcommand() {
+        let shell = shell_matches
+            .value_of("SHELL")
+            .and_then(Shell::from_name)
+            .unwrap_or_else(|| die!("error: unsupported shell"));
+        if name == "hook" {
+            hook(shell, matches.value_of("FILE"));
+        } else {
+            let loader = loader(matches.value_of("FILE"));
+            export(shell, loader);
+        }
+        return;
+    }
+
+    let watching = matches.is_present("watch");
+    if !watching {
+        match matches.value_of("FILE") {
+            None => dotenvy::dotenv(),
+            Some(file) => dotenvy::from_filename(file),
+        }
+        .unwrap_or_else(|e| die!("error: failed to load environment: {}", e));
     }
-    .unwrap_or_else(|e| die!("error: failed to load environment: {}", e));
 
     let mut command = match matches.subcommand() {
         Some((name, matches)) => {
@@ -56,6 +520,11 @@ fn main() {
         None => die!("error: missing required argument <COMMAND>"),
     };
 
+    if watching {
+        let loader = loader(matches.value_of("FILE"));
+        watch(loader, command);
+    }
+
     if cfg!(target_os = "windows") {
         match command.spawn().and_then(|mut child| child.wait()) {
             Ok(status) => process::exit(status.code().unwrap_or(1)),
@@ -0,0 +1,250 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::apply::{self, AppliedReport};
+use crate::errors::*;
+use crate::filesystem::StdFileSystem;
+use crate::find;
+use crate::loader::EnvLoader;
+use crate::map::EnvMap;
+use crate::schema::EnvSchema;
+
+/// The whole start-up sequence of an application: finding a cascade of *.env* files, parsing
+/// them, validating the result against an optional [`EnvSchema`] and applying it to the
+/// environment.
+///
+/// The cascade is read from the directory of the base file, which is searched for in the
+/// current directory and its parents. With a base file of `.env` and a profile of `test`, the
+/// files are, from lowest to highest precedence:
+///
+/// 1. `.env`
+/// 2. `.env.local`
+/// 3. `.env.test`
+/// 4. `.env.test.local`
+///
+/// Missing files are skipped, unless the base file is [`required`](Bootstrap::required).
+/// Existing environment variables are never overridden, and the environment is only modified
+/// if every step succeeds. Failures do not stop the sequence, so the returned
+/// [`BootstrapReport`] lists all of them at once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::{Bootstrap, EnvSchema};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let report = Bootstrap::new()
+///     .profile("production")
+///     .schema(EnvSchema::from_path(".env.example")?)
+///     .run();
+/// if !report.is_ok() {
+///     eprintln!("{}", report);
+///     std::process::exit(1);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bootstrap {
+    filename: PathBuf,
+    profile: Option<String>,
+    schema: Option<EnvSchema>,
+    required: bool,
+}
+
+/// The outcome of a [`Bootstrap`]: the files read, the variables they defined, the keys set
+/// and skipped, and every error encountered.
+///
+/// The `Display` implementation summarizes the outcome, listing each error on its own line.
+#[derive(Debug)]
+pub struct BootstrapReport {
+    files: Vec<PathBuf>,
+    map: EnvMap,
+    keys: AppliedReport,
+    errors: Vec<Error>,
+}
+
+impl Bootstrap {
+    /// Creates a bootstrap for the cascade based on `.env`, without a profile or schema.
+    pub fn new() -> Self {
+        Bootstrap {
+            filename: PathBuf::from(".env"),
+            profile: None,
+            schema: None,
+            required: false,
+        }
+    }
+
+    /// Sets the name of the base file. Defaults to `.env`.
+    pub fn filename<P: AsRef<Path>>(mut self, filename: P) -> Self {
+        self.filename = filename.as_ref().to_owned();
+        self
+    }
+
+    /// Also reads the files for `profile`, such as `.env.production`.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Validates the variables against `schema` before applying them.
+    ///
+    /// The variables checked are those of the files, overridden by existing environment
+    /// variables, as they will be once applied.
+    pub fn schema(mut self, schema: EnvSchema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Sets whether a missing base file is an error. Defaults to `false`.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Runs the sequence, returning its report.
+    pub fn run(&self) -> BootstrapReport {
+        let mut report = BootstrapReport {
+            files: Vec::new(),
+            map: EnvMap::new(),
+            keys: AppliedReport::default(),
+            errors: Vec::new(),
+        };
+
+        let current_dir = match env::current_dir() {
+            Ok(dir) => dir,
+            Err(err) => {
+                report.errors.push(Error::Io(err));
+                return report;
+            }
+        };
+        let dir = match find::find_in(&StdFileSystem, &current_dir, &self.filename) {
+            Ok(path) => path.parent().map_or(current_dir, Path::to_owned),
+            Err(err) if err.not_found() && !self.required => current_dir,
+            Err(err) => {
+                report.errors.push(err);
+                return report;
+            }
+        };
+
+        for path in self.cascade(&dir) {
+            match EnvLoader::new().path(&path).parse() {
+                Ok(map) => {
+                    report.map.extend(map);
+                    report.files.push(path);
+                }
+                Err(err) if err.not_found() => {}
+                Err(err) => report.errors.push(err),
+            }
+        }
+
+        if let Some(schema) = &self.schema {
+            let mut effective = report.map.clone();
+            effective.extend(env::vars_os().filter_map(|(key, value)| {
+                Some((key.into_string().ok()?, value.into_string().ok()?))
+            }));
+            if let Err(err) = schema.validate(&effective) {
+                report.errors.push(err);
+            }
+        }
+
+        if report.errors.is_empty() {
+            report.keys = apply::apply_missing(&report.map);
+        }
+        report
+    }
+
+    /// Returns the paths of the cascade in `dir`, from lowest to highest precedence.
+    fn cascade(&self, dir: &Path) -> Vec<PathBuf> {
+        let with_suffix = |suffix: &str| {
+            let mut name = OsString::from(self.filename.as_os_str());
+            name.push(suffix);
+            dir.join(name)
+        };
+        let mut paths = vec![dir.join(&self.filename), with_suffix(".local")];
+        if let Some(profile) = &self.profile {
+            paths.push(with_suffix(&format!(".{}", profile)));
+            paths.push(with_suffix(&format!(".{}.local", profile)));
+        }
+        paths
+    }
+}
+
+impl Default for Bootstrap {
+    fn default() -> Self {
+        Bootstrap::new()
+    }
+}
+
+impl BootstrapReport {
+    /// Returns `true` if every step succeeded and the variables were applied.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the errors encountered, in the order they occurred.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Returns the paths of the files which were read, from lowest to highest precedence.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the variables defined by the files, with later files overriding earlier ones.
+    pub fn map(&self) -> &EnvMap {
+        &self.map
+    }
+
+    /// Returns the keys which were set. This is empty if any step failed.
+    pub fn applied(&self) -> &[String] {
+        self.keys.applied()
+    }
+
+    /// Returns the keys which were left alone because they were already set.
+    pub fn skipped(&self) -> &[String] {
+        self.keys.skipped()
+    }
+
+    /// Returns the keys as an [`AppliedReport`].
+    pub fn keys(&self) -> &AppliedReport {
+        &self.keys
+    }
+}
+
+impl fmt::Display for BootstrapReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if !self.errors.is_empty() {
+            write!(fmt, "failed to load the environment:")?;
+            for err in &self.errors {
+                write!(fmt, "\n  {}", err)?;
+            }
+            return Ok(());
+        }
+        write!(fmt, "loaded {} variables", self.applied().len())?;
+        for (i, path) in self.files.iter().enumerate() {
+            let separator = if i == 0 { " from " } else { ", " };
+            write!(fmt, "{}{}", separator, path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a [`Bootstrap`] with the default settings: the `.env` cascade without a profile or
+/// schema.
+///
+/// # Examples
+///
+/// ```no_run
+/// let report = dotenvy::bootstrap();
+/// if !report.is_ok() {
+///     eprintln!("{}", report);
+///     std::process::exit(1);
+/// }
+/// ```
+pub fn bootstrap() -> BootstrapReport {
+    Bootstrap::new().run()
+}
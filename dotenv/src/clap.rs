@@ -0,0 +1,315 @@
+use std::fmt;
+
+use clap::{ArgMatches, Command, ValueSource};
+
+use crate::errors::*;
+use crate::loader::EnvLoader;
+use crate::map::EnvMap;
+
+/// Variables from a *.env* file used as fallbacks for clap arguments with an `env` name.
+///
+/// [`apply`](ClapEnv::apply) makes arguments declared with `env = "FOO"` fall back to the
+/// file's `FOO` without modifying the process environment. A value is taken from, in order
+/// of precedence:
+///
+/// 1. the command line,
+/// 2. the process environment,
+/// 3. the *.env* file,
+/// 4. the argument's own default value.
+///
+/// [`value_source`](ClapEnv::value_source) and [`explain`](ClapEnv::explain) report which of
+/// these each value came from.
+///
+/// # Examples
+///
+/// ```no_run
+/// use clap::{Arg, Command};
+/// use dotenvy::ClapEnv;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let env = ClapEnv::load()?;
+/// let command = Command::new("server").arg(
+///     Arg::new("port")
+///         .long("port")
+///         .env("PORT")
+///         .takes_value(true)
+///         .default_value("8080"),
+/// );
+/// let matches = env.get_matches(command);
+/// println!("port = {}", matches.value_of("port").unwrap());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClapEnv {
+    map: EnvMap,
+}
+
+/// Where the value of a clap argument came from, like clap's [`ValueSource`] but telling
+/// *.env* file values apart from built-in defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClapValueSource {
+    CommandLine,
+    Environment,
+    EnvFile,
+    Default,
+}
+
+/// The origin of one argument's value, as reported by [`ClapEnv::explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgOrigin {
+    id: String,
+    env: String,
+    source: Option<ClapValueSource>,
+}
+
+impl ClapEnv {
+    /// Parses the *.env* file in the current directory or its parents. A missing file yields
+    /// no fallbacks.
+    pub fn load() -> Result<Self> {
+        match EnvLoader::new().parse() {
+            Ok(map) => Ok(ClapEnv::from_map(map)),
+            Err(err) if err.not_found() => Ok(ClapEnv::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Parses the file of `loader`.
+    pub fn from_loader(loader: &EnvLoader) -> Result<Self> {
+        loader.parse().map(ClapEnv::from_map)
+    }
+
+    /// Uses already parsed variables.
+    pub fn from_map(map: EnvMap) -> Self {
+        ClapEnv { map }
+    }
+
+    /// Returns the variables used as fallbacks.
+    pub fn map(&self) -> &EnvMap {
+        &self.map
+    }
+
+    /// Sets the default value of every argument whose `env` name is in the file, including
+    /// arguments of subcommands.
+    ///
+    /// This replaces the argument's own default, while clap still prefers the command line
+    /// and the process environment.
+    pub fn apply<'help>(&'help self, mut command: Command<'help>) -> Command<'help> {
+        let args: Vec<_> = command
+            .get_arguments()
+            .filter(|arg| arg.get_env().is_some() || !is_generated(arg.get_id()))
+            .map(|arg| {
+                let value = Some(arg)
+                    .filter(|arg| arg.is_takes_value_set())
+                    .and_then(|arg| self.map.get(arg.get_env()?.to_str()?));
+                (arg.get_id(), value)
+            })
+            .collect();
+        // `mut_arg` moves the argument to the end, so every argument is visited to keep
+        // their order in the help output and for positionals. Mutating the generated `help`
+        // and `version` flags would keep them even where clap removes them.
+        for (id, value) in args {
+            command = command.mut_arg(id, |arg| match value {
+                Some(value) => arg.default_value(value),
+                None => arg,
+            });
+        }
+        for subcommand in command.get_subcommands_mut() {
+            *subcommand = self.apply(subcommand.clone());
+        }
+        command
+    }
+
+    /// Applies the fallbacks and parses the process arguments, exiting on error like
+    /// [`Command::get_matches`].
+    pub fn get_matches<'help>(&'help self, command: Command<'help>) -> ArgMatches {
+        self.apply(command).get_matches()
+    }
+
+    /// Returns where the value of the argument `id` came from, or `None` if it has no value.
+    ///
+    /// `command` and `matches` are those of the (sub)command which declares the argument.
+    pub fn value_source(
+        &self,
+        command: &Command,
+        matches: &ArgMatches,
+        id: &str,
+    ) -> Option<ClapValueSource> {
+        let source = match matches.value_source(id)? {
+            ValueSource::CommandLine => ClapValueSource::CommandLine,
+            ValueSource::EnvVariable => ClapValueSource::Environment,
+            _ if self.env_name(command, id).is_some() => ClapValueSource::EnvFile,
+            _ => ClapValueSource::Default,
+        };
+        Some(source)
+    }
+
+    /// Reports the origin of every argument of `command` which has an `env` name.
+    pub fn explain(&self, command: &Command, matches: &ArgMatches) -> Vec<ArgOrigin> {
+        command
+            .get_arguments()
+            .filter_map(|arg| {
+                let env = arg.get_env()?.to_str()?;
+                Some(ArgOrigin {
+                    id: arg.get_id().to_owned(),
+                    env: env.to_owned(),
+                    source: self.value_source(command, matches, arg.get_id()),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the `env` name of the argument `id`, if the file provides it.
+    fn env_name<'a>(&self, command: &'a Command, id: &str) -> Option<&'a str> {
+        let arg = command.get_arguments().find(|arg| arg.get_id() == id)?;
+        let env = arg.get_env()?.to_str()?;
+        if self.map.contains_key(env) {
+            Some(env)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns `true` for the ids of the flags clap may generate.
+fn is_generated(id: &str) -> bool {
+    id == "help" || id == "version"
+}
+
+impl ArgOrigin {
+    /// Returns the argument's id.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the argument's `env` name.
+    pub fn env(&self) -> &str {
+        &self.env
+    }
+
+    /// Returns where the value came from, or `None` if the argument has no value.
+    pub fn source(&self) -> Option<ClapValueSource> {
+        self.source
+    }
+}
+
+impl fmt::Display for ArgOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}): ", self.id, self.env)?;
+        f.write_str(match self.source {
+            Some(ClapValueSource::CommandLine) => "command line",
+            Some(ClapValueSource::Environment) => "process environment",
+            Some(ClapValueSource::EnvFile) => ".env file",
+            Some(ClapValueSource::Default) => "default value",
+            None => "not set",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Arg;
+
+    use super::*;
+
+    fn command<'a>() -> Command<'a> {
+        Command::new("test")
+            .arg(
+                Arg::new("port")
+                    .long("port")
+                    .env("DOTENVY_CLAP_TEST_PORT")
+                    .takes_value(true)
+                    .default_value("80"),
+            )
+            .arg(
+                Arg::new("host")
+                    .long("host")
+                    .env("DOTENVY_CLAP_TEST_HOST")
+                    .takes_value(true)
+                    .default_value("localhost"),
+            )
+            .arg(
+                Arg::new("name")
+                    .long("name")
+                    .env("DOTENVY_CLAP_TEST_NAME")
+                    .takes_value(true),
+            )
+            .subcommand(
+                Command::new("run").arg(
+                    Arg::new("jobs")
+                        .long("jobs")
+                        .env("DOTENVY_CLAP_TEST_JOBS")
+                        .takes_value(true),
+                ),
+            )
+    }
+
+    fn env() -> ClapEnv {
+        let map = vec![
+            ("DOTENVY_CLAP_TEST_PORT", "8080"),
+            ("DOTENVY_CLAP_TEST_JOBS", "4"),
+        ];
+        ClapEnv::from_map(map.into_iter().collect())
+    }
+
+    #[test]
+    fn test_file_values_are_fallbacks() {
+        let env = env();
+        let command = env.apply(command());
+        let matches = command
+            .clone()
+            .try_get_matches_from(["test", "run"])
+            .unwrap();
+        assert_eq!(matches.value_of("port"), Some("8080"));
+        assert_eq!(matches.value_of("host"), Some("localhost"));
+        assert_eq!(matches.value_of("name"), None);
+        let (_, run) = matches.subcommand().unwrap();
+        assert_eq!(run.value_of("jobs"), Some("4"));
+
+        assert_eq!(
+            env.value_source(&command, &matches, "port"),
+            Some(ClapValueSource::EnvFile)
+        );
+        assert_eq!(
+            env.value_source(&command, &matches, "host"),
+            Some(ClapValueSource::Default)
+        );
+        assert_eq!(env.value_source(&command, &matches, "name"), None);
+        assert_eq!(std::env::var_os("DOTENVY_CLAP_TEST_PORT"), None);
+    }
+
+    #[test]
+    fn test_help_is_unchanged() {
+        let env = ClapEnv::from_map(EnvMap::new());
+        let render = |mut command: Command| {
+            let mut help = Vec::new();
+            command.write_help(&mut help).unwrap();
+            String::from_utf8(help).unwrap()
+        };
+        assert_eq!(render(env.apply(command())), render(command()));
+    }
+
+    #[test]
+    fn test_command_line_wins() {
+        let env = env();
+        let command = env.apply(command());
+        let matches = command
+            .clone()
+            .try_get_matches_from(["test", "--port", "9090"])
+            .unwrap();
+        assert_eq!(matches.value_of("port"), Some("9090"));
+        let explained: Vec<_> = env
+            .explain(&command, &matches)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            explained,
+            [
+                "port (DOTENVY_CLAP_TEST_PORT): command line",
+                "host (DOTENVY_CLAP_TEST_HOST): default value",
+                "name (DOTENVY_CLAP_TEST_NAME): not set",
+            ]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use config::{ConfigError, Map, Source, Value, ValueKind};
+
+use crate::errors::*;
+use crate::filesystem;
+use crate::format::{Dotenv, EnvFormat};
+use crate::loader;
+use crate::syntax::Syntax;
+
+/// A *.env* file as a [`config::Source`], for layering it with the `config` crate's other
+/// sources.
+///
+/// Keys are lowercased like `config::Environment` does, so that they can be looked up the
+/// same way. A [`prefix`](DotenvSource::prefix) limits the source to matching keys, and a
+/// [`separator`](DotenvSource::separator) turns keys into nested paths.
+///
+/// # Examples
+///
+/// ```no_run
+/// use config::Config;
+/// use dotenvy::DotenvSource;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let settings = Config::builder()
+///     .add_source(config::File::with_name("settings"))
+///     .add_source(DotenvSource::new().prefix("APP").separator("__"))
+///     .build()?;
+/// let port: u16 = settings.get("server.port")?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DotenvSource {
+    source: loader::Source,
+    syntax: Syntax,
+    required: bool,
+    prefix: Option<String>,
+    separator: Option<String>,
+}
+
+impl DotenvSource {
+    /// Creates a source for the *.env* file in the current directory or its parents.
+    pub fn new() -> Self {
+        DotenvSource {
+            source: loader::Source::Filename(PathBuf::from(".env")),
+            syntax: Syntax::default(),
+            required: true,
+            prefix: None,
+            separator: None,
+        }
+    }
+
+    /// Searches for `filename` in the current directory and its parents.
+    pub fn filename<P: AsRef<Path>>(mut self, filename: P) -> Self {
+        self.source = loader::Source::Filename(filename.as_ref().to_owned());
+        self
+    }
+
+    /// Reads the file at `path`, without searching parent directories.
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.source = loader::Source::Path(path.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the dialect used to parse the file.
+    pub fn syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Sets whether a missing file is an error. Defaults to `true`.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Only includes keys starting with `prefix` and a `_`, which are removed.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Splits keys on `separator` into nested paths, so `SERVER__PORT` becomes `server.port`
+    /// with a separator of `__`.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = Some(separator.into());
+        self
+    }
+
+    fn read(&self) -> Result<Map<String, Value>> {
+        let mut collected = Map::new();
+        let path = match self.source.resolve() {
+            Ok(path) => path,
+            Err(err) if err.not_found() && !self.required => return Ok(collected),
+            Err(err) => return Err(err),
+        };
+        let mut file = match filesystem::open_file(&path) {
+            Ok(file) => file,
+            Err(err) if err.not_found() && !self.required => return Ok(collected),
+            Err(err) => return Err(err),
+        };
+        let map = Dotenv::with_syntax(self.syntax.clone()).parse(&mut file)?;
+
+        let origin = path.display().to_string();
+        for (key, value) in map {
+            let prefix = self.prefix.as_deref();
+            let key = match loader::config_key(&key, prefix, self.separator.as_deref()) {
+                Some(key) => key,
+                None => continue,
+            };
+            let value = Value::new(Some(&origin), ValueKind::String(value));
+            collected.insert(key, value);
+        }
+        Ok(collected)
+    }
+}
+
+impl Default for DotenvSource {
+    fn default() -> Self {
+        DotenvSource::new()
+    }
+}
+
+impl Source for DotenvSource {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> std::result::Result<Map<String, Value>, ConfigError> {
+        self.read()
+            .map_err(|err| ConfigError::Foreign(Box::new(err)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use config::Config;
+
+    use super::*;
+
+    fn envfile(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_collect() {
+        let file = envfile("PORT=8080\nDEBUG=true\nHOST='local host'\n");
+        let config = Config::builder()
+            .add_source(DotenvSource::new().path(file.path()))
+            .build()
+            .unwrap();
+        assert_eq!(config.get::<u16>("port").unwrap(), 8080);
+        assert!(config.get::<bool>("debug").unwrap());
+        assert_eq!(config.get_string("host").unwrap(), "local host");
+    }
+
+    #[test]
+    fn test_prefix_and_separator() {
+        let file = envfile("APP_SERVER__PORT=8080\nAPP_NAME=demo\nOTHER=1\n");
+        let source = DotenvSource::new()
+            .path(file.path())
+            .prefix("APP")
+            .separator("__");
+        let config = Config::builder().add_source(source).build().unwrap();
+        assert_eq!(config.get::<u16>("server.port").unwrap(), 8080);
+        assert_eq!(config.get_string("name").unwrap(), "demo");
+        assert!(config.get_string("other").is_err());
+    }
+
+    #[test]
+    fn test_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = DotenvSource::new().path(dir.path().join("missing.env"));
+        assert!(source.collect().is_err());
+        assert!(source.required(false).collect().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let file = envfile("A B\n");
+        assert!(DotenvSource::new().path(file.path()).collect().is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! The fixtures defining the reference behaviour of the [`Dotenv`](crate::Dotenv) format.
+//!
+//! Each [`Fixture`] is an input file with the variables it must parse to, written as a JSON
+//! object, or `null` if parsing must fail. Other implementations of [`EnvFormat`] can check
+//! themselves against the fixtures with [`run`], or read them with [`fixtures`] to drive their
+//! own test harness.
+//!
+//! # Examples
+//!
+//! ```
+//! use dotenvy::{conformance, Dotenv};
+//!
+//! let failures = conformance::run(&Dotenv::new());
+//! assert!(failures.is_empty(), "{:?}", failures);
+//! ```
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::format::EnvFormat;
+use crate::map::EnvMap;
+
+/// An input file and the variables it must parse to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixture {
+    name: &'static str,
+    input: &'static str,
+    expected: &'static str,
+}
+
+/// A fixture whose input an [`EnvFormat`] did not parse as expected, returned by [`run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    fixture: &'static str,
+    message: String,
+}
+
+macro_rules! fixtures {
+    ($($name:literal),* $(,)?) => {
+        &[$(Fixture {
+            name: $name,
+            input: include_str!(concat!("../conformance/", $name, ".env")),
+            expected: include_str!(concat!("../conformance/", $name, ".json")),
+        }),*]
+    };
+}
+
+static FIXTURES: &[Fixture] = fixtures![
+    "basic",
+    "comments",
+    "export",
+    "quotes",
+    "substitution",
+    "multiline",
+    "whitespace",
+    "empty",
+    "duplicates",
+    "bom",
+    "invalid_key",
+    "invalid_escape",
+    "unquoted_whitespace",
+    "unterminated_quote",
+];
+
+/// Returns every fixture.
+pub fn fixtures() -> &'static [Fixture] {
+    FIXTURES
+}
+
+/// Checks `format` against every fixture, returning those it fails.
+///
+/// Variables are compared regardless of their order. The parser prefers variables of the
+/// process environment in substitutions, so the fixtures only reference variables prefixed
+/// with `DOTENVY_CONFORMANCE_`, which the result depends on if they are set.
+pub fn run(format: &dyn EnvFormat) -> Vec<Failure> {
+    FIXTURES
+        .iter()
+        .filter_map(|fixture| fixture.check(format).err())
+        .collect()
+}
+
+impl Fixture {
+    /// Returns the name of the fixture, such as `quotes`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the contents of the input file.
+    pub fn input(&self) -> &'static str {
+        self.input
+    }
+
+    /// Returns the expected variables as JSON: an object of string values, or `null` if
+    /// parsing must fail.
+    pub fn expected_json(&self) -> &'static str {
+        self.expected
+    }
+
+    /// Returns the expected variables, or `None` if parsing must fail.
+    pub fn expected(&self) -> Option<EnvMap> {
+        let value: Value = serde_json::from_str(self.expected).expect("fixtures are valid JSON");
+        let object = value.as_object()?;
+        Some(
+            object
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str().expect("values are strings")))
+                .collect(),
+        )
+    }
+
+    /// Parses the input with `format` and compares the result with the expected variables.
+    pub fn check(&self, format: &dyn EnvFormat) -> Result<(), Failure> {
+        let fail = |message: String| Failure {
+            fixture: self.name,
+            message,
+        };
+        match (self.expected(), format.parse(&mut self.input.as_bytes())) {
+            (Some(expected), Ok(actual)) => {
+                let (expected, actual) = (sorted(&expected), sorted(&actual));
+                if expected == actual {
+                    Ok(())
+                } else {
+                    Err(fail(format!("expected {:?}, got {:?}", expected, actual)))
+                }
+            }
+            (Some(_), Err(err)) => Err(fail(format!("expected success, got error: {}", err))),
+            (None, Ok(actual)) => Err(fail(format!(
+                "expected an error, got {:?}",
+                sorted(&actual)
+            ))),
+            (None, Err(_)) => Ok(()),
+        }
+    }
+}
+
+impl Failure {
+    /// Returns the name of the fixture which failed.
+    pub fn fixture(&self) -> &'static str {
+        self.fixture
+    }
+
+    /// Returns a description of the difference.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}: {}", self.fixture, self.message)
+    }
+}
+
+fn sorted(map: &EnvMap) -> Vec<(&str, &str)> {
+    let mut pairs: Vec<_> = map.iter().collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use super::*;
+    use crate::format::Dotenv;
+
+    #[test]
+    fn test_reference_passes() {
+        let failures: Vec<_> = run(&Dotenv::new()).iter().map(Failure::to_string).collect();
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[test]
+    fn test_fixtures_are_well_formed() {
+        for fixture in fixtures() {
+            if fixture.expected_json().trim() != "null" {
+                assert!(fixture.expected().is_some(), "{}", fixture.name());
+            }
+        }
+    }
+
+    struct Lines;
+
+    impl EnvFormat for Lines {
+        fn parse(&self, reader: &mut dyn Read) -> crate::Result<EnvMap> {
+            let mut input = String::new();
+            reader
+                .read_to_string(&mut input)
+                .map_err(crate::Error::Io)?;
+            Ok(input
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_naive_format_fails() {
+        let failures = run(&Lines);
+        let names: Vec<_> = failures.iter().map(Failure::fixture).collect();
+        assert!(names.contains(&"quotes"));
+        assert!(names.contains(&"invalid_key") || names.contains(&"unterminated_quote"));
+        assert!(failures[0].to_string().starts_with(failures[0].fixture()));
+    }
+}
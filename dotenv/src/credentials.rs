@@ -0,0 +1,359 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::apply;
+use crate::errors::*;
+use crate::format::is_valid_key;
+use crate::map::EnvMap;
+
+/// A loader for systemd credentials, as passed to a service with `LoadCredential=` or
+/// `SetCredential=`.
+///
+/// systemd places each credential in a file within the directory named by
+/// `$CREDENTIALS_DIRECTORY`. The file name is used as the variable name and the file contents
+/// as its value, exactly as written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::SystemdCredentials;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // With `LoadCredential=DATABASE_PASSWORD:/etc/myapp/db.pass` in the unit file
+/// SystemdCredentials::new().load()?;
+/// dotenvy::dotenv()?;
+/// let password = std::env::var("DATABASE_PASSWORD")?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemdCredentials {
+    dir: Option<PathBuf>,
+}
+
+impl SystemdCredentials {
+    /// Creates a loader for the directory named by `$CREDENTIALS_DIRECTORY`.
+    pub fn new() -> Self {
+        SystemdCredentials { dir: None }
+    }
+
+    /// Creates a loader for the credentials in `dir`, ignoring `$CREDENTIALS_DIRECTORY`.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Self {
+        SystemdCredentials {
+            dir: Some(dir.as_ref().to_owned()),
+        }
+    }
+
+    /// Returns the directory which will be read.
+    ///
+    /// An error is returned if no directory was given and `$CREDENTIALS_DIRECTORY` is not set.
+    pub fn resolve_dir(&self) -> Result<PathBuf> {
+        match &self.dir {
+            Some(dir) => Ok(dir.clone()),
+            None => env::var_os("CREDENTIALS_DIRECTORY")
+                .map(PathBuf::from)
+                .ok_or_else(|| Error::VarNotFound {
+                    key: "CREDENTIALS_DIRECTORY".to_owned(),
+                    suggestions: Vec::new(),
+                }),
+        }
+    }
+
+    /// Reads the credentials without modifying the environment.
+    ///
+    /// Credentials are returned sorted by name. An error is returned if a file name is not a
+    /// valid variable name or a file is not valid UTF-8 or contains a NUL character, which the
+    /// environment cannot hold.
+    pub fn parse(&self) -> Result<EnvMap> {
+        read_key_per_file(&self.resolve_dir()?, "credentials")
+    }
+
+    /// Loads the credentials into the environment, preserving any existing environment
+    /// variables of the same name.
+    ///
+    /// Returns all credentials read from the directory.
+    pub fn load(&self) -> Result<EnvMap> {
+        let map = self.parse()?;
+        apply::apply_missing(&map);
+        Ok(map)
+    }
+
+    /// Loads the credentials into the environment, overriding any existing environment
+    /// variables of the same name.
+    ///
+    /// Returns all credentials read from the directory.
+    pub fn load_override(&self) -> Result<EnvMap> {
+        let map = self.parse()?;
+        apply::apply_all(&map);
+        Ok(map)
+    }
+}
+
+/// A loader for a directory of secrets with one file per variable, such as the `/run/secrets`
+/// directory of Docker secrets or a mounted Kubernetes `Secret` volume.
+///
+/// Each regular file becomes a variable named after the file, optionally with a prefix. The
+/// file contents are trimmed of surrounding whitespace, since secret files are often written
+/// with a trailing newline. Hidden files are skipped, such as the `..data` links Kubernetes
+/// creates.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::SecretsDir;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // `/run/secrets/DATABASE_PASSWORD` is loaded as `MYAPP_DATABASE_PASSWORD`
+/// SecretsDir::new().prefix("MYAPP_").load()?;
+/// dotenvy::dotenv()?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretsDir {
+    dir: PathBuf,
+    prefix: String,
+    trim: bool,
+    required: bool,
+}
+
+impl SecretsDir {
+    /// Creates a loader for `/run/secrets`.
+    pub fn new() -> Self {
+        SecretsDir::from_dir("/run/secrets")
+    }
+
+    /// Creates a loader for the secrets in `dir`.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Self {
+        SecretsDir {
+            dir: dir.as_ref().to_owned(),
+            prefix: String::new(),
+            trim: true,
+            required: false,
+        }
+    }
+
+    /// Sets a prefix added to every variable name.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets whether whitespace is trimmed from the file contents. Defaults to `true`.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Sets whether a missing directory is an error. Defaults to `false`, which reads no
+    /// variables when the directory does not exist.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Returns the directory which will be read.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reads the secrets without modifying the environment.
+    ///
+    /// Secrets are returned sorted by name. An error is returned if a file name with the prefix
+    /// is not a valid variable name, or a file is not valid UTF-8 or contains a NUL character,
+    /// which the environment cannot hold.
+    pub fn parse(&self) -> Result<EnvMap> {
+        let files = match read_key_per_file(&self.dir, "secrets") {
+            Err(err) if err.not_found() && !self.required => return Ok(EnvMap::new()),
+            result => result?,
+        };
+        let mut map = EnvMap::new();
+        for (name, contents) in files.iter() {
+            let key = format!("{}{}", self.prefix, name);
+            if !is_valid_key(&key) {
+                return Err(Error::InvalidFormat {
+                    format: "secrets",
+                    source: format!("{} is not a valid variable name", key).into(),
+                });
+            }
+            let value = if self.trim { contents.trim() } else { contents };
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+
+    /// Loads the secrets into the environment, preserving any existing environment variables
+    /// of the same name.
+    ///
+    /// Returns all secrets read from the directory.
+    pub fn load(&self) -> Result<EnvMap> {
+        let map = self.parse()?;
+        apply::apply_missing(&map);
+        Ok(map)
+    }
+
+    /// Loads the secrets into the environment, overriding any existing environment variables
+    /// of the same name.
+    ///
+    /// Returns all secrets read from the directory.
+    pub fn load_override(&self) -> Result<EnvMap> {
+        let map = self.parse()?;
+        apply::apply_all(&map);
+        Ok(map)
+    }
+}
+
+impl Default for SecretsDir {
+    fn default() -> Self {
+        SecretsDir::new()
+    }
+}
+
+/// Reads every regular file in `dir` into a map from file name to contents, sorted by name.
+///
+/// Hidden files and subdirectories are skipped. `format` names the source in errors.
+pub(crate) fn read_key_per_file(dir: &Path, format: &'static str) -> Result<EnvMap> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(Error::Io)? {
+        let path = entry.map_err(Error::Io)?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_owned(),
+            None => {
+                return Err(Error::InvalidFormat {
+                    format,
+                    source: format!("{} is not a valid variable name", path.display()).into(),
+                })
+            }
+        };
+        // `fs::metadata` follows symlinks, which Kubernetes uses for mounted files
+        if name.starts_with('.') || !fs::metadata(&path).map_err(Error::Io)?.is_file() {
+            continue;
+        }
+        if !is_valid_key(&name) {
+            return Err(Error::InvalidFormat {
+                format,
+                source: format!("{} is not a valid variable name", name).into(),
+            });
+        }
+        files.push((name, path));
+    }
+    files.sort();
+
+    let mut map = EnvMap::new();
+    for (name, path) in files {
+        let contents = fs::read(&path).map_err(Error::Io)?;
+        let value = String::from_utf8(contents).map_err(|_| Error::InvalidFormat {
+            format,
+            source: format!("{} is not valid UTF-8", path.display()).into(),
+        })?;
+        if value.contains('\0') {
+            return Err(Error::InvalidFormat {
+                format,
+                source: format!("{} contains a NUL character", path.display()).into(),
+            });
+        }
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("TOKEN"), "secret\n").unwrap();
+        fs::write(dir.path().join("DB.PASSWORD"), "hunter2").unwrap();
+        fs::write(dir.path().join(".hidden"), "x").unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+
+        let map = SystemdCredentials::from_dir(dir.path()).parse().unwrap();
+        let pairs: Vec<_> = map.iter().collect();
+        assert_eq!(pairs, [("DB.PASSWORD", "hunter2"), ("TOKEN", "secret\n")]);
+    }
+
+    #[test]
+    fn test_invalid_credential_name() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("db-password"), "x").unwrap();
+        let err = SystemdCredentials::from_dir(dir.path())
+            .parse()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error parsing credentials input: db-password is not a valid variable name"
+        );
+    }
+
+    #[test]
+    fn test_load_credentials() {
+        env::set_var("DOTENVY_CREDENTIALS_TEST_EXISTING", "old");
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("DOTENVY_CREDENTIALS_TEST_EXISTING"), "new").unwrap();
+        fs::write(dir.path().join("DOTENVY_CREDENTIALS_TEST_NEW"), "new").unwrap();
+
+        SystemdCredentials::from_dir(dir.path()).load().unwrap();
+        assert_eq!(
+            env::var("DOTENVY_CREDENTIALS_TEST_EXISTING").unwrap(),
+            "old"
+        );
+        assert_eq!(env::var("DOTENVY_CREDENTIALS_TEST_NEW").unwrap(), "new");
+    }
+
+    #[test]
+    fn test_read_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("db_password"), "  hunter2\n").unwrap();
+        fs::create_dir(dir.path().join("..2024_01_01")).unwrap();
+        fs::write(dir.path().join("..data"), "x").unwrap();
+
+        let secrets = SecretsDir::from_dir(dir.path()).prefix("APP_");
+        let map = secrets.parse().unwrap();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [("APP_db_password", "hunter2")]
+        );
+
+        let map = secrets.trim(false).parse().unwrap();
+        assert_eq!(map.get("APP_db_password"), Some("  hunter2\n"));
+    }
+
+    #[test]
+    fn test_invalid_secret_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("TOKEN"), "x").unwrap();
+        let err = SecretsDir::from_dir(dir.path())
+            .prefix("A=")
+            .load()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error parsing secrets input: A=TOKEN is not a valid variable name"
+        );
+    }
+
+    #[test]
+    fn test_nul_in_value() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("DOTENVY_CREDENTIALS_TEST_NUL"), b"a\0b").unwrap();
+        let err = SystemdCredentials::from_dir(dir.path()).load().unwrap_err();
+        assert!(
+            err.to_string().ends_with("contains a NUL character"),
+            "{}",
+            err
+        );
+        assert!(SecretsDir::from_dir(dir.path()).load().is_err());
+        assert!(env::var_os("DOTENVY_CREDENTIALS_TEST_NUL").is_none());
+    }
+
+    #[test]
+    fn test_missing_secrets_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = SecretsDir::from_dir(dir.path().join("missing"));
+        assert!(secrets.parse().unwrap().is_empty());
+        assert!(secrets.required(true).parse().unwrap_err().not_found());
+    }
+}
@@ -0,0 +1,176 @@
+use std::io;
+use std::str;
+
+use crate::errors::*;
+
+/// How an [`EnvLoader`](crate::EnvLoader) decodes files which are not valid UTF-8, set with
+/// [`EnvLoader::decoding`](crate::EnvLoader::decoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoding {
+    /// Fails with an IO error of kind [`InvalidData`](io::ErrorKind::InvalidData). This is the
+    /// default.
+    Strict,
+    /// Replaces invalid sequences with U+FFFD, the replacement character, and reports the file
+    /// through [`EnvLoader::on_invalid_utf8`](crate::EnvLoader::on_invalid_utf8).
+    Lossy,
+    /// Passes invalid sequences in values through to the environment unchanged, as an
+    /// `OsString`.
+    ///
+    /// Maps returned by the loader hold the values as with [`Lossy`](Decoding::Lossy), while
+    /// loading sets the raw values. Files containing the characters U+10FF80 to U+10FFFF,
+    /// which stand in for raw bytes while parsing, are rejected.
+    #[cfg(unix)]
+    Raw,
+}
+
+/// The first character standing in for a raw byte. Byte `b` maps to `RAW_BASE + b`, and only
+/// bytes from 0x80 can be invalid.
+#[cfg(unix)]
+const RAW_BASE: u32 = 0x10FF00;
+
+/// The text of a file, and whether it contained invalid UTF-8.
+#[derive(Debug)]
+pub(crate) struct Decoded {
+    pub(crate) text: String,
+    pub(crate) invalid: bool,
+}
+
+/// Decodes `bytes` according to `decoding`.
+pub(crate) fn decode(bytes: Vec<u8>, decoding: Decoding) -> Result<Decoded> {
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => {
+            #[cfg(unix)]
+            if decoding == Decoding::Raw && text.chars().any(is_raw_char) {
+                return Err(invalid_data("contains characters reserved for raw bytes"));
+            }
+            return Ok(Decoded {
+                text,
+                invalid: false,
+            });
+        }
+        Err(err) => err.into_bytes(),
+    };
+
+    let text = match decoding {
+        Decoding::Strict => return Err(invalid_data("stream did not contain valid UTF-8")),
+        Decoding::Lossy => String::from_utf8_lossy(&bytes).into_owned(),
+        #[cfg(unix)]
+        Decoding::Raw => {
+            let mut text = String::with_capacity(bytes.len());
+            let mut rest = &bytes[..];
+            loop {
+                match str::from_utf8(rest) {
+                    Ok(valid) => {
+                        push_checked(&mut text, valid)?;
+                        break;
+                    }
+                    Err(err) => {
+                        let (valid, invalid) = rest.split_at(err.valid_up_to());
+                        // `valid_up_to` bytes are known to be valid
+                        push_checked(&mut text, str::from_utf8(valid).unwrap_or_default())?;
+                        let len = err.error_len().unwrap_or(invalid.len());
+                        text.extend(invalid[..len].iter().filter_map(|&b| raw_char(b)));
+                        rest = &invalid[len..];
+                    }
+                }
+            }
+            text
+        }
+    };
+    Ok(Decoded {
+        text,
+        invalid: true,
+    })
+}
+
+#[cfg(unix)]
+fn push_checked(text: &mut String, valid: &str) -> Result<()> {
+    if valid.chars().any(is_raw_char) {
+        return Err(invalid_data("contains characters reserved for raw bytes"));
+    }
+    text.push_str(valid);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn raw_char(byte: u8) -> Option<char> {
+    std::char::from_u32(RAW_BASE + u32::from(byte))
+}
+
+#[cfg(unix)]
+fn is_raw_char(c: char) -> bool {
+    (RAW_BASE + 0x80..=RAW_BASE + 0xFF).contains(&u32::from(c))
+}
+
+/// Returns the raw bytes of a value decoded with [`Decoding::Raw`], if it has any.
+#[cfg(unix)]
+pub(crate) fn raw_value(value: &str) -> Option<std::ffi::OsString> {
+    use std::os::unix::ffi::OsStringExt;
+
+    if !value.chars().any(is_raw_char) {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(value.len());
+    for c in value.chars() {
+        if is_raw_char(c) {
+            bytes.push((u32::from(c) - RAW_BASE) as u8);
+        } else {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+    Some(std::ffi::OsString::from_vec(bytes))
+}
+
+/// Replaces the characters standing in for raw bytes with U+FFFD.
+#[cfg(unix)]
+pub(crate) fn lossy_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if is_raw_char(c) { '\u{FFFD}' } else { c })
+        .collect()
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_valid() {
+        for &decoding in &[Decoding::Strict, Decoding::Lossy] {
+            let decoded = decode(b"A=\xc3\xa9\n".to_vec(), decoding).unwrap();
+            assert_eq!(decoded.text, "A=\u{e9}\n");
+            assert!(!decoded.invalid);
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let err = decode(b"A=\xff\n".to_vec(), Decoding::Strict).unwrap_err();
+        assert!(matches!(err, Error::Io(err) if err.kind() == io::ErrorKind::InvalidData));
+
+        let decoded = decode(b"A=\xff\n".to_vec(), Decoding::Lossy).unwrap();
+        assert_eq!(decoded.text, "A=\u{FFFD}\n");
+        assert!(decoded.invalid);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_decode_raw() {
+        let decoded = decode(b"A=caf\xe9 \xc3\xa9\xff\n".to_vec(), Decoding::Raw).unwrap();
+        assert!(decoded.invalid);
+        let value = decoded.text.trim_end().trim_start_matches("A=");
+        assert_eq!(
+            std::os::unix::ffi::OsStringExt::into_vec(raw_value(value).unwrap()),
+            b"caf\xe9 \xc3\xa9\xff"
+        );
+        assert_eq!(lossy_value(value), "caf\u{FFFD} \u{e9}\u{FFFD}");
+        assert_eq!(raw_value("plain"), None);
+
+        assert!(decode("A=\u{10FFFF}".as_bytes().to_vec(), Decoding::Raw).is_err());
+    }
+}
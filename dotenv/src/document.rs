@@ -0,0 +1,903 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::errors::*;
+use crate::format::{double_quoted, invalid_dotenv, is_valid_key, quote, single_quoted, unquoted};
+use crate::iter::{eval_end_state, ParseState};
+use crate::parse;
+use crate::syntax::{BareKeys, Syntax};
+
+/// A *.env* file which keeps its comments, blank lines and formatting.
+///
+/// Unlike [`Iter`](crate::Iter), which only yields the parsed variables, a `Document` can be
+/// written back out with [`to_string`](ToString::to_string). Lines which have not been
+/// modified are reproduced exactly as they were read.
+///
+/// The environment is never read: variable substitutions are kept as written, and bare keys
+/// allowed by [`Syntax::bare_keys`] stay bare entries.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::Document;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = "# the port\nPORT =  8080  \nHOST=\"localhost\" # local\n";
+/// let mut document = Document::parse(input)?;
+/// assert_eq!(document.get("PORT").map(|entry| entry.value()), Some("8080"));
+/// assert_eq!(document.to_string(), input);
+///
+/// document.normalize();
+/// assert_eq!(document.to_string(), "# the port\nPORT=8080\nHOST=localhost # local\n");
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    syntax: Syntax,
+    bom: bool,
+    lines: Vec<RawLine>,
+}
+
+/// A line of a [`Document`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    /// An empty or whitespace-only line.
+    Blank,
+    /// A comment filling the whole line, including the comment character.
+    Comment(String),
+    /// A variable declaration, which may span several lines if its value does.
+    Entry(Entry),
+}
+
+/// A variable declaration in a [`Document`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    export: bool,
+    bare: bool,
+    key: String,
+    value: String,
+    raw_value: String,
+    comment: Option<String>,
+    annotations: Vec<Annotation>,
+}
+
+/// Structured metadata in a comment, such as `# @required` or `# @type: u16`.
+///
+/// An annotation applies to the next declaration, when its comment is on one of the lines
+/// directly above it, or to the declaration on the same line for a trailing comment. A
+/// blank line ends the annotations collected so far. Each comment holds one annotation: a
+/// name prefixed with `@`, optionally followed by `:` or whitespace and a value.
+///
+/// Annotations are read by [`EnvSchema`](crate::EnvSchema) to turn an example file into a
+/// contract for the environment.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::Document;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let document = Document::parse("# The port to listen on\n# @type: u16\nPORT=8080 # @required\n")?;
+/// let port = document.get("PORT").unwrap();
+/// assert_eq!(port.annotation("type").and_then(|a| a.value()), Some("u16"));
+/// assert!(port.annotation("required").is_some());
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    name: String,
+    value: Option<String>,
+}
+
+/// How the value of an [`Entry`] is quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// The value is not quoted, or only parts of it are.
+    Unquoted,
+    /// The value is enclosed in single quotes.
+    Single,
+    /// The value is enclosed in double quotes.
+    Double,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RawLine {
+    line: Line,
+    /// The text the line was parsed from, including its line terminator. `None` once the
+    /// line has been modified.
+    original: Option<String>,
+    /// The byte offset of an entry's raw value in `original`.
+    value_start: usize,
+}
+
+impl Document {
+    /// Parses a document with the default [`Syntax`].
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::parse_with_syntax(input, Syntax::default())
+    }
+
+    /// Parses a document with a custom [`Syntax`].
+    pub fn parse_with_syntax(input: &str, syntax: Syntax) -> Result<Self> {
+        let (bom, input) = match input.strip_prefix('\u{feff}') {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+
+        let mut lines = Vec::new();
+        let mut annotations = Vec::new();
+        let mut text = String::new();
+        let mut state = ParseState::Complete;
+        for physical in input.split_inclusive('\n') {
+            let start = text.len();
+            text.push_str(physical);
+            if start == 0 && syntax.starts_with_comment(physical.trim_start()) {
+                let raw = RawLine::parse(std::mem::take(&mut text), None, &syntax)?;
+                push_line(&mut lines, &mut annotations, raw, &syntax);
+                continue;
+            }
+
+            let (pos, end_state) = eval_end_state(state, physical, &syntax);
+            state = match end_state {
+                ParseState::Complete => {
+                    let raw = RawLine::parse(std::mem::take(&mut text), None, &syntax)?;
+                    push_line(&mut lines, &mut annotations, raw, &syntax);
+                    ParseState::Complete
+                }
+                ParseState::Comment => {
+                    let comment = Some(start + pos);
+                    let raw = RawLine::parse(std::mem::take(&mut text), comment, &syntax)?;
+                    push_line(&mut lines, &mut annotations, raw, &syntax);
+                    ParseState::Complete
+                }
+                other => other,
+            };
+        }
+
+        if !text.is_empty() {
+            match state {
+                ParseState::Complete | ParseState::WhiteSpace | ParseState::Escape => {
+                    let raw = RawLine::parse(text, None, &syntax)?;
+                    push_line(&mut lines, &mut annotations, raw, &syntax);
+                }
+                _ => {
+                    let len = text.len();
+                    return Err(Error::LineParse(text, len));
+                }
+            }
+        }
+
+        Ok(Document { syntax, bom, lines })
+    }
+
+    /// Returns the dialect the document was parsed with.
+    pub fn syntax(&self) -> &Syntax {
+        &self.syntax
+    }
+
+    /// Returns an iterator over all lines.
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter().map(|raw| &raw.line)
+    }
+
+    /// Returns an iterator over all variable declarations.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// Returns the last declaration of `key`, which is the one a parsed map would contain.
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries().filter(|entry| entry.key == key).last()
+    }
+
+    /// Sets the value of `key`, leaving every other line untouched.
+    ///
+    /// The last declaration of `key` is updated in place, keeping its quote style unless the
+    /// new value cannot be written in it, as well as its spacing and trailing comment. If
+    /// `key` is not declared, a declaration is appended to the end of the document.
+    ///
+    /// An error is returned if `key` is not a valid variable name or the value cannot be
+    /// quoted in the document's [`Syntax`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::Document;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut document = Document::parse("# the port\nPORT = '8080' # http\n")?;
+    /// document.set("PORT", "9090")?;
+    /// document.set("HOST", "localhost")?;
+    /// assert_eq!(document.to_string(), "# the port\nPORT = '9090' # http\nHOST=localhost\n");
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let syntax = &self.syntax;
+        let existing = self.lines.iter_mut().rev().find(|raw| match &raw.line {
+            Line::Entry(entry) => entry.key == key,
+            _ => false,
+        });
+        if let Some(raw) = existing {
+            if let Line::Entry(entry) = &mut raw.line {
+                let old_len = entry.raw_value.len();
+                let was_bare = entry.bare;
+                entry.set_value(value, syntax)?;
+                if was_bare {
+                    // there is no value to replace, so the line is written anew
+                    raw.original = None;
+                } else if let Some(original) = &mut raw.original {
+                    let end = raw.value_start + old_len;
+                    let mut replacement = entry.raw_value.clone();
+                    // an empty value may be directly followed by its comment
+                    if old_len == 0 && syntax.starts_with_comment(&original[end..]) {
+                        replacement.push(' ');
+                    }
+                    original.replace_range(raw.value_start..end, &replacement);
+                }
+            }
+            return Ok(());
+        }
+
+        if !is_valid_key(key) {
+            return Err(invalid_dotenv(format!("`{}` is not a valid key", key)));
+        }
+        let mut entry = Entry {
+            export: false,
+            bare: false,
+            key: key.to_owned(),
+            value: String::new(),
+            raw_value: String::new(),
+            comment: None,
+            annotations: Vec::new(),
+        };
+        entry.set_value(value, syntax)?;
+        if let Some(original) = self.lines.last_mut().and_then(|raw| raw.original.as_mut()) {
+            if !original.ends_with('\n') {
+                original.push('\n');
+            }
+        }
+        self.lines.push(RawLine {
+            line: Line::Entry(entry),
+            original: None,
+            value_start: 0,
+        });
+        Ok(())
+    }
+
+    /// Rewrites every line in the canonical style.
+    ///
+    /// Indentation, trailing whitespace and spaces around `=` are removed, a single space is
+    /// placed before trailing comments, runs of blank lines are collapsed and values are
+    /// quoted as [`Dotenv`](crate::Dotenv) would write them. Values containing variable
+    /// substitutions keep their original quoting, since requoting could change their meaning.
+    /// Comments and the order of lines are preserved.
+    pub fn normalize(&mut self) {
+        let syntax = &self.syntax;
+        let mut lines: Vec<RawLine> = Vec::with_capacity(self.lines.len());
+        for mut raw in self.lines.drain(..) {
+            if raw.line == Line::Blank
+                && lines
+                    .last()
+                    .map_or(true, |previous| previous.line == Line::Blank)
+            {
+                continue;
+            }
+            if let Line::Entry(entry) = &mut raw.line {
+                entry.normalize(syntax);
+            }
+            raw.original = None;
+            lines.push(raw);
+        }
+        while lines.last().map_or(false, |raw| raw.line == Line::Blank) {
+            lines.pop();
+        }
+        self.lines = lines;
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.bom {
+            f.write_str("\u{feff}")?;
+        }
+        for raw in &self.lines {
+            match &raw.original {
+                Some(original) => f.write_str(original)?,
+                None => writeln!(f, "{}", raw.line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Line {
+    /// Writes the line in the canonical style, without a line terminator.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Blank => Ok(()),
+            Line::Comment(comment) => f.write_str(comment),
+            Line::Entry(entry) => {
+                if entry.export {
+                    f.write_str("export ")?;
+                }
+                if entry.bare {
+                    f.write_str(&entry.key)?;
+                } else {
+                    write!(f, "{}={}", entry.key, entry.raw_value)?;
+                }
+                if let Some(comment) = &entry.comment {
+                    write!(f, " {}", comment)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Adds a parsed line to `lines`, attaching any pending annotations to declarations.
+fn push_line(
+    lines: &mut Vec<RawLine>,
+    annotations: &mut Vec<Annotation>,
+    mut raw: RawLine,
+    syntax: &Syntax,
+) {
+    match &mut raw.line {
+        Line::Blank => annotations.clear(),
+        Line::Comment(comment) => annotations.extend(Annotation::parse(comment, syntax)),
+        Line::Entry(entry) => {
+            entry.annotations.append(annotations);
+            let trailing = entry
+                .comment
+                .as_deref()
+                .and_then(|comment| Annotation::parse(comment, syntax));
+            entry.annotations.extend(trailing);
+        }
+    }
+    lines.push(raw);
+}
+
+impl RawLine {
+    /// Parses a logical line. `comment` is the byte offset of a trailing comment.
+    fn parse(original: String, comment: Option<usize>, syntax: &Syntax) -> Result<Self> {
+        let content = original
+            .strip_suffix('\n')
+            .map(|content| content.strip_suffix('\r').unwrap_or(content))
+            .unwrap_or(&original);
+
+        let mut value_start = 0;
+        let line = if content.trim().is_empty() {
+            Line::Blank
+        } else if syntax.starts_with_comment(content.trim_start()) {
+            Line::Comment(content.trim().to_owned())
+        } else {
+            let (entry, start) = Entry::parse(content, comment, syntax)?;
+            value_start = start;
+            Line::Entry(entry)
+        };
+        Ok(RawLine {
+            line,
+            original: Some(original),
+            value_start,
+        })
+    }
+}
+
+impl Entry {
+    /// Parses a declaration, returning it with the byte offset of its raw value in `content`.
+    fn parse(content: &str, comment: Option<usize>, syntax: &Syntax) -> Result<(Self, usize)> {
+        if let Some(entry) = Entry::parse_bare(content, comment, syntax) {
+            return Ok((entry, content.len()));
+        }
+
+        // validate the line as the parser would, then read the value without substitutions
+        parse::check_line(content, syntax)?;
+        let literal_syntax = syntax
+            .clone()
+            .interpolation(false)
+            .percent_interpolation(false);
+        let (key, value) = parse::parse_line(content, &mut HashMap::new(), &literal_syntax)?
+            .ok_or_else(|| Error::LineParse(content.to_owned(), 0))?;
+
+        let (code, mut comment) = match comment {
+            Some(pos) => (&content[..pos], Some(content[pos..].trim_end().to_owned())),
+            None => (content, None),
+        };
+
+        let mut rest = code.trim_start();
+        let mut export = false;
+        if syntax.allow_export {
+            if let Some(after) = rest.strip_prefix("export") {
+                let trimmed = after.trim_start();
+                if trimmed.len() < after.len() && !trimmed.starts_with('=') {
+                    export = true;
+                    rest = trimmed;
+                }
+            }
+        }
+        let rest = rest[key.len()..].trim_start();
+        let value_part = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+        let mut raw_value = value_part.trim_end().to_owned();
+        let value_start = code.len() - value_part.len();
+
+        // a value starting with a comment character is empty: everything after is a comment
+        if syntax.starts_with_comment(&raw_value) {
+            comment = Some(content[value_start..].trim_end().to_owned());
+            raw_value.clear();
+        }
+
+        let entry = Entry {
+            export,
+            bare: false,
+            key,
+            value,
+            raw_value,
+            comment,
+            annotations: Vec::new(),
+        };
+        Ok((entry, value_start))
+    }
+
+    /// Parses a line with only a key name, if `syntax` allows them.
+    ///
+    /// The line is kept as is rather than inheriting the variable's value, so a document never
+    /// depends on or copies the environment.
+    fn parse_bare(content: &str, comment: Option<usize>, syntax: &Syntax) -> Option<Self> {
+        if syntax.bare_keys == BareKeys::Reject {
+            return None;
+        }
+        let code = comment.map_or(content, |pos| &content[..pos]);
+        let (code, comment) = match code.find(|c| syntax.is_comment(c)) {
+            Some(pos) => (&code[..pos], Some(content[pos..].trim_end().to_owned())),
+            None => (
+                code,
+                comment.map(|pos| content[pos..].trim_end().to_owned()),
+            ),
+        };
+        let code = code.trim();
+        let (export, key) = match code.strip_prefix("export") {
+            Some(after) if syntax.allow_export && after.starts_with(char::is_whitespace) => {
+                (true, after.trim_start())
+            }
+            _ => (false, code),
+        };
+        if !is_valid_key(key) {
+            return None;
+        }
+        Some(Entry {
+            export,
+            bare: true,
+            key: key.to_owned(),
+            value: String::new(),
+            raw_value: String::new(),
+            comment,
+            annotations: Vec::new(),
+        })
+    }
+
+    /// Returns the variable's name.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the variable's value, unquoted and unescaped.
+    ///
+    /// Variable substitutions such as `$HOME` are not expanded. The value of a bare key is
+    /// empty.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns `true` if the declaration is a key name without `=` or a value, which
+    /// [`BareKeys`] inherits from the environment.
+    pub fn is_bare(&self) -> bool {
+        self.bare
+    }
+
+    /// Returns the value exactly as written, including any quotes.
+    pub fn raw_value(&self) -> &str {
+        &self.raw_value
+    }
+
+    /// Returns how the value is quoted.
+    pub fn quote_style(&self) -> QuoteStyle {
+        if self.raw_value.starts_with('\'') {
+            QuoteStyle::Single
+        } else if self.raw_value.starts_with('"') {
+            QuoteStyle::Double
+        } else {
+            QuoteStyle::Unquoted
+        }
+    }
+
+    /// Returns `true` if the declaration is prefixed with `export`.
+    pub fn is_exported(&self) -> bool {
+        self.export
+    }
+
+    /// Returns the trailing comment, including the comment character.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Returns the annotations which apply to this declaration.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Returns the first annotation called `name`, without the `@`.
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations
+            .iter()
+            .find(|annotation| annotation.name == name)
+    }
+
+    /// Replaces the value, keeping its quote style where the new value allows it.
+    fn set_value(&mut self, value: &str, syntax: &Syntax) -> Result<()> {
+        let styled = match self.quote_style() {
+            QuoteStyle::Unquoted => unquoted(value, syntax),
+            QuoteStyle::Single => single_quoted(value, syntax),
+            QuoteStyle::Double => double_quoted(value, syntax),
+        };
+        self.raw_value = styled.or_else(|| quote(value, syntax)).ok_or_else(|| {
+            invalid_dotenv(format!("the value of `{}` cannot be written", self.key))
+        })?;
+        self.value = value.to_owned();
+        self.bare = false;
+        Ok(())
+    }
+
+    fn normalize(&mut self, syntax: &Syntax) {
+        if self.bare || has_substitution(&self.raw_value, syntax) {
+            return;
+        }
+        if let Some(quoted) = quote(&self.value, syntax) {
+            self.raw_value = quoted;
+        }
+    }
+}
+
+impl Annotation {
+    /// Creates an annotation. `name` is given without the `@`.
+    pub fn new(name: impl Into<String>, value: Option<String>) -> Self {
+        Annotation {
+            name: name.into(),
+            value,
+        }
+    }
+
+    /// Parses an annotation from a comment, including its comment character.
+    fn parse(comment: &str, syntax: &Syntax) -> Option<Self> {
+        let text = comment.trim_start_matches(|c| syntax.is_comment(c)).trim();
+        let text = text.strip_prefix('@')?;
+        let name_end = text
+            .find(|c: char| c.is_whitespace() || c == ':')
+            .unwrap_or(text.len());
+        let (name, rest) = text.split_at(name_end);
+        if name.is_empty() {
+            return None;
+        }
+        let rest = rest.trim_start();
+        let value = rest.strip_prefix(':').unwrap_or(rest).trim();
+        Some(Annotation::new(
+            name,
+            Some(value.to_owned()).filter(|value| !value.is_empty()),
+        ))
+    }
+
+    /// Returns the annotation's name, without the `@`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the annotation's value, if it has one.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+/// Returns `true` if parsing `raw_value` would substitute a variable.
+///
+/// With percent interpolation, any `%` outside single quotes counts, since `%%` is also read
+/// differently from the literal value.
+fn has_substitution(raw_value: &str, syntax: &Syntax) -> bool {
+    let mut strong_quote = false;
+    let mut weak_quote = false;
+    let mut escaped = false;
+    for c in raw_value.chars() {
+        if escaped {
+            escaped = false;
+        } else if strong_quote {
+            strong_quote = !syntax.is_single_quote(c);
+        } else if syntax.is_substitution(c) || syntax.is_percent_substitution(c) {
+            return true;
+        } else if weak_quote {
+            if syntax.is_double_quote(c) {
+                weak_quote = false;
+            } else {
+                escaped = syntax.is_escape(c);
+            }
+        } else if syntax.is_single_quote(c) {
+            strong_quote = true;
+        } else if syntax.is_double_quote(c) {
+            weak_quote = true;
+        } else {
+            escaped = syntax.is_escape(c);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(document: &Document, key: &str) -> Entry {
+        document.get(key).cloned().unwrap()
+    }
+
+    fn normalized(input: &str) -> String {
+        let mut document = Document::parse(input).unwrap();
+        document.normalize();
+        document.to_string()
+    }
+
+    #[test]
+    fn test_lossless() {
+        let input = "\u{feff}# header\r\n\n  export  A = 'one' # first\nB=\"multi\nline\"\n\tC=3";
+        assert_eq!(Document::parse(input).unwrap().to_string(), input);
+    }
+
+    #[test]
+    fn test_lines() {
+        let document = Document::parse("# header\n\nA=1\n").unwrap();
+        let lines: Vec<_> = document.lines().cloned().collect();
+        assert_eq!(lines[0], Line::Comment("# header".to_string()));
+        assert_eq!(lines[1], Line::Blank);
+        assert!(matches!(&lines[2], Line::Entry(entry) if entry.key() == "A"));
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn test_entries() {
+        let document =
+            Document::parse("export A = 'one' # first\nB=\"x\\ny\"\nC=$A\nD= # empty\nE=#x\n")
+                .unwrap();
+
+        let a = entry(&document, "A");
+        assert!(a.is_exported());
+        assert_eq!(a.value(), "one");
+        assert_eq!(a.raw_value(), "'one'");
+        assert_eq!(a.quote_style(), QuoteStyle::Single);
+        assert_eq!(a.comment(), Some("# first"));
+
+        let b = entry(&document, "B");
+        assert_eq!(b.value(), "x\ny");
+        assert_eq!(b.quote_style(), QuoteStyle::Double);
+
+        let c = entry(&document, "C");
+        assert_eq!(c.value(), "$A");
+        assert_eq!(c.quote_style(), QuoteStyle::Unquoted);
+
+        let d = entry(&document, "D");
+        assert_eq!(
+            (d.value(), d.raw_value(), d.comment()),
+            ("", "", Some("# empty"))
+        );
+
+        let e = entry(&document, "E");
+        assert_eq!(
+            (e.value(), e.raw_value(), e.comment()),
+            ("", "", Some("#x"))
+        );
+    }
+
+    #[test]
+    fn test_get_returns_last_declaration() {
+        let document = Document::parse("A=1\nA=2\n").unwrap();
+        assert_eq!(document.get("A").map(Entry::value), Some("2"));
+        assert!(document.get("B").is_none());
+    }
+
+    #[test]
+    fn test_export_as_key() {
+        let document = Document::parse("export=1\nexport = 2\n").unwrap();
+        let entries: Vec<_> = document.entries().collect();
+        assert!(entries
+            .iter()
+            .all(|entry| entry.key() == "export" && !entry.is_exported()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Document::parse("A B\n").is_err());
+        assert!(Document::parse("A=\"open\n").is_err());
+        assert!(Document::parse("A=${B\n").is_err());
+    }
+
+    #[test]
+    fn test_normalize_spacing() {
+        assert_eq!(
+            normalized("  A =  1  \nB=2#hash\nC=3   #   comment  \n"),
+            "A=1\nB='2#hash'\nC=3 #   comment\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_quoting() {
+        assert_eq!(
+            normalized("A=\"plain\"\nB=\"two words\"\nC='it'\"'\"'s'\nD=\"\"\nE='$HOME'\n"),
+            "A=plain\nB='two words'\nC=\"it's\"\nD=\nE='$HOME'\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_keeps_substitutions() {
+        assert_eq!(
+            normalized("A = \"$HOME/x\"\nB=\"\\$HOME\"\nC=${A}\n"),
+            "A=\"$HOME/x\"\nB='$HOME'\nC=${A}\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_keeps_percent_substitutions() {
+        let syntax = Syntax::new().percent_interpolation(true);
+        let input = "A=%PATH%\nB=\"%PATH%/x\"\nC=50%%\nD='%PATH%'\n";
+        let mut document = Document::parse_with_syntax(input, syntax.clone()).unwrap();
+        document.normalize();
+        assert_eq!(document.to_string(), input);
+
+        let expected: crate::Result<Vec<_>> =
+            crate::Iter::with_syntax(input.as_bytes(), syntax.clone()).collect();
+        let actual: crate::Result<Vec<_>> =
+            crate::Iter::with_syntax(document.to_string().as_bytes(), syntax).collect();
+        assert_eq!(expected.unwrap(), actual.unwrap());
+    }
+
+    #[test]
+    fn test_bare_keys_are_kept() {
+        for bare_keys in &[BareKeys::Inherit, BareKeys::Require] {
+            let syntax = Syntax::new().bare_keys(*bare_keys);
+            let input = "A=1\nDOTENVY_DOCUMENT_UNSET\n  PATH   # inherited\nexport HOME\n";
+            let mut document = Document::parse_with_syntax(input, syntax).unwrap();
+            assert_eq!(document.to_string(), input);
+            let path = entry(&document, "PATH");
+            assert!(path.is_bare());
+            assert_eq!(path.value(), "");
+            assert_eq!(path.comment(), Some("# inherited"));
+            assert!(entry(&document, "HOME").is_exported());
+
+            document.normalize();
+            assert_eq!(
+                document.to_string(),
+                "A=1\nDOTENVY_DOCUMENT_UNSET\nPATH # inherited\nexport HOME\n"
+            );
+            document.set("DOTENVY_DOCUMENT_UNSET", "x y").unwrap();
+            assert!(document
+                .to_string()
+                .contains("\nDOTENVY_DOCUMENT_UNSET='x y'\n"));
+        }
+        assert!(Document::parse("A=1\nPATH\n").is_err());
+    }
+
+    #[test]
+    fn test_normalize_blank_lines() {
+        assert_eq!(normalized("\n\n# a\n\n\n\nA=1\n  \n\n"), "# a\n\nA=1\n");
+    }
+
+    #[test]
+    fn test_normalize_multiline() {
+        assert_eq!(normalized("A=\"one\ntwo\"\n"), "A=\"one\\ntwo\"\n");
+    }
+
+    #[test]
+    fn test_normalize_preserves_values() {
+        let input = "export A='x y'\nB=\"a\\\\b\\\"c\"\nC=s\\ ix\nD=${A}-$B\nE=\"l1\nl2\" # c\n";
+        let mut document = Document::parse(input).unwrap();
+        document.normalize();
+        let expected: crate::Result<Vec<_>> = crate::Iter::new(input.as_bytes()).collect();
+        let actual: crate::Result<Vec<_>> =
+            crate::Iter::new(document.to_string().as_bytes()).collect();
+        assert_eq!(expected.unwrap(), actual.unwrap());
+    }
+
+    #[test]
+    fn test_annotations() {
+        let document = Document::parse(
+            "# @required\n# Some description\n#@type: u16\n# @ not one\nPORT=1 # @secret\n\
+             # @required\n\nHOST=x\n# @description The host name\nNAME=y\n",
+        )
+        .unwrap();
+
+        let port = entry(&document, "PORT");
+        assert_eq!(
+            port.annotations(),
+            &[
+                Annotation::new("required", None),
+                Annotation::new("type", Some("u16".to_string())),
+                Annotation::new("secret", None),
+            ]
+        );
+        assert_eq!(
+            port.annotation("type").and_then(Annotation::value),
+            Some("u16")
+        );
+        assert!(port.annotation("default").is_none());
+
+        assert!(entry(&document, "HOST").annotations().is_empty());
+        assert_eq!(
+            entry(&document, "NAME").annotations(),
+            &[Annotation::new(
+                "description",
+                Some("The host name".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_annotations_are_kept_as_comments() {
+        let input = "# @required\nA=1\n";
+        let mut document = Document::parse(input).unwrap();
+        document.normalize();
+        assert_eq!(document.to_string(), input);
+    }
+
+    #[test]
+    fn test_set_keeps_quote_style() {
+        let mut document = Document::parse("A = 1 # one\nB='2'\nC=\"3\"\n\nexport D=4\n").unwrap();
+        document.set("A", "10").unwrap();
+        document.set("B", "two words").unwrap();
+        document.set("C", "say \"hi\"").unwrap();
+        document.set("D", "$HOME").unwrap();
+        assert_eq!(
+            document.to_string(),
+            "A = 10 # one\nB='two words'\nC=\"say \\\"hi\\\"\"\n\nexport D='$HOME'\n"
+        );
+        assert_eq!(document.get("C").unwrap().value(), "say \"hi\"");
+    }
+
+    #[test]
+    fn test_set_changes_style_when_needed() {
+        let mut document = Document::parse("A='1'\nB=2\n").unwrap();
+        document.set("A", "it's").unwrap();
+        document.set("B", "a b").unwrap();
+        assert_eq!(document.to_string(), "A=\"it's\"\nB='a b'\n");
+    }
+
+    #[test]
+    fn test_set_updates_last_declaration() {
+        let mut document = Document::parse("A=1\nA=2\n").unwrap();
+        document.set("A", "3").unwrap();
+        assert_eq!(document.to_string(), "A=1\nA=3\n");
+    }
+
+    #[test]
+    fn test_set_empty_value() {
+        let mut document = Document::parse("A=# note\nB=\n").unwrap();
+        document.set("A", "1").unwrap();
+        document.set("B", "2").unwrap();
+        assert_eq!(document.to_string(), "A=1 # note\nB=2\n");
+    }
+
+    #[test]
+    fn test_set_appends() {
+        let mut document = Document::parse("A=1").unwrap();
+        document.set("B", "x y").unwrap();
+        assert_eq!(document.to_string(), "A=1\nB='x y'\n");
+
+        assert!(document.set("1B", "x").is_err());
+        assert_eq!(document.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_with_syntax() {
+        let syntax = Syntax::new().comment_chars(&[';']);
+        let mut document = Document::parse_with_syntax("; c\nA = '1' ; c\n", syntax).unwrap();
+        document.normalize();
+        assert_eq!(document.to_string(), "; c\nA=1 ; c\n");
+    }
+}
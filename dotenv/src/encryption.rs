@@ -0,0 +1,270 @@
+//! Public-key encryption of individual values.
+//!
+//! Values are sealed with [`crypto_box`], using X25519 and XSalsa20-Poly1305 as in libsodium's
+//! `crypto_box_seal`. Anyone with the public key can encrypt a value, and only the holder of
+//! the private key can decrypt it, so encrypted files can be committed while the private key
+//! stays in the deployment environment.
+
+use std::convert::TryInto;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crypto_box::aead::OsRng;
+
+use crate::errors::*;
+use crate::map::EnvMap;
+
+/// The prefix of an encrypted value, which is followed by the base64-encoded ciphertext.
+pub const ENCRYPTED_PREFIX: &str = "encrypted:";
+
+/// The environment variable read by [`EnvLoader`](crate::EnvLoader) for the private key when
+/// none is given to [`EnvLoader::private_key`](crate::EnvLoader::private_key).
+pub const PRIVATE_KEY_VAR: &str = "DOTENV_PRIVATE_KEY";
+
+/// A key for encrypting values, written as base64.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey(crypto_box::PublicKey);
+
+/// A key for decrypting values, written as base64.
+///
+/// The `Debug` implementation does not show the key.
+#[derive(Clone)]
+pub struct PrivateKey(crypto_box::SecretKey);
+
+impl PublicKey {
+    /// Encrypts `value`, returning it with the [`ENCRYPTED_PREFIX`].
+    pub fn encrypt(&self, value: &str) -> Result<String> {
+        let ciphertext = self
+            .0
+            .seal(&mut OsRng, value.as_bytes())
+            .map_err(|_| invalid_encrypted("the value could not be encrypted".to_owned()))?;
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            STANDARD.encode(ciphertext)
+        ))
+    }
+}
+
+impl PrivateKey {
+    /// Generates a new random key.
+    pub fn generate() -> Self {
+        PrivateKey(crypto_box::SecretKey::generate(&mut OsRng))
+    }
+
+    /// Returns the public key for encrypting values which this key can decrypt.
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(self.0.public_key())
+    }
+
+    /// Decrypts a value written by [`PublicKey::encrypt`].
+    ///
+    /// Values without the [`ENCRYPTED_PREFIX`] are returned unchanged.
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let encoded = match value.strip_prefix(ENCRYPTED_PREFIX) {
+            Some(encoded) => encoded,
+            None => return Ok(value.to_owned()),
+        };
+        let ciphertext = STANDARD
+            .decode(encoded)
+            .map_err(|err| invalid_encrypted(format!("invalid base64: {}", err)))?;
+        let plaintext = self.0.unseal(&ciphertext).map_err(|_| {
+            invalid_encrypted("the value could not be decrypted with this key".to_owned())
+        })?;
+        String::from_utf8(plaintext)
+            .map_err(|_| invalid_encrypted("the decrypted value is not valid UTF-8".to_owned()))
+    }
+
+    /// Reads the key from the [`PRIVATE_KEY_VAR`] environment variable, if it is set.
+    pub(crate) fn from_env() -> Result<Option<Self>> {
+        match env::var(PRIVATE_KEY_VAR) {
+            Ok(key) => key.parse().map(Some),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(err) => Err(Error::EnvVar(err)),
+        }
+    }
+}
+
+impl EnvMap {
+    /// Encrypts the value of `key` in place, so that the map can be written with the value
+    /// encrypted. Values which are already encrypted are left unchanged.
+    ///
+    /// Returns `false` if the map does not contain `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dotenvy::{EnvMap, PrivateKey};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let private_key = PrivateKey::generate();
+    /// let mut map = EnvMap::new();
+    /// map.insert("DATABASE_PASSWORD", "hunter2");
+    ///
+    /// map.encrypt_value("DATABASE_PASSWORD", &private_key.public_key())?;
+    /// let encrypted = map.get("DATABASE_PASSWORD").unwrap();
+    /// assert!(encrypted.starts_with("encrypted:"));
+    /// assert_eq!(private_key.decrypt(encrypted)?, "hunter2");
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn encrypt_value(&mut self, key: &str, public_key: &PublicKey) -> Result<bool> {
+        let value = match self.get(key) {
+            Some(value) if value.starts_with(ENCRYPTED_PREFIX) => return Ok(true),
+            Some(value) => public_key.encrypt(value)?,
+            None => return Ok(false),
+        };
+        self.insert(key, value);
+        Ok(true)
+    }
+
+    /// Decrypts every encrypted value in place, with `private_key` or else the key in the
+    /// [`PRIVATE_KEY_VAR`] environment variable.
+    ///
+    /// The environment variable is only read if there are encrypted values, so a malformed
+    /// key does not affect maps without any.
+    pub(crate) fn decrypt_values(&mut self, private_key: Option<&PrivateKey>) -> Result<()> {
+        let encrypted: Vec<_> = self
+            .iter()
+            .filter(|(_, value)| value.starts_with(ENCRYPTED_PREFIX))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+        if encrypted.is_empty() {
+            return Ok(());
+        }
+        let from_env;
+        let private_key = match private_key {
+            Some(private_key) => Some(private_key),
+            None => {
+                from_env = PrivateKey::from_env()?;
+                from_env.as_ref()
+            }
+        };
+        for (key, value) in encrypted {
+            let private_key = private_key.ok_or_else(|| {
+                invalid_encrypted(format!(
+                    "{} is encrypted, but no private key was given and {} is not set",
+                    key, PRIVATE_KEY_VAR
+                ))
+            })?;
+            let value = private_key
+                .decrypt(&value)
+                .map_err(|err| invalid_encrypted(format!("{}: {}", key, source_message(err))))?;
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+fn invalid_encrypted(message: String) -> Error {
+    Error::InvalidFormat {
+        format: "encrypted",
+        source: message.into(),
+    }
+}
+
+fn source_message(err: Error) -> String {
+    match err {
+        Error::InvalidFormat { source, .. } => source.to_string(),
+        err => err.to_string(),
+    }
+}
+
+fn decode_key(s: &str) -> Result<[u8; crypto_box::KEY_SIZE]> {
+    let bytes = STANDARD
+        .decode(s.trim())
+        .map_err(|err| invalid_encrypted(format!("invalid base64 key: {}", err)))?;
+    let len = bytes.len();
+    bytes.try_into().map_err(|_| {
+        invalid_encrypted(format!(
+            "a key is {} bytes, not {}",
+            crypto_box::KEY_SIZE,
+            len
+        ))
+    })
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        decode_key(s).map(|bytes| PublicKey(bytes.into()))
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        decode_key(s).map(|bytes| PrivateKey(bytes.into()))
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&STANDARD.encode(self.0.as_bytes()))
+    }
+}
+
+impl fmt::Display for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&STANDARD.encode(self.0.to_bytes()))
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PublicKey").field(&self.to_string()).finish()
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PrivateKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let private_key = PrivateKey::generate();
+        let public_key = private_key.public_key();
+        let encrypted = public_key.encrypt("s3cret value").unwrap();
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        assert_eq!(private_key.decrypt(&encrypted).unwrap(), "s3cret value");
+        assert_eq!(private_key.decrypt("plain").unwrap(), "plain");
+        assert!(PrivateKey::generate().decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_keys_from_str() {
+        let private_key = PrivateKey::generate();
+        let parsed: PrivateKey = private_key.to_string().parse().unwrap();
+        assert_eq!(parsed.public_key(), private_key.public_key());
+        let public_key: PublicKey = private_key.public_key().to_string().parse().unwrap();
+        assert_eq!(public_key, private_key.public_key());
+        assert!("c2hvcnQ=".parse::<PublicKey>().is_err());
+        assert_eq!(format!("{:?}", private_key), "PrivateKey(..)");
+    }
+
+    #[test]
+    fn test_decrypt_values() {
+        let private_key = PrivateKey::generate();
+        let mut map: EnvMap = vec![("A", "1"), ("B", "2")].into_iter().collect();
+        assert!(map.encrypt_value("B", &private_key.public_key()).unwrap());
+        assert!(!map.encrypt_value("C", &private_key.public_key()).unwrap());
+
+        let mut without_key = map.clone();
+        let err = without_key.decrypt_values(None).unwrap_err();
+        assert!(err.to_string().contains("B is encrypted"));
+
+        map.decrypt_values(Some(&private_key)).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("A", "1"), ("B", "2")]);
+    }
+}
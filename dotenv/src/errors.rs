@@ -2,6 +2,11 @@ use std::env;
 use std::error;
 use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::parse;
+use crate::schema::Violation;
+use crate::suggest;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -11,15 +16,195 @@ pub enum Error {
     LineParse(String, usize),
     Io(io::Error),
     EnvVar(env::VarError),
+    /// The input could not be parsed by an [`EnvFormat`](crate::EnvFormat).
+    InvalidFormat {
+        format: &'static str,
+        source: Box<dyn error::Error + Send + Sync>,
+    },
+    /// Variables did not satisfy an [`EnvSchema`](crate::EnvSchema).
+    Validation(Vec<Violation>),
+    /// The path exists, but is a directory, FIFO, device or other special file rather than a
+    /// regular file.
+    NotAFile {
+        path: PathBuf,
+    },
+    /// An environment variable requested with [`var`](crate::var) or a similar function is not
+    /// set. `suggestions` holds similarly named variables which are set, closest first.
+    VarNotFound {
+        key: String,
+        suggestions: Vec<String>,
+    },
+    /// Reading or parsing the file at `path` failed with `source`. `path` is absolute.
+    File {
+        path: PathBuf,
+        source: Box<Error>,
+    },
+}
+
+/// The common mistakes recognized in lines which fail to parse, returned by
+/// [`Error::parse_error_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A quote is opened but not closed, as in `KEY="value`.
+    UnbalancedQuote,
+    /// The key is not followed by `=`, as in `KEY value`.
+    MissingEquals,
+    /// An unquoted value contains whitespace, as in `KEY=two words`.
+    UnquotedWhitespace,
+    /// A `\` is followed by a character it does not escape, as in `KEY=C:\Users`.
+    InvalidEscape,
+    /// The key contains a character which is not allowed, as in `API-KEY=value`.
+    InvalidKey,
+}
+
+impl ParseErrorKind {
+    /// Returns a suggestion for fixing the mistake.
+    pub fn hint(self) -> &'static str {
+        match self {
+            ParseErrorKind::UnbalancedQuote => {
+                "a quote is not closed; add the closing quote, or escape quotes inside double quotes as `\\\"`"
+            }
+            ParseErrorKind::MissingEquals => {
+                "separate the key from its value with `=`, as in `KEY=value`"
+            }
+            ParseErrorKind::UnquotedWhitespace => {
+                "values containing whitespace must be quoted, as in `KEY=\"two words\"`"
+            }
+            ParseErrorKind::InvalidEscape => {
+                "only `\\n`, `\\\\`, `\\'`, `\\\"`, `\\$` and `\\ ` are escapes; use single quotes to keep backslashes as they are"
+            }
+            ParseErrorKind::InvalidKey => {
+                "keys may only contain letters, digits, `_` and `.`, and must start with a letter or `_`"
+            }
+        }
+    }
 }
 
 impl Error {
     pub fn not_found(&self) -> bool {
-        if let Error::Io(ref io_error) = *self {
-            return io_error.kind() == io::ErrorKind::NotFound;
+        match self {
+            Error::Io(io_error) => io_error.kind() == io::ErrorKind::NotFound,
+            Error::File { source, .. } => source.not_found(),
+            _ => false,
         }
-        false
     }
+
+    /// Returns the likely mistake behind an [`Error::LineParse`], if it is a common one.
+    pub fn parse_error_kind(&self) -> Option<ParseErrorKind> {
+        match self {
+            Error::LineParse(line, index) => parse::classify(line, *index),
+            Error::File { source, .. } => source.parse_error_kind(),
+            _ => None,
+        }
+    }
+
+    /// Returns a suggestion for fixing an [`Error::LineParse`], if its mistake is recognized.
+    ///
+    /// The suggestion is also included when the error is displayed, so that operators can fix
+    /// their *.env* files without reading the documentation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let err = dotenvy::from_read("GREETING=hello world\n".as_bytes()).unwrap_err();
+    /// assert_eq!(
+    ///     err.hint(),
+    ///     Some("values containing whitespace must be quoted, as in `KEY=\"two words\"`")
+    /// );
+    /// ```
+    pub fn hint(&self) -> Option<&'static str> {
+        self.parse_error_kind().map(ParseErrorKind::hint)
+    }
+
+    /// Returns the path of the file the error is about, if it is known.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::NotAFile { path } | Error::File { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns a value which displays the error with its path relative to `root`, such as the
+    /// directory a file was searched for from, and with `/` as the separator on every
+    /// platform.
+    ///
+    /// Paths outside of `root` are displayed in full, also with `/` as the separator.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// if let Err(err) = dotenvy::EnvLoader::new().load() {
+    ///     eprintln!("error: {}", err.display_relative(&std::env::current_dir()?));
+    /// }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn display_relative<'a>(&'a self, root: &'a Path) -> RelativeDisplay<'a> {
+        RelativeDisplay { error: self, root }
+    }
+
+    /// Wraps the error in [`Error::File`], as an error reading or parsing the file at `path`.
+    ///
+    /// Errors which already name their path are returned as they are. A relative `path` is
+    /// made absolute against the current directory.
+    pub(crate) fn in_file(self, path: &Path) -> Error {
+        match self {
+            Error::NotAFile { .. } | Error::File { .. } => self,
+            source => {
+                let path = match env::current_dir() {
+                    Ok(dir) if path.is_relative() => dir.join(path),
+                    _ => path.to_owned(),
+                };
+                Error::File {
+                    path,
+                    source: Box::new(source),
+                }
+            }
+        }
+    }
+
+    fn fmt_with(&self, fmt: &mut fmt::Formatter, root: Option<&Path>) -> fmt::Result {
+        let display = |path: &Path| match root {
+            Some(root) => portable(path.strip_prefix(root).unwrap_or(path)),
+            None => path.display().to_string(),
+        };
+        match self {
+            Error::NotAFile { path } => write!(fmt, "{} is not a regular file", display(path)),
+            Error::File { path, source } => {
+                write!(fmt, "{}: ", display(path))?;
+                source.fmt_with(fmt, root)
+            }
+            _ => fmt::Display::fmt(self, fmt),
+        }
+    }
+}
+
+/// Displays an [`Error`] with its path relative to a root, returned by
+/// [`Error::display_relative`].
+#[derive(Debug, Clone, Copy)]
+pub struct RelativeDisplay<'a> {
+    error: &'a Error,
+    root: &'a Path,
+}
+
+impl fmt::Display for RelativeDisplay<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt_with(fmt, Some(self.root))
+    }
+}
+
+/// Returns `path` with `/` as the separator.
+#[cfg(windows)]
+fn portable(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Returns `path` as is, since `\` is not a separator but may be part of a file name.
+#[cfg(not(windows))]
+fn portable(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
 }
 
 impl error::Error for Error {
@@ -27,6 +212,8 @@ impl error::Error for Error {
         match self {
             Error::Io(err) => Some(err),
             Error::EnvVar(err) => Some(err),
+            Error::InvalidFormat { source, .. } => Some(source.as_ref()),
+            Error::File { source, .. } => Some(source.as_ref()),
             _ => None,
         }
     }
@@ -37,11 +224,39 @@ impl fmt::Display for Error {
         match self {
             Error::Io(err) => write!(fmt, "{}", err),
             Error::EnvVar(err) => write!(fmt, "{}", err),
-            Error::LineParse(line, error_index) => write!(
-                fmt,
-                "Error parsing line: '{}', error at line index: {}",
-                line, error_index
-            ),
+            Error::LineParse(line, error_index) => {
+                write!(
+                    fmt,
+                    "Error parsing line: '{}', error at line index: {}",
+                    line, error_index
+                )?;
+                match self.hint() {
+                    Some(hint) => write!(fmt, "; hint: {}", hint),
+                    None => Ok(()),
+                }
+            }
+            Error::InvalidFormat { format, source } => {
+                write!(fmt, "Error parsing {} input: {}", format, source)
+            }
+            Error::Validation(violations) => {
+                write!(fmt, "Invalid environment: ")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, "; ")?;
+                    }
+                    write!(fmt, "{}", violation)?;
+                }
+                Ok(())
+            }
+            Error::NotAFile { .. } | Error::File { .. } => self.fmt_with(fmt, None),
+            Error::VarNotFound { key, suggestions } => {
+                write!(fmt, "environment variable `{}` not found", key)?;
+                if !suggestions.is_empty() {
+                    fmt.write_str("; ")?;
+                    suggest::write_suggestions(fmt, suggestions)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -78,6 +293,15 @@ mod test {
         assert!(err.source().is_none());
     }
 
+    #[test]
+    fn test_invalid_format_error_source() {
+        let err = Error::InvalidFormat {
+            format: "JSON",
+            source: "expected an object".into(),
+        };
+        assert_eq!("expected an object", err.source().unwrap().to_string());
+    }
+
     #[test]
     fn test_error_not_found_true() {
         let err = Error::Io(io::ErrorKind::NotFound.into());
@@ -110,6 +334,28 @@ mod test {
         assert_eq!(var_err_desc, err_desc);
     }
 
+    #[test]
+    fn test_var_not_found_error_display() {
+        let err = Error::VarNotFound {
+            key: "DATABASE_URL".to_string(),
+            suggestions: Vec::new(),
+        };
+        assert!(err.source().is_none());
+        assert_eq!(
+            "environment variable `DATABASE_URL` not found",
+            format!("{}", err)
+        );
+
+        let err = Error::VarNotFound {
+            key: "DATBASE_URL".to_string(),
+            suggestions: vec!["DATABASE_URL".to_string(), "DATABASE_URLS".to_string()],
+        };
+        assert_eq!(
+            "environment variable `DATBASE_URL` not found; did you mean `DATABASE_URL` or `DATABASE_URLS`?",
+            format!("{}", err)
+        );
+    }
+
     #[test]
     fn test_lineparse_error_display() {
         let err = Error::LineParse("test line".to_string(), 2);
@@ -119,4 +365,101 @@ mod test {
             err_desc
         );
     }
+
+    #[test]
+    fn test_invalid_format_error_display() {
+        let err = Error::InvalidFormat {
+            format: "JSON",
+            source: "expected an object".into(),
+        };
+        let err_desc = format!("{}", err);
+        assert_eq!("Error parsing JSON input: expected an object", err_desc);
+    }
+
+    #[test]
+    fn test_file_error_display() {
+        let root = Path::new("/app");
+        let err = Error::File {
+            path: root.join("config").join(".env"),
+            source: Box::new(Error::LineParse("A=\"1".to_string(), 2)),
+        };
+        assert_eq!(err.path(), Some(Path::new("/app/config/.env")));
+        assert!(err.source().unwrap().downcast_ref::<Error>().is_some());
+        assert_eq!(
+            "/app/config/.env: Error parsing line: 'A=\"1', error at line index: 2; hint: a quote \
+             is not closed; add the closing quote, or escape quotes inside double quotes as `\\\"`",
+            format!("{}", err)
+        );
+        assert_eq!(
+            "config/.env: Error parsing line: 'A=\"1', error at line index: 2; hint: a quote is \
+             not closed; add the closing quote, or escape quotes inside double quotes as `\\\"`",
+            format!("{}", err.display_relative(root))
+        );
+        assert_eq!(
+            format!("{}", err),
+            format!("{}", err.display_relative(Path::new("/elsewhere")))
+        );
+
+        let err = Error::File {
+            path: root.join(".env"),
+            source: Box::new(Error::Io(io::ErrorKind::NotFound.into())),
+        };
+        assert!(err.not_found());
+
+        let err = Error::NotAFile {
+            path: root.join(".env"),
+        };
+        assert_eq!(
+            ".env is not a regular file",
+            format!("{}", err.display_relative(root))
+        );
+        assert!(Error::EnvVar(env::VarError::NotPresent).path().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_display_relative_keeps_backslashes_on_unix() {
+        let root = Path::new("/app");
+        let err = Error::NotAFile {
+            path: root.join("a\\b.env"),
+        };
+        assert_eq!(
+            "a\\b.env is not a regular file",
+            format!("{}", err.display_relative(root))
+        );
+    }
+
+    #[test]
+    fn test_in_file() {
+        let err = Error::LineParse("A=\"1".to_string(), 2).in_file(Path::new(".env"));
+        assert_eq!(
+            err.path(),
+            Some(env::current_dir().unwrap().join(".env").as_path())
+        );
+        assert!(err.parse_error_kind().is_some());
+
+        let path = Path::new("/app/.env");
+        let err = err.in_file(path);
+        assert_ne!(err.path(), Some(path));
+        let err = Error::NotAFile {
+            path: path.to_owned(),
+        };
+        assert!(matches!(
+            err.in_file(Path::new("/other")),
+            Error::NotAFile { .. }
+        ));
+    }
+
+    #[test]
+    fn test_validation_error_display() {
+        let schema = crate::EnvSchema::new()
+            .var(crate::VarSpec::new("A").required(true))
+            .var(crate::VarSpec::new("B").required(true));
+        let err = schema.validate(&crate::EnvMap::new()).unwrap_err();
+        assert!(err.source().is_none());
+        assert_eq!(
+            "Invalid environment: `A` is required but not set; `B` is required but not set",
+            format!("{}", err)
+        );
+    }
 }
@@ -0,0 +1,277 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use figment::value::{Dict, Map, Value};
+use figment::{Metadata, Profile, Provider};
+
+use crate::errors::*;
+use crate::filesystem;
+use crate::format::{Dotenv, EnvFormat};
+use crate::loader;
+use crate::map::EnvMap;
+use crate::syntax::Syntax;
+
+/// A cascade of *.env* files as a [`figment::Provider`], for Rocket and other figment-based
+/// applications.
+///
+/// The base file provides the [default profile](Profile::Default). Each profile added with
+/// [`profile`](DotenvProvider::profile) is read from the base file's name with the profile
+/// appended, so the `debug` profile of `.env` comes from `.env.debug`. Missing files are
+/// skipped unless the provider is [`required`](DotenvProvider::required).
+///
+/// Keys are lowercased like `figment::providers::Env` does, and values are parsed into
+/// numbers, booleans and arrays the same way.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::DotenvProvider;
+/// use figment::Figment;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let figment = Figment::from(
+///     DotenvProvider::new()
+///         .prefix("ROCKET")
+///         .profile("debug")
+///         .profile("release"),
+/// )
+/// .select("release");
+/// let port: u16 = figment.extract_inner("port")?;
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DotenvProvider {
+    source: loader::Source,
+    syntax: Syntax,
+    profiles: Vec<String>,
+    required: bool,
+    prefix: Option<String>,
+    separator: Option<String>,
+}
+
+impl DotenvProvider {
+    /// Creates a provider for the *.env* file in the current directory or its parents.
+    pub fn new() -> Self {
+        DotenvProvider {
+            source: loader::Source::Filename(PathBuf::from(".env")),
+            syntax: Syntax::default(),
+            profiles: Vec::new(),
+            required: false,
+            prefix: None,
+            separator: None,
+        }
+    }
+
+    /// Searches for `filename` in the current directory and its parents.
+    pub fn filename<P: AsRef<Path>>(mut self, filename: P) -> Self {
+        self.source = loader::Source::Filename(filename.as_ref().to_owned());
+        self
+    }
+
+    /// Reads the file at `path`, without searching parent directories.
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.source = loader::Source::Path(path.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the dialect used to parse the files.
+    pub fn syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Reads the figment profile `name` from the base file's name followed by `.name`.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profiles.push(name.into());
+        self
+    }
+
+    /// Sets whether a missing base file is an error. Defaults to `false`, like figment's own
+    /// file providers. Profile files are always optional.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Only includes keys starting with `prefix` and a `_`, which are removed.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Splits keys on `separator` into nested dictionaries, so `DATABASES__MAIN__URL` becomes
+    /// `databases.main.url` with a separator of `__`.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = Some(separator.into());
+        self
+    }
+
+    /// Returns the source of the file for `profile`.
+    fn profile_source(&self, profile: &str) -> loader::Source {
+        let with_suffix = |path: &Path| {
+            let mut name = OsString::from(path.as_os_str());
+            name.push(".");
+            name.push(profile);
+            PathBuf::from(name)
+        };
+        match &self.source {
+            loader::Source::Filename(filename) => loader::Source::Filename(with_suffix(filename)),
+            loader::Source::Path(path) => loader::Source::Path(with_suffix(path)),
+        }
+    }
+
+    /// Parses the file at `source`, returning `None` if it does not exist.
+    fn read(&self, source: &loader::Source) -> Result<Option<EnvMap>> {
+        let path = match source.resolve() {
+            Ok(path) => path,
+            Err(err) if err.not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut file = match filesystem::open_file(&path) {
+            Ok(file) => file,
+            Err(err) if err.not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Dotenv::with_syntax(self.syntax.clone())
+            .parse(&mut file)
+            .map(Some)
+    }
+
+    fn to_dict(&self, map: EnvMap) -> Dict {
+        let mut dict = Dict::new();
+        for (key, value) in map {
+            let key = loader::config_key(&key, self.prefix.as_deref(), self.separator.as_deref());
+            if let Some(key) = key {
+                let path: Vec<_> = key.split('.').collect();
+                insert_nested(&mut dict, &path, value.parse().expect("infallible"));
+            }
+        }
+        dict
+    }
+}
+
+impl Default for DotenvProvider {
+    fn default() -> Self {
+        DotenvProvider::new()
+    }
+}
+
+impl Provider for DotenvProvider {
+    fn metadata(&self) -> Metadata {
+        let metadata = Metadata::named("dotenv file");
+        match self.source.resolve() {
+            Ok(path) => metadata.source(path.as_path()),
+            Err(_) => metadata,
+        }
+    }
+
+    fn data(&self) -> std::result::Result<Map<Profile, Dict>, figment::Error> {
+        let to_figment = |err: Error| figment::Error::from(err.to_string());
+
+        let mut data = Map::new();
+        match self.read(&self.source).map_err(to_figment)? {
+            Some(map) => {
+                data.insert(Profile::Default, self.to_dict(map));
+            }
+            None if self.required => {
+                let err = Error::Io(io::Error::new(io::ErrorKind::NotFound, "path not found"));
+                return Err(to_figment(err));
+            }
+            None => {}
+        }
+        for profile in &self.profiles {
+            let source = self.profile_source(profile);
+            if let Some(map) = self.read(&source).map_err(to_figment)? {
+                data.insert(Profile::new(profile), self.to_dict(map));
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Inserts `value` at the nested `path`, replacing any value which is not a dictionary.
+fn insert_nested(dict: &mut Dict, path: &[&str], value: Value) {
+    match path {
+        [] => {}
+        [key] => {
+            dict.insert((*key).to_owned(), value);
+        }
+        [key, rest @ ..] => {
+            let entry = dict
+                .entry((*key).to_owned())
+                .or_insert_with(|| Value::from(Dict::new()));
+            if entry.as_dict().is_none() {
+                *entry = Value::from(Dict::new());
+            }
+            if let Value::Dict(_, inner) = entry {
+                insert_nested(inner, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use figment::Figment;
+
+    use super::*;
+
+    fn provider(files: &[(&str, &str)]) -> (tempfile::TempDir, DotenvProvider) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, contents) in files {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+        let provider = DotenvProvider::new().path(dir.path().join(".env"));
+        (dir, provider)
+    }
+
+    #[test]
+    fn test_default_profile() {
+        let (_dir, provider) = provider(&[(".env", "PORT=8080\nDEBUG=true\nNAME='a b'\n")]);
+        let figment = Figment::from(provider);
+        assert_eq!(figment.extract_inner::<u16>("port").unwrap(), 8080);
+        assert!(figment.extract_inner::<bool>("debug").unwrap());
+        assert_eq!(figment.extract_inner::<String>("name").unwrap(), "a b");
+    }
+
+    #[test]
+    fn test_profiles() {
+        let (_dir, provider) = provider(&[
+            (".env", "PORT=8080\nNAME=demo\n"),
+            (".env.release", "PORT=80\n"),
+        ]);
+        let provider = provider.profile("release").profile("staging");
+        let data = provider.data().unwrap();
+        assert_eq!(data.len(), 2);
+
+        let figment = Figment::from(provider).select("release");
+        assert_eq!(figment.extract_inner::<u16>("port").unwrap(), 80);
+        assert_eq!(figment.extract_inner::<String>("name").unwrap(), "demo");
+    }
+
+    #[test]
+    fn test_prefix_and_separator() {
+        let (_dir, provider) = provider(&[(".env", "APP_DB__URL=x\nAPP_DB__POOL=4\nOTHER=1\n")]);
+        let figment = Figment::from(provider.prefix("APP").separator("__"));
+        assert_eq!(figment.extract_inner::<String>("db.url").unwrap(), "x");
+        assert_eq!(figment.extract_inner::<u8>("db.pool").unwrap(), 4);
+        assert!(figment.find_value("other").is_err());
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let (_dir, provider) = provider(&[]);
+        assert!(provider.data().unwrap().is_empty());
+        assert!(provider.required(true).data().is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let (_dir, provider) = provider(&[(".env", "A B\n")]);
+        assert!(provider.data().is_err());
+    }
+}
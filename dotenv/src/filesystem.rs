@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::{env, fmt};
+
+use crate::errors::*;
+
+/// The file operations used to find and read environment files.
+///
+/// [`EnvLoader`](crate::EnvLoader) uses [`StdFileSystem`] by default. Another implementation
+/// can be plugged in with [`EnvLoader::file_system`](crate::EnvLoader::file_system), so tests,
+/// fuzzing harnesses and sandboxed targets can exercise file discovery without touching the
+/// real disk. [`MemoryFileSystem`] is provided for this.
+pub trait FileSystem {
+    /// Returns the directory in which the search for a file starts.
+    fn current_dir(&self) -> io::Result<PathBuf>;
+
+    /// Returns `true` if `path` is a regular file, or `false` if it does not exist or is
+    /// something else.
+    fn is_file(&self, path: &Path) -> io::Result<bool>;
+
+    /// Opens the file at `path` for reading.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>>;
+
+    /// Returns `true` if anything exists at `path`, including directories and special files.
+    ///
+    /// This is only used to report [`Error::NotAFile`] instead of a missing file. The default
+    /// implementation returns [`is_file`](FileSystem::is_file).
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        self.is_file(path)
+    }
+}
+
+/// Returns [`Error::NotAFile`] if something other than a regular file exists at `path`.
+///
+/// Check this before opening, since opening a FIFO blocks until it has a writer.
+pub(crate) fn check_is_file(fs: &dyn FileSystem, path: &Path) -> Result<()> {
+    if !fs.is_file(path).map_err(Error::Io)? && fs.exists(path).map_err(Error::Io)? {
+        return Err(Error::NotAFile {
+            path: path.to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Opens the regular file at `path`. See [`check_is_file`].
+pub(crate) fn open_file(path: &Path) -> Result<File> {
+    check_is_file(&StdFileSystem, path)?;
+    File::open(path).map_err(Error::Io)
+}
+
+/// The operating system's file system, through [`std::fs`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        env::current_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> io::Result<bool> {
+        match fs::metadata(path) {
+            Ok(metadata) => Ok(metadata.is_file()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        match fs::metadata(path) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// A file system held in memory.
+///
+/// Paths are compared as given, without normalization, so files should be added with the
+/// same absolute paths that the search will produce.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvLoader, MemoryFileSystem};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let fs = MemoryFileSystem::new("/app/src")
+///     .file("/app/.env", "PORT=8080");
+/// let map = EnvLoader::new().file_system(fs).parse()?;
+/// assert_eq!(map.get("PORT"), Some("8080"));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct MemoryFileSystem {
+    current_dir: PathBuf,
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileSystem {
+    /// Creates an empty file system whose current directory is `current_dir`.
+    pub fn new<P: AsRef<Path>>(current_dir: P) -> Self {
+        MemoryFileSystem {
+            current_dir: current_dir.as_ref().to_owned(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Adds a file, replacing any file at the same path.
+    pub fn file<P: AsRef<Path>>(mut self, path: P, contents: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.as_ref().to_owned(), contents.into());
+        self
+    }
+}
+
+impl fmt::Debug for MemoryFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut paths: Vec<_> = self.files.keys().collect();
+        paths.sort();
+        f.debug_struct("MemoryFileSystem")
+            .field("current_dir", &self.current_dir)
+            .field("files", &paths)
+            .finish()
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        Ok(self.current_dir.clone())
+    }
+
+    fn is_file(&self, path: &Path) -> io::Result<bool> {
+        Ok(self.files.contains_key(path))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Box::new(Cursor::new(contents.clone()))),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "file not found")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_file_system() {
+        let fs = MemoryFileSystem::new("/app").file("/app/.env", "A=1");
+        assert_eq!(fs.current_dir().unwrap(), Path::new("/app"));
+        assert!(fs.is_file(Path::new("/app/.env")).unwrap());
+        assert!(!fs.is_file(Path::new("/.env")).unwrap());
+
+        let mut contents = String::new();
+        fs.open(Path::new("/app/.env"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "A=1");
+        assert_eq!(
+            fs.open(Path::new("/.env")).err().map(|err| err.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_std_file_system_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!StdFileSystem.is_file(&dir.path().join("missing")).unwrap());
+        assert!(!StdFileSystem.is_file(dir.path()).unwrap());
+        assert!(StdFileSystem.exists(dir.path()).unwrap());
+        assert!(!StdFileSystem.exists(&dir.path().join("missing")).unwrap());
+    }
+
+    #[test]
+    fn test_open_file_rejects_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        match open_file(dir.path()) {
+            Err(Error::NotAFile { path }) => assert_eq!(path, dir.path()),
+            other => panic!("expected NotAFile, got {:?}", other.map(|_| ())),
+        }
+        assert!(open_file(&dir.path().join("missing"))
+            .unwrap_err()
+            .not_found());
+    }
+}
@@ -1,8 +1,9 @@
 use std::fs::File;
 use std::path::{Path, PathBuf};
-use std::{env, fs, io};
+use std::{env, io};
 
 use crate::errors::*;
+use crate::filesystem::{open_file, FileSystem, StdFileSystem};
 use crate::iter::Iter;
 
 pub struct Finder<'a> {
@@ -23,7 +24,7 @@ impl<'a> Finder<'a> {
 
     pub fn find(self) -> Result<(PathBuf, Iter<File>)> {
         let path = find(&env::current_dir().map_err(Error::Io)?, self.filename)?;
-        let file = File::open(&path).map_err(Error::Io)?;
+        let file = open_file(&path).map_err(|err| err.in_file(&path))?;
         let iter = Iter::new(file);
         Ok((path, iter))
     }
@@ -31,27 +32,30 @@ impl<'a> Finder<'a> {
 
 /// Searches for `filename` in `directory` and parent directories until found or root is reached.
 pub fn find(directory: &Path, filename: &Path) -> Result<PathBuf> {
-    let candidate = directory.join(filename);
+    find_in(&StdFileSystem, directory, filename)
+}
 
-    match fs::metadata(&candidate) {
-        Ok(metadata) => {
-            if metadata.is_file() {
-                return Ok(candidate);
-            }
+/// Searches for `filename` like [`find`], in the given file system.
+///
+/// Directories and special files named `filename` are skipped, so a directory such as a
+/// Python virtual environment named `.env` does not stop the search.
+pub fn find_in(fs: &dyn FileSystem, directory: &Path, filename: &Path) -> Result<PathBuf> {
+    let mut directory = directory;
+    loop {
+        let candidate = directory.join(filename);
+
+        if fs.is_file(&candidate).map_err(Error::Io)? {
+            return Ok(candidate);
         }
-        Err(error) => {
-            if error.kind() != io::ErrorKind::NotFound {
-                return Err(Error::Io(error));
-            }
+
+        match directory.parent() {
+            Some(parent) => directory = parent,
+            None => break,
         }
     }
 
-    if let Some(parent) = directory.parent() {
-        find(parent, filename)
-    } else {
-        Err(Error::Io(io::Error::new(
-            io::ErrorKind::NotFound,
-            "path not found",
-        )))
-    }
+    Err(Error::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        "path not found",
+    )))
 }
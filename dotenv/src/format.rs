@@ -0,0 +1,571 @@
+#[cfg(feature = "json")]
+mod json;
+mod properties;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use crate::errors::*;
+use crate::iter::Iter;
+use crate::map::EnvMap;
+use crate::parse;
+use crate::syntax::Syntax;
+
+#[cfg(feature = "json")]
+pub use self::json::Json;
+pub use self::properties::Properties;
+#[cfg(feature = "toml")]
+pub use self::toml::Toml;
+#[cfg(feature = "yaml")]
+pub use self::yaml::Yaml;
+
+/// A file format which can be parsed into environment variables.
+///
+/// Implement this trait to load variables from formats other than *.env* files with
+/// [`EnvLoader`](crate::EnvLoader). Formats which can also be written implement
+/// [`write_entry`](EnvFormat::write_entry).
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::{EnvFormat, EnvLoader, EnvMap};
+/// use std::io::{BufRead, BufReader, Read};
+///
+/// /// One `KEY value` pair per line.
+/// struct SpaceSeparated;
+///
+/// impl EnvFormat for SpaceSeparated {
+///     fn parse(&self, reader: &mut dyn Read) -> dotenvy::Result<EnvMap> {
+///         let mut map = EnvMap::new();
+///         for line in BufReader::new(reader).lines() {
+///             let line = line.map_err(dotenvy::Error::Io)?;
+///             if let Some((key, value)) = line.split_once(' ') {
+///                 map.insert(key, value);
+///             }
+///         }
+///         Ok(map)
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = EnvLoader::new()
+///     .filename("vars.txt")
+///     .format(SpaceSeparated)
+///     .parse()?;
+/// println!("loaded {} variables", map.len());
+/// #     Ok(())
+/// # }
+/// ```
+pub trait EnvFormat {
+    /// Parses all variables from `reader`.
+    fn parse(&self, reader: &mut dyn Read) -> Result<EnvMap>;
+
+    /// Writes a single variable to `writer`, including its line terminator.
+    ///
+    /// The default implementation returns an error of kind
+    /// [`Unsupported`](io::ErrorKind::Unsupported), for formats which can only be read.
+    fn write_entry(&self, key: &str, value: &str, writer: &mut dyn Write) -> Result<()> {
+        let _ = (key, value, writer);
+        Err(Error::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this format cannot be written",
+        )))
+    }
+
+    /// Writes all variables in `map` to `writer`, in the map's order.
+    fn write(&self, map: &EnvMap, writer: &mut dyn Write) -> Result<()> {
+        for (key, value) in map.iter() {
+            self.write_entry(key, value, writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Joins the key of a nested value onto the key of its parent.
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+pub(crate) fn nested_key(prefix: &str, separator: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}{}{}", prefix, separator, key)
+    }
+}
+
+/// Appends `s` to `output` as a JSON string, escaped as [`Json`] writes the strings of arrays.
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub(crate) fn push_json_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            c if c < ' ' => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// The *.env* file format. This is the default [`EnvFormat`].
+///
+/// Where multiple declarations for the same environment variable exist, the *last one* is
+/// kept in the parsed map.
+///
+/// When writing, values are left unquoted where possible, single quoted where that needs
+/// no escapes, and double quoted with escapes otherwise, so that parsing the output
+/// yields the original values.
+///
+/// # Profiles
+///
+/// With [`profile`](Dotenv::profile), one file can hold the variables of several
+/// environments. Lines before the first `[profile.<name>]` header are always read, and the
+/// section of the selected profile is read after them, so that its values take precedence.
+///
+/// ```
+/// use dotenvy::{Dotenv, EnvFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = "\
+/// PORT=8080
+/// LOG_LEVEL=debug
+///
+/// [profile.production]
+/// LOG_LEVEL=warn
+/// ";
+/// let map = Dotenv::new().profile("production").parse(&mut input.as_bytes())?;
+/// assert_eq!(map.get("PORT"), Some("8080"));
+/// assert_eq!(map.get("LOG_LEVEL"), Some("warn"));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dotenv {
+    syntax: Syntax,
+    profile: Option<String>,
+}
+
+impl Dotenv {
+    /// Creates the format with the default [`Syntax`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the format with a custom [`Syntax`].
+    pub fn with_syntax(syntax: Syntax) -> Self {
+        Dotenv {
+            syntax,
+            profile: None,
+        }
+    }
+
+    /// Selects the `[profile.<name>]` section to read along with the lines before the first
+    /// section header. Other sections are skipped.
+    ///
+    /// Without a profile, section headers are parse errors.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Returns the dialect used when parsing.
+    pub fn syntax(&self) -> &Syntax {
+        &self.syntax
+    }
+}
+
+impl EnvFormat for Dotenv {
+    fn parse(&self, reader: &mut dyn Read) -> Result<EnvMap> {
+        let mut iter = Iter::with_syntax(reader, self.syntax.clone());
+        if let Some(profile) = &self.profile {
+            iter = iter.profile(profile.clone());
+        }
+        iter.remove_bom()?;
+        iter.collect()
+    }
+
+    fn write_entry(&self, key: &str, value: &str, writer: &mut dyn Write) -> Result<()> {
+        if !is_valid_key(key) {
+            return Err(invalid_dotenv(format!("`{}` is not a valid key", key)));
+        }
+        let value = quote(value, &self.syntax)
+            .ok_or_else(|| invalid_dotenv(format!("the value of `{}` cannot be written", key)))?;
+        writeln!(writer, "{}={}", key, value).map_err(Error::Io)
+    }
+}
+
+pub(crate) fn invalid_dotenv(message: String) -> Error {
+    Error::InvalidFormat {
+        format: "dotenv",
+        source: message.into(),
+    }
+}
+
+pub(crate) fn is_valid_key(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Returns `true` if the process environment can hold `key` set to `value`.
+///
+/// Setting a variable with an empty key, with `=` or NUL in its key, or with NUL in its value
+/// would panic.
+pub(crate) fn is_settable(key: &str, value: &str) -> bool {
+    is_settable_key(key) && !value.contains('\0')
+}
+
+fn is_settable_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(|c| c == '=' || c == '\0')
+}
+
+/// Checks that the process environment can hold every variable `format` parsed into `map`.
+pub(crate) fn check_settable(format: &'static str, map: &EnvMap) -> Result<()> {
+    for (key, value) in map.iter() {
+        let message = if !is_settable_key(key) {
+            format!("`{}` is not a valid variable name", key.escape_debug())
+        } else if value.contains('\0') {
+            format!("the value of `{}` contains a NUL character", key)
+        } else {
+            continue;
+        };
+        return Err(Error::InvalidFormat {
+            format,
+            source: message.into(),
+        });
+    }
+    Ok(())
+}
+
+/// Quotes `value` so that `syntax` parses it back unchanged, or returns `None` if it cannot.
+pub(crate) fn quote(value: &str, syntax: &Syntax) -> Option<String> {
+    unquoted(value, syntax)
+        .or_else(|| single_quoted(value, syntax))
+        .or_else(|| double_quoted(value, syntax))
+}
+
+/// Returns `value` as is if it needs no quotes.
+pub(crate) fn unquoted(value: &str, syntax: &Syntax) -> Option<String> {
+    let is_plain = |c: char| {
+        (c.is_ascii_alphanumeric() || "_-./:@%+,=~".contains(c))
+            && !syntax.is_comment(c)
+            && !syntax.is_percent_substitution(c)
+    };
+    if value.chars().all(is_plain) {
+        Some(value.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Wraps `value` in single quotes, if it can be written without escapes.
+pub(crate) fn single_quoted(value: &str, syntax: &Syntax) -> Option<String> {
+    // strong quotes hold anything except the quote itself, a backslash which would escape
+    // the closing quote while reading lines, and line breaks which we keep on one line
+    if syntax.single_quotes && !value.contains(|c| c == '\'' || c == '\n' || syntax.is_escape(c)) {
+        Some(format!("'{}'", value))
+    } else {
+        None
+    }
+}
+
+/// Wraps `value` in double quotes, escaping it as needed.
+pub(crate) fn double_quoted(value: &str, syntax: &Syntax) -> Option<String> {
+    if !syntax.double_quotes {
+        return None;
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' if syntax.escapes => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '$' if syntax.escapes && syntax.interpolation => quoted.push_str("\\$"),
+            // `%%` is a literal `%` even within double quotes
+            '%' if syntax.percent_interpolation => quoted.push_str("%%"),
+            '\n' if syntax.escapes => quoted.push_str("\\n"),
+            '"' | '\\' | '\n' => return None,
+            '$' if syntax.interpolation => return None,
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Some(quoted)
+}
+
+/// Escapes `value` so that it can be written after the `=` of a declaration, such as
+/// `KEY=<escaped>`, and read back unchanged by this crate's parser.
+///
+/// The value is written as is when it only contains characters which need no quotes, in single
+/// quotes when it contains no quotes, backslashes or line breaks, and in double quotes
+/// otherwise, with `"`, `\`, `$` and line breaks escaped. The result never spans several lines.
+/// Values are handled as sequences of `char`s, independently of the locale.
+///
+/// This is for the default [`Syntax`]; use [`escape_value_with_syntax`] for files read with
+/// another one, such as one with [`percent_interpolation`](Syntax::percent_interpolation).
+///
+/// [`unescape_value`] is its inverse.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(dotenvy::escape_value("plain"), "plain");
+/// assert_eq!(dotenvy::escape_value("two words"), "'two words'");
+/// assert_eq!(dotenvy::escape_value("it's $5\n"), r#""it's \$5\n""#);
+/// ```
+pub fn escape_value(value: &str) -> String {
+    // double quotes with escapes hold any value
+    quote(value, &Syntax::new()).expect("the default syntax quotes any value")
+}
+
+/// Escapes `value` like [`escape_value`], so that `syntax` reads it back unchanged, or returns
+/// `None` if `syntax` cannot write it, like double quotes without escapes.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::Syntax;
+///
+/// let syntax = Syntax::new().percent_interpolation(true);
+/// assert_eq!(dotenvy::escape_value("50%").as_str(), "50%");
+/// assert_eq!(dotenvy::escape_value_with_syntax("50%", &syntax).as_deref(), Some("'50%'"));
+/// ```
+pub fn escape_value_with_syntax(value: &str, syntax: &Syntax) -> Option<String> {
+    quote(value, syntax)
+}
+
+/// Unescapes `raw`, a value as written after the `=` of a declaration, as this crate's parser
+/// reads it.
+///
+/// Quotes are removed and escapes are resolved, but variables such as `$HOME` are not
+/// substituted: `$` is kept as is. The whole of `raw` must be the value, so trailing whitespace
+/// or comments are an error.
+///
+/// For any value, `unescape_value(&escape_value(value))` returns the value unchanged.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), dotenvy::Error> {
+/// assert_eq!(dotenvy::unescape_value("'two words'")?, "two words");
+/// assert_eq!(dotenvy::unescape_value(r#""line\none""#)?, "line\none");
+/// assert!(dotenvy::unescape_value("two words").is_err());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn unescape_value(raw: &str) -> Result<String> {
+    let syntax = Syntax::new().interpolation(false);
+    match parse::parse_value(raw, &mut HashMap::new(), &syntax)? {
+        (value, true) => Ok(value),
+        (_, false) => {
+            let end = raw.trim_end().len();
+            Err(Error::LineParse(raw.to_owned(), end))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dotenv_last_declaration_wins() {
+        let map = Dotenv::new()
+            .parse(&mut "A=1\nB=$A\nA=2\n".as_bytes())
+            .unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("A", "2"), ("B", "1")]);
+    }
+
+    #[cfg(all(feature = "json", feature = "toml", feature = "yaml"))]
+    #[test]
+    fn test_arrays_agree() {
+        let parse = |format: &dyn EnvFormat, input: &str| {
+            let map = format.parse(&mut input.as_bytes()).unwrap();
+            map.get("A").unwrap().to_owned()
+        };
+        let json = parse(
+            &Json::new(),
+            r#"{"A": ["x\"\\\u0001\t", 1, true, ["y"], {"k": "v"}]}"#,
+        );
+        assert_eq!(json, r#"["x\"\\\u0001\t",1,true,["y"],{"k":"v"}]"#);
+        assert_eq!(
+            parse(
+                &Toml::new(),
+                r#"A = ["x\"\\\u0001\t", 1, true, ["y"], { k = "v" }]"#
+            ),
+            json
+        );
+        assert_eq!(
+            parse(&Yaml::new(), r#"A: ["x\"\\\x01\t", 1, true, [y], {k: v}]"#),
+            json
+        );
+    }
+
+    #[test]
+    fn test_dotenv_removes_bom() {
+        let map = Dotenv::new().parse(&mut &b"\xEF\xBB\xBFA=1"[..]).unwrap();
+        assert_eq!(map.get("A"), Some("1"));
+    }
+
+    fn round_trip(format: &Dotenv, value: &str) -> String {
+        let mut output = Vec::new();
+        format.write_entry("KEY", value, &mut output).unwrap();
+        let map = format.parse(&mut output.as_slice()).unwrap();
+        assert_eq!(map.get("KEY"), Some(value));
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_dotenv_write_quoting() {
+        let format = Dotenv::new();
+        assert_eq!(round_trip(&format, ""), "KEY=\n");
+        assert_eq!(
+            round_trip(&format, "postgres://u@h:5432/db"),
+            "KEY=postgres://u@h:5432/db\n"
+        );
+        assert_eq!(round_trip(&format, "two words"), "KEY='two words'\n");
+        assert_eq!(round_trip(&format, "$HOME #no"), "KEY='$HOME #no'\n");
+        assert_eq!(round_trip(&format, "it's"), "KEY=\"it's\"\n");
+        assert_eq!(
+            round_trip(&format, "a\\b \"$c\"\nd"),
+            "KEY=\"a\\\\b \\\"\\$c\\\"\\nd\"\n"
+        );
+    }
+
+    #[test]
+    fn test_dotenv_write_with_syntax() {
+        let format = Dotenv::with_syntax(Syntax::new().single_quotes(false).escapes(false));
+        assert_eq!(round_trip(&format, "a b"), "KEY=\"a b\"\n");
+        let format = Dotenv::with_syntax(Syntax::new().interpolation(false).escapes(false));
+        assert_eq!(round_trip(&format, "it's $5"), "KEY=\"it's $5\"\n");
+
+        let mut output = Vec::new();
+        assert!(format.write_entry("KEY", "\"'", &mut output).is_err());
+    }
+
+    #[test]
+    fn test_dotenv_write_percent_interpolation() {
+        let format = Dotenv::with_syntax(Syntax::new().percent_interpolation(true));
+        assert_eq!(round_trip(&format, "%PATH%"), "KEY='%PATH%'\n");
+        assert_eq!(round_trip(&format, "50%"), "KEY='50%'\n");
+        assert_eq!(
+            round_trip(&format, "it's %PATH%"),
+            "KEY=\"it's %%PATH%%\"\n"
+        );
+        assert_eq!(round_trip(&format, "%%"), "KEY='%%'\n");
+        assert_eq!(round_trip(&format, "'%%'"), "KEY=\"'%%%%'\"\n");
+
+        let syntax = Syntax::new().percent_interpolation(true);
+        for value in generated_values(1000) {
+            let escaped = escape_value_with_syntax(&value, &syntax).unwrap();
+            let input = format!("KEY={}\n", escaped);
+            if value.contains('\0') {
+                // the environment cannot hold such a value
+                assert!(format.parse(&mut input.as_bytes()).is_err(), "{:?}", input);
+                continue;
+            }
+            let map = format.parse(&mut input.as_bytes()).unwrap();
+            assert_eq!(map.get("KEY"), Some(value.as_str()), "{:?}", input);
+        }
+    }
+
+    /// Returns `count` values made of characters the escaping must care about, generated from
+    /// a fixed seed so failures can be reproduced.
+    fn generated_values(count: usize) -> Vec<String> {
+        const ALPHABET: &[char] = &[
+            'a', 'Z', '0', '_', ' ', '\t', '\n', '\r', '\\', '\'', '"', '$', '{', '}', '(', '#',
+            '%', '=', '`', '!', '\0', '\u{feff}', 'é', 'ß', 'İ', '日', '🦀',
+        ];
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..count)
+            .map(|_| {
+                let len = (next() % 12) as usize;
+                (0..len)
+                    .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_escape_round_trip() {
+        for value in generated_values(5000) {
+            let escaped = escape_value(&value);
+            assert!(!escaped.contains('\n'), "{:?} spans lines", escaped);
+            assert_eq!(unescape_value(&escaped).unwrap(), value, "{:?}", escaped);
+
+            // the parser reads it back the same way, with the substitutions it performs
+            let input = format!("KEY={}\nNEXT=1\n", escaped);
+            if value.contains('\0') {
+                // the environment cannot hold such a value
+                assert!(
+                    Dotenv::new().parse(&mut input.as_bytes()).is_err(),
+                    "{:?}",
+                    input
+                );
+                continue;
+            }
+            let map = Dotenv::new().parse(&mut input.as_bytes()).unwrap();
+            assert_eq!(map.get("KEY"), Some(value.as_str()), "{:?}", input);
+            assert_eq!(map.get("NEXT"), Some("1"), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_unescape_value() {
+        assert_eq!(unescape_value("").unwrap(), "");
+        assert_eq!(unescape_value("a'b c'\\ d").unwrap(), "ab c d");
+        assert_eq!(unescape_value("\"$HOME\"").unwrap(), "$HOME");
+        assert_eq!(unescape_value("'\\n'").unwrap(), "\\n");
+        assert_eq!(unescape_value("\"a\\\"b\\$c\"").unwrap(), "a\"b$c");
+        for invalid in &["'open", "\"open", "a b", "a ", "a #comment", "\"\\q\""] {
+            assert!(unescape_value(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_dotenv_write_invalid_key() {
+        let mut output = Vec::new();
+        assert!(Dotenv::new().write_entry("1KEY", "1", &mut output).is_err());
+        assert!(Dotenv::new().write_entry("A B", "1", &mut output).is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_dotenv_write_map() {
+        let map: EnvMap = vec![("B", "2"), ("A", "1")].into_iter().collect();
+        let mut output = Vec::new();
+        Dotenv::new().write(&map, &mut output).unwrap();
+        assert_eq!(output, b"B=2\nA=1\n");
+    }
+
+    #[test]
+    fn test_dotenv_with_syntax() {
+        let format = Dotenv::with_syntax(Syntax::new().interpolation(false));
+        let map = format.parse(&mut "A=1\nB=$A\n".as_bytes()).unwrap();
+        assert_eq!(map.get("B"), Some("$A"));
+    }
+
+    #[test]
+    fn test_dotenv_error() {
+        assert!(Dotenv::new().parse(&mut "A B".as_bytes()).is_err());
+    }
+}
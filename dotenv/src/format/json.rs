@@ -0,0 +1,151 @@
+use std::io::Read;
+
+use serde_json::Value;
+
+use crate::errors::*;
+use crate::format::{check_settable, nested_key, EnvFormat};
+use crate::map::EnvMap;
+
+/// A flat JSON object, such as `{"KEY": "value"}`.
+///
+/// Nested objects are flattened by joining their keys with a separator, which defaults to
+/// `_`, so `{"DB": {"HOST": "localhost"}}` yields `DB_HOST=localhost`. Numbers and booleans
+/// are converted to their textual form, `null` becomes an empty string and arrays are kept
+/// as compact JSON.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvFormat, Json};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = r#"{"DB": {"HOST": "localhost", "PORT": 5432}}"#;
+/// let map = Json::new().separator("__").parse(&mut input.as_bytes())?;
+/// assert_eq!(map.get("DB__PORT"), Some("5432"));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json {
+    separator: String,
+}
+
+impl Json {
+    /// Creates the format, flattening nested objects with `_`.
+    pub fn new() -> Self {
+        Json {
+            separator: String::from("_"),
+        }
+    }
+
+    /// Sets the separator used to join the keys of nested objects.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    fn flatten(&self, prefix: &str, value: Value, map: &mut EnvMap) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    self.flatten(&nested_key(prefix, &self.separator, &key), value, map);
+                }
+            }
+            Value::String(s) => {
+                map.insert(prefix, s);
+            }
+            Value::Null => {
+                map.insert(prefix, String::new());
+            }
+            other => {
+                map.insert(prefix, other.to_string());
+            }
+        }
+    }
+}
+
+impl Default for Json {
+    fn default() -> Self {
+        Json::new()
+    }
+}
+
+impl EnvFormat for Json {
+    fn parse(&self, reader: &mut dyn Read) -> Result<EnvMap> {
+        let value: Value = serde_json::from_reader(reader).map_err(|err| Error::InvalidFormat {
+            format: "JSON",
+            source: Box::new(err),
+        })?;
+        if !value.is_object() {
+            return Err(Error::InvalidFormat {
+                format: "JSON",
+                source: "expected an object at the top level".into(),
+            });
+        }
+        let mut map = EnvMap::new();
+        self.flatten("", value, &mut map);
+        check_settable("JSON", &map)?;
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(format: &Json, input: &str) -> Result<EnvMap> {
+        format.parse(&mut input.as_bytes())
+    }
+
+    #[test]
+    fn test_flat_object() {
+        let map = parse(&Json::new(), r#"{"B": "2", "A": "1"}"#).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("B", "2"), ("A", "1")]);
+    }
+
+    #[test]
+    fn test_scalars() {
+        let map = parse(
+            &Json::new(),
+            r#"{"NUM": 8080, "FLAG": true, "NONE": null, "LIST": [1, "a"]}"#,
+        )
+        .unwrap();
+        assert_eq!(map.get("NUM"), Some("8080"));
+        assert_eq!(map.get("FLAG"), Some("true"));
+        assert_eq!(map.get("NONE"), Some(""));
+        assert_eq!(map.get("LIST"), Some(r#"[1,"a"]"#));
+    }
+
+    #[test]
+    fn test_nested_objects() {
+        let input = r#"{"APP": {"DB": {"HOST": "localhost"}, "NAME": "demo"}}"#;
+
+        let map = parse(&Json::new(), input).unwrap();
+        assert_eq!(map.get("APP_DB_HOST"), Some("localhost"));
+        assert_eq!(map.get("APP_NAME"), Some("demo"));
+
+        let map = parse(&Json::new().separator("."), input).unwrap();
+        assert_eq!(map.get("APP.DB.HOST"), Some("localhost"));
+    }
+
+    #[test]
+    fn test_not_an_object() {
+        let err = parse(&Json::new(), r#"["A", "B"]"#).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat { format: "JSON", .. }));
+    }
+
+    #[test]
+    fn test_unsettable_variables() {
+        for input in [r#"{"A=B": "1"}"#, r#"{"": "1"}"#, r#"{"K": "a\u0000b"}"#] {
+            let err = parse(&Json::new(), input).unwrap_err();
+            assert!(matches!(err, Error::InvalidFormat { format: "JSON", .. }));
+        }
+        assert!(parse(&Json::new().separator("="), r#"{"A": {"B": "1"}}"#).is_err());
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let err = parse(&Json::new(), r#"{"A": "#).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat { format: "JSON", .. }));
+    }
+}
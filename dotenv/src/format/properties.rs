@@ -0,0 +1,295 @@
+use std::char;
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::errors::*;
+use crate::format::{check_settable, EnvFormat};
+use crate::map::EnvMap;
+
+/// The Java `.properties` format.
+///
+/// Keys are separated from values by `=`, `:` or whitespace, lines ending in a backslash
+/// continue on the next line, `#` and `!` start comment lines, and both keys and values may
+/// contain `\t`, `\n`, `\r`, `\f` and `\uXXXX` escapes. Input is read as UTF-8.
+///
+/// When writing, special characters are escaped as `java.util.Properties::store` does,
+/// while non-ASCII characters are written as UTF-8 unless
+/// [`escape_unicode`](Properties::escape_unicode) is set.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvFormat, Properties};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = "# database\ndb.url = jdbc:postgresql://localhost/app\ngreeting: caf\\u00e9\n";
+/// let map = Properties::new().parse(&mut input.as_bytes())?;
+/// assert_eq!(map.get("db.url"), Some("jdbc:postgresql://localhost/app"));
+/// assert_eq!(map.get("greeting"), Some("café"));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    escape_unicode: bool,
+}
+
+impl Properties {
+    /// Creates the format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether non-ASCII characters are written as `\uXXXX` escapes, for consumers
+    /// which read files as ISO-8859-1. Defaults to `false`.
+    pub fn escape_unicode(mut self, escape: bool) -> Self {
+        self.escape_unicode = escape;
+        self
+    }
+
+    fn escape(&self, s: &str, is_key: bool, output: &mut String) {
+        for (index, c) in s.chars().enumerate() {
+            match c {
+                '\\' => output.push_str("\\\\"),
+                '\t' => output.push_str("\\t"),
+                '\n' => output.push_str("\\n"),
+                '\r' => output.push_str("\\r"),
+                '\x0c' => output.push_str("\\f"),
+                '=' | ':' | '#' | '!' => {
+                    output.push('\\');
+                    output.push(c);
+                }
+                ' ' if is_key || index == 0 => output.push_str("\\ "),
+                c if c < ' ' || c == '\x7f' || (self.escape_unicode && !c.is_ascii()) => {
+                    let mut units = [0; 2];
+                    for unit in c.encode_utf16(&mut units) {
+                        output.push_str(&format!("\\u{:04X}", unit));
+                    }
+                }
+                c => output.push(c),
+            }
+        }
+    }
+}
+
+impl EnvFormat for Properties {
+    fn parse(&self, reader: &mut dyn Read) -> Result<EnvMap> {
+        let mut map = EnvMap::new();
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next() {
+            let line = line.map_err(Error::Io)?;
+            let first = line.trim_start_matches(is_whitespace);
+            if first.is_empty() || first.starts_with('#') || first.starts_with('!') {
+                continue;
+            }
+
+            let mut logical = first.to_owned();
+            while ends_with_continuation(&logical) {
+                logical.pop();
+                match lines.next() {
+                    Some(next) => {
+                        let next = next.map_err(Error::Io)?;
+                        logical.push_str(next.trim_start_matches(is_whitespace));
+                    }
+                    None => break,
+                }
+            }
+
+            let (key, value) = parse_logical_line(&logical)?;
+            map.insert(key, value);
+        }
+        check_settable("properties", &map)?;
+        Ok(map)
+    }
+
+    fn write_entry(&self, key: &str, value: &str, writer: &mut dyn Write) -> Result<()> {
+        let mut line = String::with_capacity(key.len() + value.len() + 2);
+        self.escape(key, true, &mut line);
+        line.push('=');
+        self.escape(value, false, &mut line);
+        line.push('\n');
+        writer.write_all(line.as_bytes()).map_err(Error::Io)
+    }
+}
+
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\x0c'
+}
+
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+/// Splits a logical line into its unescaped key and value.
+fn parse_logical_line(line: &str) -> Result<(String, String)> {
+    let mut chars = line.char_indices().peekable();
+    let mut key_end = line.len();
+    while let Some((index, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == '=' || c == ':' || is_whitespace(c) {
+            key_end = index;
+            break;
+        }
+    }
+
+    let mut rest = line[key_end..].trim_start_matches(is_whitespace);
+    if rest.starts_with('=') || rest.starts_with(':') {
+        rest = rest[1..].trim_start_matches(is_whitespace);
+    }
+
+    Ok((unescape(&line[..key_end])?, unescape(rest)?))
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let invalid = || Error::LineParse(s.to_owned(), 0);
+    let mut output = String::with_capacity(s.len());
+    let mut units = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                if hex.len() != 4 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(invalid());
+                }
+                units.push(u16::from_str_radix(&hex, 16).map_err(|_| invalid())?);
+                // collect consecutive escapes so surrogate pairs decode together
+                let mut lookahead = chars.clone();
+                if lookahead.next() == Some('\\') && lookahead.next() == Some('u') {
+                    continue;
+                }
+                for decoded in char::decode_utf16(units.drain(..)) {
+                    output.push(decoded.map_err(|_| invalid())?);
+                }
+            }
+            Some('t') => output.push('\t'),
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('f') => output.push('\x0c'),
+            Some(other) => output.push(other),
+            // a trailing backslash at the end of the input is dropped, as Java does
+            None => {}
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(input: &str) -> Result<EnvMap> {
+        Properties::new().parse(&mut input.as_bytes())
+    }
+
+    fn write(format: &Properties, key: &str, value: &str) -> String {
+        let mut output = Vec::new();
+        format.write_entry(key, value, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_separators() {
+        let map = parse("a=1\nb = 2\nc:3\nd 4\ne\t:\t5\nf\n").unwrap();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [
+                ("a", "1"),
+                ("b", "2"),
+                ("c", "3"),
+                ("d", "4"),
+                ("e", "5"),
+                ("f", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let map = parse("# comment\n  ! also a comment\n\n   \na=1 # not a comment\n").unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("a", "1 # not a comment")]);
+    }
+
+    #[test]
+    fn test_continuation_lines() {
+        let map =
+            parse("fruits = apple, \\\n         banana\nescaped = a\\\\\nnext = b\n").unwrap();
+        assert_eq!(map.get("fruits"), Some("apple, banana"));
+        assert_eq!(map.get("escaped"), Some("a\\"));
+        assert_eq!(map.get("next"), Some("b"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        let map =
+            parse("key\\ with\\:odd\\!chars = tab\\there\\=\\nnewline\\u00e9\\uD83D\\uDE00\n")
+                .unwrap();
+        assert_eq!(
+            map.get("key with:odd!chars"),
+            Some("tab\there=\nnewline\u{e9}\u{1F600}")
+        );
+    }
+
+    #[test]
+    fn test_invalid_unicode_escape() {
+        assert!(parse("a=\\u12").is_err());
+        assert!(parse("a=\\uD83D").is_err());
+        assert!(parse("a=\\uDE00").is_err());
+    }
+
+    #[test]
+    fn test_unsettable_variables() {
+        for input in ["A\\=B=1\n", "=1\n", "K=a\\u0000b\n"] {
+            let err = parse(input).unwrap_err();
+            assert!(matches!(
+                err,
+                Error::InvalidFormat {
+                    format: "properties",
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_write_escapes() {
+        let format = Properties::new();
+        assert_eq!(write(&format, "db.url", "a=b:c"), "db.url=a\\=b\\:c\n");
+        assert_eq!(
+            write(&format, "my key", " lead trail "),
+            "my\\ key=\\ lead trail \n"
+        );
+        assert_eq!(
+            write(&format, "#k", "line\nbreak\\"),
+            "\\#k=line\\nbreak\\\\\n"
+        );
+        assert_eq!(write(&format, "k", "café"), "k=café\n");
+
+        let format = Properties::new().escape_unicode(true);
+        assert_eq!(
+            write(&format, "k", "café \u{1F600}"),
+            "k=caf\\u00E9 \\uD83D\\uDE00\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let map: EnvMap = vec![
+            ("plain", "value"),
+            ("spaced key", "  both ends  "),
+            ("special", "#!=:\\\t\r\n\x0c\x01"),
+            ("unicode", "café \u{1F600}"),
+        ]
+        .into_iter()
+        .collect();
+        for format in &[Properties::new(), Properties::new().escape_unicode(true)] {
+            let mut output = Vec::new();
+            format.write(&map, &mut output).unwrap();
+            assert_eq!(format.parse(&mut output.as_slice()).unwrap(), map);
+        }
+    }
+}
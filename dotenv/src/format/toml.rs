@@ -0,0 +1,275 @@
+use std::io::Read;
+
+use toml::Value;
+
+use crate::errors::*;
+use crate::format::{check_settable, nested_key, push_json_string, EnvFormat};
+use crate::map::EnvMap;
+
+/// A TOML document, or a named table within one such as Cargo's `[env]`.
+///
+/// Nested tables are flattened by joining their keys with a separator, which defaults to
+/// `_`. A table with a `value` key and otherwise only Cargo's `force` and `relative` keys, as in
+/// `KEY = { value = "...", force = true }`, yields that value. Numbers, booleans and datetimes are converted to their textual form,
+/// and arrays are kept as compact JSON, as with [`Json`](crate::Json).
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvFormat, Toml};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = r#"
+/// [package]
+/// name = "app"
+///
+/// [env]
+/// PORT = 8080
+/// LOG = { value = "debug", force = true }
+/// "#;
+/// let map = Toml::new().table("env").parse(&mut input.as_bytes())?;
+/// assert_eq!(map.get("PORT"), Some("8080"));
+/// assert_eq!(map.get("LOG"), Some("debug"));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toml {
+    table: Option<String>,
+    separator: String,
+}
+
+impl Toml {
+    /// Creates the format, reading the whole document and flattening nested tables with `_`.
+    pub fn new() -> Self {
+        Toml {
+            table: None,
+            separator: String::from("_"),
+        }
+    }
+
+    /// Reads only the named table. Nested tables are named with dots, e.g.
+    /// `package.metadata.env`.
+    pub fn table(mut self, table: impl Into<String>) -> Self {
+        self.table = Some(table.into());
+        self
+    }
+
+    /// Sets the separator used to join the keys of nested tables.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    fn flatten(&self, prefix: &str, value: Value, map: &mut EnvMap) -> Result<()> {
+        match value {
+            Value::Table(mut table) if !prefix.is_empty() && is_cargo_value(&table) => {
+                if let Some(value) = table.remove("value") {
+                    self.flatten(prefix, value, map)?;
+                }
+            }
+            Value::Table(table) => {
+                for (key, value) in table {
+                    self.flatten(&nested_key(prefix, &self.separator, &key), value, map)?;
+                }
+            }
+            Value::String(s) => {
+                map.insert(prefix, s);
+            }
+            Value::Integer(i) => {
+                map.insert(prefix, i.to_string());
+            }
+            Value::Float(f) => {
+                map.insert(prefix, f.to_string());
+            }
+            Value::Boolean(b) => {
+                map.insert(prefix, b.to_string());
+            }
+            Value::Datetime(datetime) => {
+                map.insert(prefix, datetime.to_string());
+            }
+            Value::Array(_) => {
+                let mut json = String::new();
+                to_json(&value, &mut json);
+                map.insert(prefix, json);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Toml {
+    fn default() -> Self {
+        Toml::new()
+    }
+}
+
+impl EnvFormat for Toml {
+    fn parse(&self, reader: &mut dyn Read) -> Result<EnvMap> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).map_err(Error::Io)?;
+        let mut value: Value = toml::from_str(&contents).map_err(|err| Error::InvalidFormat {
+            format: "TOML",
+            source: Box::new(err),
+        })?;
+
+        if let Some(path) = &self.table {
+            for name in path.split('.') {
+                value = match value {
+                    Value::Table(mut table) => table
+                        .remove(name)
+                        .ok_or_else(|| invalid(format!("table `{}` not found", path)))?,
+                    _ => return Err(invalid(format!("`{}` is not a table", path))),
+                };
+            }
+            if !value.is_table() {
+                return Err(invalid(format!("`{}` is not a table", path)));
+            }
+        }
+
+        let mut map = EnvMap::new();
+        self.flatten("", value, &mut map)?;
+        check_settable("TOML", &map)?;
+        Ok(map)
+    }
+}
+
+/// Appends `value` to `output` as compact JSON, with datetimes as strings.
+fn to_json(value: &Value, output: &mut String) {
+    match value {
+        Value::String(s) => push_json_string(output, s),
+        Value::Integer(i) => output.push_str(&i.to_string()),
+        // JSON has no infinities or NaN
+        Value::Float(f) if !f.is_finite() => output.push_str("null"),
+        Value::Float(f) => output.push_str(&f.to_string()),
+        Value::Boolean(b) => output.push_str(&b.to_string()),
+        Value::Datetime(datetime) => push_json_string(output, &datetime.to_string()),
+        Value::Array(array) => {
+            output.push('[');
+            for (i, value) in array.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                to_json(value, output);
+            }
+            output.push(']');
+        }
+        Value::Table(table) => {
+            output.push('{');
+            for (i, (key, value)) in table.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                push_json_string(output, key);
+                output.push(':');
+                to_json(value, output);
+            }
+            output.push('}');
+        }
+    }
+}
+
+/// Returns `true` if `table` is a Cargo `[env]` entry such as `{ value = "...", force = true }`.
+fn is_cargo_value(table: &toml::value::Table) -> bool {
+    table.contains_key("value")
+        && table
+            .keys()
+            .all(|key| key == "value" || key == "force" || key == "relative")
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidFormat {
+        format: "TOML",
+        source: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(format: &Toml, input: &str) -> Result<EnvMap> {
+        format.parse(&mut input.as_bytes())
+    }
+
+    #[test]
+    fn test_whole_document() {
+        let map = parse(&Toml::new(), "B = 'two'\nA = 1\n[DB]\nHOST = 'localhost'\n").unwrap();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [("B", "two"), ("A", "1"), ("DB_HOST", "localhost")]
+        );
+    }
+
+    #[test]
+    fn test_scalars() {
+        let input = "INT = 3\nFLOAT = 1.5\nBOOL = false\nDATE = 1979-05-27\n";
+        let map = parse(&Toml::new(), input).unwrap();
+        assert_eq!(map.get("INT"), Some("3"));
+        assert_eq!(map.get("FLOAT"), Some("1.5"));
+        assert_eq!(map.get("BOOL"), Some("false"));
+        assert_eq!(map.get("DATE"), Some("1979-05-27"));
+    }
+
+    #[test]
+    fn test_named_table() {
+        let input = "name = 'app'\n[package.metadata.env]\nA = 'a'\n[env]\nB = 'b'\n";
+
+        let map = parse(&Toml::new().table("env"), input).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("B", "b")]);
+
+        let map = parse(&Toml::new().table("package.metadata.env"), input).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("A", "a")]);
+    }
+
+    #[test]
+    fn test_missing_table() {
+        let err = parse(&Toml::new().table("env"), "A = 1\n").unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat { format: "TOML", .. }));
+        assert!(parse(&Toml::new().table("A"), "A = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_cargo_value_tables() {
+        let input =
+            "[env]\nA = { value = 'a', force = true }\nB = { relative = true, value = 'b' }\n";
+        let map = parse(&Toml::new().table("env"), input).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("A", "a"), ("B", "b")]);
+
+        let map = parse(&Toml::new(), "[db]\nvalue = 'x'\nhost = 'h'\n").unwrap();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [("db_value", "x"), ("db_host", "h")]
+        );
+    }
+
+    #[test]
+    fn test_nested_separator() {
+        let map = parse(&Toml::new().separator("."), "[a.b]\nc = 1\n").unwrap();
+        assert_eq!(map.get("a.b.c"), Some("1"));
+    }
+
+    #[test]
+    fn test_array() {
+        let input = "A = [1, 2.5, 'x\"y', true, 1979-05-27, [nan], { k = 'v' }]\n";
+        let map = parse(&Toml::new(), input).unwrap();
+        assert_eq!(
+            map.get("A"),
+            Some(r#"[1,2.5,"x\"y",true,"1979-05-27",[null],{"k":"v"}]"#)
+        );
+    }
+
+    #[test]
+    fn test_unsettable_variables() {
+        for input in ["'A=B' = 1\n", "'' = 1\n", "K = \"a\\u0000b\"\n"] {
+            let err = parse(&Toml::new(), input).unwrap_err();
+            assert!(matches!(err, Error::InvalidFormat { format: "TOML", .. }));
+        }
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let err = parse(&Toml::new(), "A = ").unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat { format: "TOML", .. }));
+    }
+}
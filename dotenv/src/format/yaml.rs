@@ -0,0 +1,217 @@
+use std::io::Read;
+
+use serde_yaml::Value;
+
+use crate::errors::*;
+use crate::format::{check_settable, nested_key, push_json_string, EnvFormat};
+use crate::map::EnvMap;
+
+/// A YAML mapping, such as a Kubernetes `ConfigMap` data section or a CI variable dump.
+///
+/// Nested mappings are flattened by joining their keys with a separator, which defaults to
+/// `_`, so `DB: {HOST: localhost}` yields `DB_HOST=localhost`. Numbers and booleans are
+/// converted to their textual form, `null` becomes an empty string and sequences are kept as
+/// compact JSON, as with [`Json`](crate::Json).
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvFormat, Yaml};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = "DB:\n  HOST: localhost\n  PORT: 5432\n";
+/// let map = Yaml::new().parse(&mut input.as_bytes())?;
+/// assert_eq!(map.get("DB_PORT"), Some("5432"));
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Yaml {
+    separator: String,
+}
+
+impl Yaml {
+    /// Creates the format, flattening nested mappings with `_`.
+    pub fn new() -> Self {
+        Yaml {
+            separator: String::from("_"),
+        }
+    }
+
+    /// Sets the separator used to join the keys of nested mappings.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    fn flatten(&self, prefix: &str, value: Value, map: &mut EnvMap) -> Result<()> {
+        match value {
+            Value::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    let key = scalar(key).ok_or_else(|| invalid("keys must be scalars"))?;
+                    self.flatten(&nested_key(prefix, &self.separator, &key), value, map)?;
+                }
+            }
+            Value::Tagged(tagged) => self.flatten(prefix, tagged.value, map)?,
+            Value::Sequence(_) => {
+                let mut json = String::new();
+                to_json(&value, &mut json)?;
+                map.insert(prefix, json);
+            }
+            other => {
+                map.insert(prefix, scalar(other).unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Yaml {
+    fn default() -> Self {
+        Yaml::new()
+    }
+}
+
+impl EnvFormat for Yaml {
+    fn parse(&self, reader: &mut dyn Read) -> Result<EnvMap> {
+        let value: Value = serde_yaml::from_reader(reader).map_err(|err| Error::InvalidFormat {
+            format: "YAML",
+            source: Box::new(err),
+        })?;
+        let mut map = EnvMap::new();
+        match value {
+            // an empty document
+            Value::Null => {}
+            Value::Mapping(_) => self.flatten("", value, &mut map)?,
+            _ => return Err(invalid("expected a mapping at the top level")),
+        }
+        check_settable("YAML", &map)?;
+        Ok(map)
+    }
+}
+
+fn scalar(value: Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null => Some(String::new()),
+        Value::Tagged(tagged) => scalar(tagged.value),
+        Value::Sequence(_) | Value::Mapping(_) => None,
+    }
+}
+
+/// Appends `value` to `output` as compact JSON.
+fn to_json(value: &Value, output: &mut String) -> Result<()> {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(b) => output.push_str(&b.to_string()),
+        // JSON has no infinities or NaN
+        Value::Number(n) if n.as_f64().map_or(false, |f| !f.is_finite()) => output.push_str("null"),
+        Value::Number(n) => output.push_str(&n.to_string()),
+        Value::String(s) => push_json_string(output, s),
+        Value::Sequence(sequence) => {
+            output.push('[');
+            for (i, value) in sequence.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                to_json(value, output)?;
+            }
+            output.push(']');
+        }
+        Value::Mapping(mapping) => {
+            output.push('{');
+            for (i, (key, value)) in mapping.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                let key = scalar(key.clone()).ok_or_else(|| invalid("keys must be scalars"))?;
+                push_json_string(output, &key);
+                output.push(':');
+                to_json(value, output)?;
+            }
+            output.push('}');
+        }
+        Value::Tagged(tagged) => to_json(&tagged.value, output)?,
+    }
+    Ok(())
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::InvalidFormat {
+        format: "YAML",
+        source: message.into().into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(format: &Yaml, input: &str) -> Result<EnvMap> {
+        format.parse(&mut input.as_bytes())
+    }
+
+    #[test]
+    fn test_flat_mapping() {
+        let map = parse(&Yaml::new(), "B: two\nA: 'one'\n").unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("B", "two"), ("A", "one")]);
+    }
+
+    #[test]
+    fn test_scalars() {
+        let map = parse(
+            &Yaml::new(),
+            "PORT: 8080\nDEBUG: true\nEMPTY: ~\n1: numeric\n",
+        )
+        .unwrap();
+        assert_eq!(map.get("PORT"), Some("8080"));
+        assert_eq!(map.get("DEBUG"), Some("true"));
+        assert_eq!(map.get("EMPTY"), Some(""));
+        assert_eq!(map.get("1"), Some("numeric"));
+    }
+
+    #[test]
+    fn test_nested_mappings() {
+        let input = "APP:\n  DB:\n    HOST: localhost\n  NAME: demo\n";
+
+        let map = parse(&Yaml::new(), input).unwrap();
+        assert_eq!(map.get("APP_DB_HOST"), Some("localhost"));
+        assert_eq!(map.get("APP_NAME"), Some("demo"));
+
+        let map = parse(&Yaml::new().separator("__"), input).unwrap();
+        assert_eq!(map.get("APP__DB__HOST"), Some("localhost"));
+    }
+
+    #[test]
+    fn test_empty_document() {
+        assert!(parse(&Yaml::new(), "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sequence() {
+        let input = "LIST:\n  - a\n  - 1\n  - true\n  - ~\n  - \"q\\\"\\n\"\n  - [x]\n  - {K: v, 2: .inf}\n";
+        let map = parse(&Yaml::new(), input).unwrap();
+        assert_eq!(
+            map.get("LIST"),
+            Some(r#"["a",1,true,null,"q\"\n",["x"],{"K":"v","2":null}]"#)
+        );
+        assert!(parse(&Yaml::new(), "LIST:\n  - {[a]: b}\n").is_err());
+        assert!(parse(&Yaml::new(), "- a\n").is_err());
+    }
+
+    #[test]
+    fn test_unsettable_variables() {
+        for input in ["A=B: 1\n", "'': 1\n", "K: \"a\\0b\"\n"] {
+            let err = parse(&Yaml::new(), input).unwrap_err();
+            assert!(matches!(err, Error::InvalidFormat { format: "YAML", .. }));
+        }
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let err = parse(&Yaml::new(), "A: [").unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat { format: "YAML", .. }));
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::*;
+use crate::iter::{eval_end_state, ParseState};
+use crate::map::EnvMap;
+use crate::parse;
+use crate::syntax::Syntax;
+
+/// A parser which keeps the result of its previous parse, and only parses the statements
+/// which changed since, for reloading large files with low latency.
+///
+/// The parts of the input before and after the first and last difference with the previous
+/// input are reused as is, using the offsets recorded while parsing. Only the statements in
+/// between, and those whose values may depend on other variables, such as `B=${A}` or a bare
+/// key, are parsed again. The result is always the same as parsing the whole input with
+/// [`Iter::with_syntax`](crate::Iter::with_syntax).
+///
+/// Profile sections are not supported.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::IncrementalParser;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut parser = IncrementalParser::new();
+/// let map = parser.parse("A=1\nB=2\nC=3\n")?;
+/// assert_eq!(map.get("B"), Some("2"));
+///
+/// let map = parser.parse("A=1\nB=20\nC=3\n")?;
+/// assert_eq!(map.get("B"), Some("20"));
+/// assert_eq!(parser.reparsed(), 1);
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct IncrementalParser {
+    syntax: Syntax,
+    text: String,
+    statements: Vec<Statement>,
+    reparsed: usize,
+}
+
+/// A statement of the previous input, which spans one or more lines.
+#[derive(Debug, Clone)]
+struct Statement {
+    /// The byte offsets of the statement in the input, including its line terminator.
+    start: usize,
+    end: usize,
+    /// The statement as given to the line parser, kept if it must always be parsed again.
+    dynamic: Option<String>,
+    /// The variable declared, if any.
+    parsed: Option<(String, String)>,
+    /// What the statement recorded for substitutions in later statements.
+    binding: Option<(String, Option<String>)>,
+}
+
+/// A statement of the new input, either reused or still to be parsed.
+enum Slot {
+    Reused(Statement),
+    Fresh {
+        start: usize,
+        end: usize,
+        line: String,
+    },
+}
+
+impl IncrementalParser {
+    /// Creates a parser for the default [`Syntax`].
+    pub fn new() -> Self {
+        IncrementalParser::with_syntax(Syntax::new())
+    }
+
+    /// Creates a parser for `syntax`.
+    pub fn with_syntax(syntax: Syntax) -> Self {
+        IncrementalParser {
+            syntax,
+            text: String::new(),
+            statements: Vec::new(),
+            reparsed: 0,
+        }
+    }
+
+    /// Returns the number of statements parsed by the last call to
+    /// [`parse`](IncrementalParser::parse), rather than reused.
+    pub fn reparsed(&self) -> usize {
+        self.reparsed
+    }
+
+    /// Parses `input`, reusing what did not change since the previous call.
+    ///
+    /// If an error is returned, the previous input is kept as the base for the next call.
+    pub fn parse(&mut self, input: &str) -> Result<EnvMap> {
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+        let old = self.text.as_str();
+        let prefix = common_prefix(old, input);
+        let suffix = common_suffix(&old[prefix..], &input[prefix..]);
+
+        let mut slots = Vec::new();
+        let mut pos = 0;
+        for statement in &self.statements {
+            let reusable = statement.end < prefix
+                || (statement.end == prefix && old[..statement.end].ends_with('\n'));
+            if !reusable {
+                break;
+            }
+            pos = statement.end;
+            slots.push(Slot::Reused(statement.clone()));
+        }
+
+        // statements which start after a line terminator within the common suffix start at the
+        // same place in the new input, shifted by the difference in length
+        let suffix_start = old.len() - suffix;
+        let shift = |offset: usize| offset + input.len() - old.len();
+        let first_reused = slots.len();
+        let tail: HashMap<usize, usize> = self.statements[first_reused..]
+            .iter()
+            .enumerate()
+            .filter(|(_, statement)| statement.start > suffix_start)
+            .map(|(index, statement)| (shift(statement.start), first_reused + index))
+            .collect();
+
+        while pos < input.len() {
+            if let Some(&index) = tail.get(&pos) {
+                slots.extend(self.statements[index..].iter().map(|statement| {
+                    Slot::Reused(Statement {
+                        start: shift(statement.start),
+                        end: shift(statement.end),
+                        ..statement.clone()
+                    })
+                }));
+                break;
+            }
+            let (line, end) = next_statement(input, pos, &self.syntax)?;
+            slots.push(Slot::Fresh {
+                start: pos,
+                end,
+                line,
+            });
+            pos = end;
+        }
+
+        let mut substitution_data = HashMap::new();
+        let mut statements = Vec::with_capacity(slots.len());
+        let mut reparsed = 0;
+        for slot in slots {
+            let statement = match slot {
+                Slot::Reused(statement) if statement.dynamic.is_none() => {
+                    if let Some((key, value)) = &statement.binding {
+                        substitution_data.insert(key.clone(), value.clone());
+                    }
+                    statement
+                }
+                Slot::Reused(statement) => {
+                    let line = statement.dynamic.unwrap_or_default();
+                    reparsed += 1;
+                    self.parse_statement(
+                        statement.start,
+                        statement.end,
+                        line,
+                        &mut substitution_data,
+                    )?
+                }
+                Slot::Fresh { start, end, line } => {
+                    reparsed += 1;
+                    self.parse_statement(start, end, line, &mut substitution_data)?
+                }
+            };
+            statements.push(statement);
+        }
+
+        let mut map = EnvMap::new();
+        for (key, value) in statements.iter().filter_map(|s| s.parsed.as_ref()) {
+            map.insert(key.as_str(), value.as_str());
+        }
+        self.text = input.to_owned();
+        self.statements = statements;
+        self.reparsed = reparsed;
+        Ok(map)
+    }
+
+    /// Reads the file at `path` and parses it with [`parse`](IncrementalParser::parse).
+    pub fn parse_path<P: AsRef<Path>>(&mut self, path: P) -> Result<EnvMap> {
+        let input = fs::read_to_string(path).map_err(Error::Io)?;
+        self.parse(&input)
+    }
+
+    fn parse_statement(
+        &self,
+        start: usize,
+        end: usize,
+        line: String,
+        substitution_data: &mut HashMap<String, Option<String>>,
+    ) -> Result<Statement> {
+        let parsed = parse::parse_line(&line, substitution_data, &self.syntax)?;
+        let binding = parsed
+            .as_ref()
+            .map(|(key, _)| (key.clone(), substitution_data.get(key).cloned().flatten()));
+        Ok(Statement {
+            start,
+            end,
+            dynamic: Some(line).filter(|line| self.is_dynamic(line)),
+            parsed,
+            binding,
+        })
+    }
+
+    /// Returns `true` if the value of `line` may depend on other variables.
+    ///
+    /// Blank lines and comments, which are given as empty lines, declare nothing.
+    fn is_dynamic(&self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return false;
+        }
+        !line.contains('=')
+            || line
+                .chars()
+                .any(|c| self.syntax.is_substitution(c) || self.syntax.is_percent_substitution(c))
+    }
+}
+
+impl Default for IncrementalParser {
+    fn default() -> Self {
+        IncrementalParser::new()
+    }
+}
+
+/// Reads the statement starting at `start`, returning it as given to the line parser with the
+/// offset just after it.
+///
+/// This splits the input exactly as [`Iter`](crate::Iter) does.
+fn next_statement(input: &str, start: usize, syntax: &Syntax) -> Result<(String, usize)> {
+    let mut state = ParseState::Complete;
+    let mut line_start = start;
+    loop {
+        if line_start == input.len() {
+            let statement = input[start..].to_owned();
+            let len = statement.len();
+            return Err(Error::LineParse(statement, len));
+        }
+        let line_end = input[line_start..]
+            .find('\n')
+            .map_or(input.len(), |index| line_start + index + 1);
+        let statement = &input[start..line_end];
+        if syntax.starts_with_comment(statement.trim_start()) {
+            return Ok((String::new(), line_end));
+        }
+        let (pos, end_state) = eval_end_state(state, &input[line_start..line_end], syntax);
+        state = end_state;
+        match state {
+            ParseState::Complete => {
+                let statement = statement.strip_suffix('\n').unwrap_or(statement);
+                let statement = statement.strip_suffix('\r').unwrap_or(statement);
+                return Ok((statement.to_owned(), line_end));
+            }
+            ParseState::Comment => {
+                let statement = input[start..line_start + pos].trim_end();
+                return Ok((statement.to_owned(), line_end));
+            }
+            _ => line_start = line_end,
+        }
+    }
+}
+
+/// Returns the length of the common prefix of `a` and `b`, at a character boundary.
+fn common_prefix(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count();
+    while !a.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+/// Returns the length of the common suffix of `a` and `b`, at a character boundary.
+fn common_suffix(a: &str, b: &str) -> usize {
+    let mut len = a
+        .bytes()
+        .rev()
+        .zip(b.bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !a.is_char_boundary(a.len() - len) {
+        len -= 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iter::Iter;
+
+    fn full_parse(input: &str) -> EnvMap {
+        Iter::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .expect("valid input")
+    }
+
+    #[test]
+    fn test_reparses_changed_statements() {
+        let lines: Vec<_> = (0..1000)
+            .map(|i| format!("KEY_{}=value_{}", i, i))
+            .collect();
+        let input = lines.join("\n") + "\n";
+        let mut parser = IncrementalParser::new();
+        assert_eq!(parser.parse(&input).unwrap(), full_parse(&input));
+        assert_eq!(parser.reparsed(), 1000);
+
+        let edited = input.replace("KEY_500=value_500\n", "KEY_500=\"changed\nover lines\"\n");
+        assert_eq!(parser.parse(&edited).unwrap(), full_parse(&edited));
+        assert_eq!(parser.reparsed(), 1);
+
+        let shortened = edited.replace("KEY_10=value_10\n", "");
+        assert_eq!(parser.parse(&shortened).unwrap(), full_parse(&shortened));
+        assert_eq!(parser.reparsed(), 1);
+        assert_eq!(parser.parse(&shortened).unwrap(), full_parse(&shortened));
+        assert_eq!(parser.reparsed(), 0);
+    }
+
+    #[test]
+    fn test_comments_are_not_reparsed() {
+        let lines: Vec<_> = (0..100)
+            .map(|i| format!("# about {}\n\nKEY_{}=value_{} # trailing\n", i, i, i))
+            .collect();
+        let input = lines.concat();
+        let mut parser = IncrementalParser::new();
+        assert_eq!(parser.parse(&input).unwrap(), full_parse(&input));
+        assert_eq!(parser.reparsed(), 300);
+
+        let edited = input.replace("KEY_50=value_50", "KEY_50=changed");
+        assert_eq!(parser.parse(&edited).unwrap(), full_parse(&edited));
+        assert_eq!(parser.reparsed(), 1);
+
+        let edited = edited.replace("# about 20\n", "# about twenty\n");
+        assert_eq!(parser.parse(&edited).unwrap(), full_parse(&edited));
+        assert_eq!(parser.reparsed(), 1);
+    }
+
+    #[test]
+    fn test_matches_full_parse() {
+        let inputs = [
+            "A=1\nB=${A}2\n# comment\nC='x'\n",
+            "A=10\nB=${A}2\n# comment\nC='x'\n",
+            "A=10\nB=${A}2\n# comment\nC='x' # trailing\nD=\"multi\nline\"\n",
+            "A=10\nB=${A}2\nD=\"multi\nline\"\n",
+            "A=10\nB=${A}2\nD=\"multi\nline\"",
+            "A=10\nB=${A}2\nD=\"multi\nline\"E=1",
+            "\u{feff}A=1\nA=2\nB=$A\n",
+            "A=\r\nB=$A\r\n",
+            "",
+            "A=1\n",
+        ];
+        let mut parser = IncrementalParser::new();
+        for input in &inputs {
+            let expected: Result<EnvMap> = Iter::new(input.as_bytes()).collect();
+            match (parser.parse(input), expected) {
+                (Ok(actual), Ok(expected)) => assert_eq!(actual, expected, "{:?}", input),
+                (Err(_), Err(_)) => {}
+                (actual, expected) => panic!("{:?}: {:?} != {:?}", input, actual, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_keeps_previous_input() {
+        let mut parser = IncrementalParser::new();
+        parser.parse("A=1\nB=2\n").unwrap();
+        assert!(parser.parse("A=1\nB=\"2\n").is_err());
+        assert_eq!(
+            parser.parse("A=1\nB=3\n").unwrap(),
+            full_parse("A=1\nB=3\n")
+        );
+        assert_eq!(parser.reparsed(), 1);
+    }
+}
@@ -3,24 +3,54 @@ use std::env;
 use std::io::prelude::*;
 use std::io::BufReader;
 
+use crate::apply::AppliedReport;
 use crate::errors::*;
 use crate::parse;
+use crate::syntax::Syntax;
 
 pub struct Iter<R> {
     lines: QuotedLines<BufReader<R>>,
     substitution_data: HashMap<String, Option<String>>,
+    bom_checked: bool,
+    sections: Option<Sections>,
+}
+
+/// The `[profile.<name>]` sections to read, and the section being read.
+struct Sections {
+    profile: String,
+    in_profile: bool,
+    in_base: bool,
 }
 
 impl<R: Read> Iter<R> {
     pub fn new(reader: R) -> Iter<R> {
+        Iter::with_syntax(reader, Syntax::default())
+    }
+
+    /// Creates an iterator which parses the `reader` using a custom [`Syntax`].
+    pub fn with_syntax(reader: R, syntax: Syntax) -> Iter<R> {
         Iter {
             lines: QuotedLines {
                 buf: BufReader::new(reader),
+                syntax,
             },
             substitution_data: HashMap::new(),
+            bom_checked: false,
+            sections: None,
         }
     }
 
+    /// Recognizes `[profile.<name>]` section headers, reading the lines before the first
+    /// header and those in the section of `profile`, and skipping all others.
+    pub(crate) fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.sections = Some(Sections {
+            profile: profile.into(),
+            in_profile: false,
+            in_base: true,
+        });
+        self
+    }
+
     /// Loads all variables found in the `reader` into the environment,
     /// preserving any existing environment variables of the same name.
     ///
@@ -39,6 +69,28 @@ impl<R: Read> Iter<R> {
         Ok(())
     }
 
+    /// Loads all variables like [`load`](Iter::load), returning the keys which were set and
+    /// those which were skipped because they were already present.
+    pub(crate) fn load_report(mut self) -> Result<AppliedReport> {
+        self.remove_bom()?;
+
+        let mut report = AppliedReport::default();
+        for item in self {
+            let (key, value) = item?;
+            if report.is_applied(&key) || report.is_skipped(&key) {
+                continue;
+            }
+            if env::var(&key).is_err() {
+                env::set_var(&key, value);
+                report.push_applied(&key);
+            } else {
+                report.push_skipped(&key);
+            }
+        }
+
+        Ok(report)
+    }
+
     /// Loads all variables found in the `reader` into the environment,
     /// overriding any existing environment variables of the same name.
     ///
@@ -55,7 +107,11 @@ impl<R: Read> Iter<R> {
         Ok(())
     }
 
-    fn remove_bom(&mut self) -> Result<()> {
+    pub(crate) fn remove_bom(&mut self) -> Result<()> {
+        if self.bom_checked {
+            return Ok(());
+        }
+        self.bom_checked = true;
         let buffer = self.lines.buf.fill_buf().map_err(Error::Io)?;
         // https://www.compart.com/en/unicode/U+FEFF
         if buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
@@ -68,9 +124,10 @@ impl<R: Read> Iter<R> {
 
 struct QuotedLines<B> {
     buf: B,
+    syntax: Syntax,
 }
 
-enum ParseState {
+pub(crate) enum ParseState {
     Complete,
     Escape,
     StrongOpen,
@@ -81,7 +138,11 @@ enum ParseState {
     WhiteSpace,
 }
 
-fn eval_end_state(prev_state: ParseState, buf: &str) -> (usize, ParseState) {
+pub(crate) fn eval_end_state(
+    prev_state: ParseState,
+    buf: &str,
+    syntax: &Syntax,
+) -> (usize, ParseState) {
     let mut cur_state = prev_state;
     let mut cur_pos: usize = 0;
 
@@ -89,28 +150,28 @@ fn eval_end_state(prev_state: ParseState, buf: &str) -> (usize, ParseState) {
         cur_pos = pos;
         cur_state = match cur_state {
             ParseState::WhiteSpace => match c {
-                '#' => return (cur_pos, ParseState::Comment),
-                '\\' => ParseState::Escape,
-                '"' => ParseState::WeakOpen,
-                '\'' => ParseState::StrongOpen,
+                c if syntax.is_comment(c) => return (cur_pos, ParseState::Comment),
+                c if syntax.is_escape(c) => ParseState::Escape,
+                c if syntax.is_double_quote(c) => ParseState::WeakOpen,
+                c if syntax.is_single_quote(c) => ParseState::StrongOpen,
                 _ => ParseState::Complete,
             },
             ParseState::Escape => ParseState::Complete,
             ParseState::Complete => match c {
                 c if c.is_whitespace() && c != '\n' && c != '\r' => ParseState::WhiteSpace,
-                '\\' => ParseState::Escape,
-                '"' => ParseState::WeakOpen,
-                '\'' => ParseState::StrongOpen,
+                c if syntax.is_escape(c) => ParseState::Escape,
+                c if syntax.is_double_quote(c) => ParseState::WeakOpen,
+                c if syntax.is_single_quote(c) => ParseState::StrongOpen,
                 _ => ParseState::Complete,
             },
             ParseState::WeakOpen => match c {
-                '\\' => ParseState::WeakOpenEscape,
+                c if syntax.is_escape(c) => ParseState::WeakOpenEscape,
                 '"' => ParseState::Complete,
                 _ => ParseState::WeakOpen,
             },
             ParseState::WeakOpenEscape => ParseState::WeakOpen,
             ParseState::StrongOpen => match c {
-                '\\' => ParseState::StrongOpenEscape,
+                c if syntax.is_escape(c) => ParseState::StrongOpenEscape,
                 '\'' => ParseState::Complete,
                 _ => ParseState::StrongOpen,
             },
@@ -143,10 +204,10 @@ impl<B: BufRead> Iterator for QuotedLines<B> {
                 Ok(_n) => {
                     // Skip lines which start with a # before iteration
                     // This optimizes parsing a bit.
-                    if buf.trim_start().starts_with('#') {
+                    if self.syntax.starts_with_comment(buf.trim_start()) {
                         return Some(Ok(String::with_capacity(0)));
                     }
-                    let result = eval_end_state(cur_state, &buf[buf_pos..]);
+                    let result = eval_end_state(cur_state, &buf[buf_pos..], &self.syntax);
                     cur_pos = result.0;
                     cur_state = result.1;
 
@@ -167,7 +228,8 @@ impl<B: BufRead> Iterator for QuotedLines<B> {
                         | ParseState::WeakOpenEscape
                         | ParseState::WhiteSpace => {}
                         ParseState::Comment => {
-                            buf.truncate(buf_pos + cur_pos);
+                            // the whitespace before the comment is not part of the value
+                            buf.truncate(buf[..buf_pos + cur_pos].trim_end().len());
                             return Some(Ok(buf));
                         }
                     }
@@ -182,6 +244,9 @@ impl<R: Read> Iterator for Iter<R> {
     type Item = Result<(String, String)>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = self.remove_bom() {
+            return Some(Err(err));
+        }
         loop {
             let line = match self.lines.next() {
                 Some(Ok(line)) => line,
@@ -189,7 +254,21 @@ impl<R: Read> Iterator for Iter<R> {
                 None => return None,
             };
 
-            match parse::parse_line(&line, &mut self.substitution_data) {
+            if let Some(sections) = &mut self.sections {
+                match sections.enter(&line) {
+                    Ok(true) => continue,
+                    Ok(false) if !sections.in_base && !sections.in_profile => continue,
+                    Ok(false) => {}
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+
+            match parse::parse_line(&line, &mut self.substitution_data, &self.lines.syntax) {
+                // the environment cannot hold a value with a NUL character
+                Ok(Some((_, value))) if value.contains('\0') => {
+                    let index = line.find('\0').unwrap_or(0);
+                    return Some(Err(Error::LineParse(line, index)));
+                }
                 Ok(Some(result)) => return Some(Ok(result)),
                 Ok(None) => {}
                 Err(err) => return Some(Err(err)),
@@ -197,3 +276,257 @@ impl<R: Read> Iterator for Iter<R> {
         }
     }
 }
+
+impl Sections {
+    /// Switches section if `line` is a header, returning whether it was one.
+    fn enter(&mut self, line: &str) -> Result<bool> {
+        let header = line.trim();
+        if !header.starts_with('[') {
+            return Ok(false);
+        }
+        let name = header
+            .strip_prefix("[profile.")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .filter(|name| !name.is_empty() && !name.contains(|c| c == '[' || c == ']'))
+            .ok_or_else(|| Error::LineParse(line.to_owned(), 0))?;
+        self.in_base = false;
+        self.in_profile = name == self.profile;
+        Ok(true)
+    }
+}
+
+/// Lazy adapters for iterators over parsed variables, such as [`Iter`].
+///
+/// The adapters shape the variables while they are read, without collecting them into a map
+/// first, and can be chained before [`load`](EnvIterator::load). Errors are passed through
+/// unchanged.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::EnvIterator;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// dotenvy::from_filename_iter("shared.env")?
+///     .take_prefix("MYAPP_")
+///     .filter_keys(|key| key != "DEBUG")
+///     .map_values(|value| value.trim().to_owned())
+///     .load()?;
+/// #     Ok(())
+/// # }
+/// ```
+pub trait EnvIterator: Iterator<Item = Result<(String, String)>> + Sized {
+    /// Keeps only the variables whose keys satisfy `predicate`.
+    fn filter_keys<P: FnMut(&str) -> bool>(self, predicate: P) -> FilterKeys<Self, P> {
+        FilterKeys {
+            iter: self,
+            predicate,
+        }
+    }
+
+    /// Replaces every value with the result of `f`.
+    fn map_values<F: FnMut(String) -> String>(self, f: F) -> MapValues<Self, F> {
+        MapValues { iter: self, f }
+    }
+
+    /// Keeps only the variables whose keys start with `prefix`, removing the prefix.
+    ///
+    /// Keys which are exactly `prefix` are dropped, since they would become empty.
+    fn take_prefix(self, prefix: impl Into<String>) -> TakePrefix<Self> {
+        TakePrefix {
+            iter: self,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Loads all variables into the environment, preserving any existing environment variables
+    /// of the same name.
+    ///
+    /// If a variable is yielded multiple times, then the first occurrence is applied.
+    fn load(self) -> Result<()> {
+        for item in self {
+            let (key, value) = item?;
+            if env::var(&key).is_err() {
+                env::set_var(&key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads all variables into the environment, overriding any existing environment variables
+    /// of the same name.
+    ///
+    /// If a variable is yielded multiple times, then the last occurrence is applied.
+    fn load_override(self) -> Result<()> {
+        for item in self {
+            let (key, value) = item?;
+            env::set_var(key, value);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> EnvIterator for Iter<R> {}
+
+/// An iterator filtering variables by key. See [`EnvIterator::filter_keys`].
+pub struct FilterKeys<I, P> {
+    iter: I,
+    predicate: P,
+}
+
+impl<I: EnvIterator, P: FnMut(&str) -> bool> Iterator for FilterKeys<I, P> {
+    type Item = Result<(String, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        self.iter.find(|item| match item {
+            Ok((key, _)) => predicate(key),
+            Err(_) => true,
+        })
+    }
+}
+
+impl<I: EnvIterator, P: FnMut(&str) -> bool> EnvIterator for FilterKeys<I, P> {}
+
+/// An iterator transforming values. See [`EnvIterator::map_values`].
+pub struct MapValues<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<I: EnvIterator, F: FnMut(String) -> String> Iterator for MapValues<I, F> {
+    type Item = Result<(String, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let f = &mut self.f;
+        self.iter
+            .next()
+            .map(|item| item.map(|(key, value)| (key, f(value))))
+    }
+}
+
+impl<I: EnvIterator, F: FnMut(String) -> String> EnvIterator for MapValues<I, F> {}
+
+/// An iterator selecting and stripping a key prefix. See [`EnvIterator::take_prefix`].
+pub struct TakePrefix<I> {
+    iter: I,
+    prefix: String,
+}
+
+impl<I: EnvIterator> Iterator for TakePrefix<I> {
+    type Item = Result<(String, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.iter.next()? {
+                Ok((key, value)) => match key.strip_prefix(self.prefix.as_str()) {
+                    Some(rest) if !rest.is_empty() => return Some(Ok((rest.to_owned(), value))),
+                    _ => {}
+                },
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl<I: EnvIterator> EnvIterator for TakePrefix<I> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn collect<I: EnvIterator>(iter: I) -> Vec<(String, String)> {
+        iter.collect::<Result<_>>().unwrap()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_adapters_compose() {
+        let input = "APP_A=1\nOTHER=2\nAPP_B=' x '\nAPP_=3\nAPP_C=4\n";
+        let iter = Iter::new(input.as_bytes())
+            .take_prefix("APP_")
+            .filter_keys(|key| key != "C")
+            .map_values(|value| format!("<{}>", value.trim()));
+        assert_eq!(collect(iter), pairs(&[("A", "<1>"), ("B", "<x>")]));
+    }
+
+    #[test]
+    fn test_adapters_pass_errors() {
+        let iter = Iter::new("A=1\nB C\nD=2\n".as_bytes()).filter_keys(|_| false);
+        assert_eq!(iter.filter(Result::is_err).count(), 1);
+    }
+
+    #[test]
+    fn test_iter_removes_bom() {
+        let iter = Iter::new(&b"\xEF\xBB\xBFA=1\n"[..]);
+        assert_eq!(collect(iter), pairs(&[("A", "1")]));
+    }
+
+    #[test]
+    fn test_adapted_load() {
+        env::set_var("DOTENVY_ITER_TEST_EXISTING", "old");
+        let input = "X_DOTENVY_ITER_TEST_EXISTING=new\nX_DOTENVY_ITER_TEST_NEW=new\n";
+        Iter::new(input.as_bytes())
+            .take_prefix("X_")
+            .load()
+            .unwrap();
+        assert_eq!(env::var("DOTENVY_ITER_TEST_EXISTING").unwrap(), "old");
+        assert_eq!(env::var("DOTENVY_ITER_TEST_NEW").unwrap(), "new");
+
+        Iter::new(input.as_bytes())
+            .take_prefix("X_")
+            .map_values(|value| value.to_uppercase())
+            .load_override()
+            .unwrap();
+        assert_eq!(env::var("DOTENVY_ITER_TEST_EXISTING").unwrap(), "NEW");
+    }
+
+    #[test]
+    fn test_profile_sections() {
+        let input = "A=base\nB=base\n\n[profile.production]\nA=prod\n\n[profile.staging]\nA=staging\nC=staging\n";
+        let iter = Iter::new(input.as_bytes()).profile("production");
+        assert_eq!(
+            collect(iter),
+            pairs(&[("A", "base"), ("B", "base"), ("A", "prod")])
+        );
+        let iter = Iter::new(input.as_bytes()).profile("staging");
+        assert_eq!(
+            collect(iter),
+            pairs(&[
+                ("A", "base"),
+                ("B", "base"),
+                ("A", "staging"),
+                ("C", "staging")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_invalid_section_header() {
+        for header in ["[production]", "[profile.]", "[profile.x"] {
+            let input = format!("{}\nA=1\n", header);
+            let mut iter = Iter::new(input.as_bytes()).profile("x");
+            assert!(iter.next().unwrap().is_err(), "{}", header);
+        }
+        assert!(Iter::new("[profile.x]\n".as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn test_nul_in_value() {
+        let mut iter = Iter::new("A=a\0b\nB=1\n".as_bytes());
+        assert!(matches!(iter.next(), Some(Err(Error::LineParse(line, 3))) if line == "A=a\0b"));
+        assert_eq!(
+            iter.next().unwrap().unwrap(),
+            ("B".to_owned(), "1".to_owned())
+        );
+    }
+}
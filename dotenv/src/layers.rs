@@ -0,0 +1,589 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::errors::*;
+use crate::loader::EnvLoader;
+use crate::map::EnvMap;
+use crate::schema::EnvSchema;
+use crate::watch::Watcher;
+
+/// A stack of variable sources, such as *.env* files and the process environment, which can
+/// be resolved into the effective environment along with where each value came from.
+///
+/// Layers are added from lowest to highest precedence: a variable defined by a later layer
+/// overrides the same variable from an earlier one. To match [`dotenv`](crate::dotenv), which
+/// never overrides existing variables, add the process environment last.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvMap, Layers};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let defaults: EnvMap = vec![("PORT", "3000"), ("HOST", "localhost")].into_iter().collect();
+/// let local: EnvMap = vec![("PORT", "8080")].into_iter().collect();
+///
+/// let resolved = Layers::new()
+///     .map("defaults", defaults)
+///     .map("local", local)
+///     .resolve()?;
+///
+/// let port = resolved.get("PORT").unwrap();
+/// assert_eq!(port.value(), "8080");
+/// assert_eq!(port.source(), "local");
+/// assert_eq!(port.to_string(), "PORT=8080 from local\n  overrides 3000 from defaults");
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Layers {
+    layers: Vec<Layer>,
+    ttls: HashMap<String, Duration>,
+}
+
+/// Fetches the variables of a provider layer.
+type Fetch = dyn Fn() -> Result<EnvMap>;
+
+enum Layer {
+    Loader(Box<EnvLoader>),
+    Map(String, EnvMap),
+    Provider(String, Box<Fetch>),
+    ProcessEnv,
+}
+
+/// The variables of a provider layer, and when they were fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fetched {
+    map: EnvMap,
+    at: Instant,
+}
+
+impl Layers {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        Layers {
+            layers: Vec::new(),
+            ttls: HashMap::new(),
+        }
+    }
+
+    /// Adds the file at `path`. The file is skipped when resolving if it does not exist.
+    pub fn file<P: AsRef<Path>>(self, path: P) -> Self {
+        self.loader(EnvLoader::new().path(path))
+    }
+
+    /// Adds the file read by `loader`. The file is skipped when resolving if it cannot be
+    /// found.
+    ///
+    /// Variables from the file are attributed to its path. The loader's
+    /// [`defaults`](EnvLoader::defaults) are attributed to `default`, and have a lower
+    /// precedence than every layer.
+    pub fn loader(mut self, loader: EnvLoader) -> Self {
+        self.layers.push(Layer::Loader(Box::new(loader)));
+        self
+    }
+
+    /// Adds the variables in `map`, attributed to `name`.
+    pub fn map(mut self, name: impl Into<String>, map: EnvMap) -> Self {
+        self.layers.push(Layer::Map(name.into(), map));
+        self
+    }
+
+    /// Adds the variables returned by `fetch`, attributed to `name`, such as secrets from a
+    /// remote store.
+    ///
+    /// `fetch` is called when resolving, and again by
+    /// [`ResolvedEnv::refresh_expired`] once a value it returned has expired.
+    pub fn provider<F>(mut self, name: impl Into<String>, fetch: F) -> Self
+    where
+        F: Fn() -> Result<EnvMap> + 'static,
+    {
+        self.layers
+            .push(Layer::Provider(name.into(), Box::new(fetch)));
+        self
+    }
+
+    /// Uses the [`ttl`](crate::VarSpec::ttl) of each variable in `schema`, as set with a
+    /// `@ttl: 300s` annotation, as the time a value fetched from a
+    /// [`provider`](Layers::provider) stays valid.
+    ///
+    /// Values from other layers never expire.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use dotenvy::{EnvMap, EnvSchema, Layers, VarSpec};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let schema = EnvSchema::new()
+    ///     .var(VarSpec::new("DB_PASSWORD").expires_after(Duration::from_secs(300)));
+    /// let layers = Layers::new()
+    ///     .provider("vault", || Ok(vec![("DB_PASSWORD", "hunter2")].into_iter().collect()))
+    ///     .ttls(&schema);
+    ///
+    /// let mut resolved = layers.resolve()?;
+    /// assert!(resolved.get("DB_PASSWORD").unwrap().expires_at().is_some());
+    /// // later, for example on a timer
+    /// for key in resolved.refresh_expired(&layers)? {
+    ///     println!("{} was rotated", key);
+    /// }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn ttls(mut self, schema: &EnvSchema) -> Self {
+        for spec in schema.vars() {
+            if let Some(ttl) = spec.ttl() {
+                self.ttls.insert(spec.key().to_owned(), ttl);
+            }
+        }
+        self
+    }
+
+    /// Adds the variables of the process environment, as they are when resolving.
+    ///
+    /// Variables whose keys or values are not valid Unicode are skipped.
+    pub fn process_env(mut self) -> Self {
+        self.layers.push(Layer::ProcessEnv);
+        self
+    }
+
+    /// Reads every layer and combines them into the effective environment.
+    ///
+    /// An error is returned if a file exists but cannot be read or parsed.
+    pub fn resolve(&self) -> Result<ResolvedEnv> {
+        self.resolve_with(HashMap::new())
+    }
+
+    /// Returns a [`Watcher`] for the files of every file and loader layer, so the layers can
+    /// be resolved again when any of them changes.
+    ///
+    /// Missing files given by path are watched too, so creating one counts as a change, but
+    /// those searched for by name are only watched once found.
+    pub fn watcher(&self) -> Watcher {
+        Watcher::new(self.layers.iter().filter_map(|layer| match layer {
+            Layer::Loader(loader) => loader.resolve_path().ok(),
+            _ => None,
+        }))
+    }
+
+    /// Resolves the layers, reusing the variables already `fetched` by provider layers.
+    fn resolve_with(&self, mut fetched: HashMap<usize, Fetched>) -> Result<ResolvedEnv> {
+        let mut resolved = ResolvedEnv::default();
+        // defaults from code have the lowest precedence of all
+        for layer in &self.layers {
+            if let Layer::Loader(loader) = layer {
+                for (key, value) in loader.default_values().iter() {
+                    resolved.define(key, "default", value);
+                }
+            }
+        }
+        for (index, layer) in self.layers.iter().enumerate() {
+            match layer {
+                Layer::Loader(loader) => {
+                    let path = match loader.resolve_path() {
+                        Ok(path) => path,
+                        Err(err) if err.not_found() => continue,
+                        Err(err) => return Err(err),
+                    };
+                    let map = match loader.parse_file() {
+                        Ok(parsed) => parsed.map,
+                        Err(err) if err.not_found() => continue,
+                        Err(err) => return Err(err),
+                    };
+                    let source = path.display().to_string();
+                    for (key, value) in map.iter() {
+                        resolved.define(key, &source, value);
+                    }
+                }
+                Layer::Map(name, map) => {
+                    for (key, value) in map.iter() {
+                        resolved.define(key, name, value);
+                    }
+                }
+                Layer::Provider(name, fetch) => {
+                    let fetched = match fetched.entry(index) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => entry.insert(Fetched {
+                            map: fetch()?,
+                            at: Instant::now(),
+                        }),
+                    };
+                    for (key, value) in fetched.map.iter() {
+                        resolved.define(key, name, value);
+                        resolved.define_provider(key, index);
+                    }
+                }
+                Layer::ProcessEnv => {
+                    for (key, value) in env::vars_os() {
+                        if let (Some(key), Some(value)) = (key.to_str(), value.to_str()) {
+                            resolved.define(key, "process environment", value);
+                        }
+                    }
+                }
+            }
+        }
+        for var in &mut resolved.vars {
+            let effective = var.effective();
+            if let (Some(index), Some(ttl)) = (effective.provider, self.ttls.get(&var.key)) {
+                var.expires_at = Some(fetched[&index].at + *ttl);
+            }
+        }
+        resolved.fetched = fetched;
+        Ok(resolved)
+    }
+}
+
+/// The effective environment produced by [`Layers::resolve`].
+///
+/// Variables keep the order in which they were first defined.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolvedEnv {
+    vars: Vec<ResolvedVar>,
+    index: HashMap<String, usize>,
+    fetched: HashMap<usize, Fetched>,
+}
+
+impl ResolvedEnv {
+    /// Returns the number of variables.
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    /// Returns `true` if no layer defined any variables.
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+
+    /// Returns the resolution of `key`, if any layer defined it.
+    pub fn get(&self, key: &str) -> Option<&ResolvedVar> {
+        self.index.get(key).map(|&index| &self.vars[index])
+    }
+
+    /// Returns the effective value of `key`, if any layer defined it.
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.get(key).map(ResolvedVar::value)
+    }
+
+    /// Returns an iterator over the variables.
+    pub fn iter(&self) -> impl Iterator<Item = &ResolvedVar> {
+        self.vars.iter()
+    }
+
+    /// Returns the effective values as a map.
+    pub fn to_map(&self) -> EnvMap {
+        self.vars
+            .iter()
+            .map(|var| (var.key(), var.value()))
+            .collect()
+    }
+
+    /// Returns the earliest time a value fetched from a provider expires, if any do.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.vars.iter().filter_map(ResolvedVar::expires_at).min()
+    }
+
+    /// Fetches the variables of every provider layer with an expired value again, and
+    /// resolves `layers` with them, so long-running services can pick up rotated credentials.
+    ///
+    /// `layers` must be the stack this environment was resolved from. Files and the process
+    /// environment are read again, while providers without expired values keep the variables
+    /// they returned before. Nothing is done if no value has expired.
+    ///
+    /// Returns the keys whose values changed, were added or were removed.
+    pub fn refresh_expired(&mut self, layers: &Layers) -> Result<Vec<String>> {
+        let now = Instant::now();
+        let mut fetched = self.fetched.clone();
+        let mut expired = false;
+        for var in &self.vars {
+            if var.expires_at.map_or(false, |at| at <= now) {
+                if let Some(index) = var.effective().provider {
+                    fetched.remove(&index);
+                    expired = true;
+                }
+            }
+        }
+        if !expired {
+            return Ok(Vec::new());
+        }
+
+        let refreshed = layers.resolve_with(fetched)?;
+        let mut changed: Vec<String> = refreshed
+            .vars
+            .iter()
+            .filter(|var| self.value(&var.key) != Some(var.value()))
+            .map(|var| var.key.clone())
+            .collect();
+        changed.extend(
+            self.vars
+                .iter()
+                .filter(|var| refreshed.get(&var.key).is_none())
+                .map(|var| var.key.clone()),
+        );
+        *self = refreshed;
+        Ok(changed)
+    }
+
+    fn define(&mut self, key: &str, source: &str, value: &str) {
+        let definition = Definition {
+            source: source.to_owned(),
+            value: value.to_owned(),
+            provider: None,
+        };
+        match self.index.get(key) {
+            Some(&index) => self.vars[index].definitions.push(definition),
+            None => {
+                self.index.insert(key.to_owned(), self.vars.len());
+                self.vars.push(ResolvedVar {
+                    key: key.to_owned(),
+                    definitions: vec![definition],
+                    expires_at: None,
+                });
+            }
+        }
+    }
+
+    /// Records that the last definition of `key` came from the provider layer at `index`.
+    fn define_provider(&mut self, key: &str, index: usize) {
+        if let Some(&var) = self.index.get(key) {
+            if let Some(definition) = self.vars[var].definitions.last_mut() {
+                definition.provider = Some(index);
+            }
+        }
+    }
+}
+
+/// A variable in a [`ResolvedEnv`], with every definition of it.
+///
+/// The `Display` implementation explains the value, listing the definitions it overrides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedVar {
+    key: String,
+    definitions: Vec<Definition>,
+    expires_at: Option<Instant>,
+}
+
+impl ResolvedVar {
+    /// Returns the name of the variable.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the effective value.
+    pub fn value(&self) -> &str {
+        self.effective().value()
+    }
+
+    /// Returns the source of the effective value.
+    pub fn source(&self) -> &str {
+        self.effective().source()
+    }
+
+    /// Returns every definition in precedence order, so that the last one is effective.
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    /// Returns when the effective value expires, if it was fetched from a provider and has a
+    /// ttl set with [`Layers::ttls`].
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    /// Returns `true` if more than one layer defined the variable.
+    pub fn is_overridden(&self) -> bool {
+        self.definitions.len() > 1
+    }
+
+    fn effective(&self) -> &Definition {
+        self.definitions
+            .last()
+            .expect("a resolved variable has a definition")
+    }
+}
+
+impl fmt::Display for ResolvedVar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={} from {}", self.key, self.value(), self.source())?;
+        for definition in self.definitions.iter().rev().skip(1) {
+            write!(
+                f,
+                "\n  overrides {} from {}",
+                definition.value, definition.source
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A value given to a variable by one layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    source: String,
+    value: String,
+    /// The index of the provider layer, if the value came from one.
+    provider: Option<usize>,
+}
+
+impl Definition {
+    /// Returns the layer which defined the value: a file path, the name of a map or
+    /// provider, `process environment` or `default`.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the value.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::fs;
+    use std::rc::Rc;
+
+    use crate::schema::VarSpec;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_files_and_process_env() {
+        env::set_var("DOTENVY_LAYERS_TEST_PROCESS", "process");
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join(".env");
+        let local = dir.path().join(".env.local");
+        fs::write(&base, "A=1\nDOTENVY_LAYERS_TEST_PROCESS=file\n").unwrap();
+        fs::write(&local, "A=2\nB=3\n").unwrap();
+
+        let resolved = Layers::new()
+            .file(&base)
+            .file(dir.path().join("missing.env"))
+            .file(&local)
+            .process_env()
+            .resolve()
+            .unwrap();
+
+        let a = resolved.get("A").unwrap();
+        assert_eq!(a.value(), "2");
+        assert_eq!(a.source(), local.display().to_string());
+        let sources: Vec<_> = a.definitions().iter().map(Definition::source).collect();
+        assert_eq!(
+            sources,
+            [base.display().to_string(), local.display().to_string()]
+        );
+
+        let process = resolved.get("DOTENVY_LAYERS_TEST_PROCESS").unwrap();
+        assert_eq!(process.value(), "process");
+        assert_eq!(process.source(), "process environment");
+        assert!(process.is_overridden());
+        assert!(!resolved.get("B").unwrap().is_overridden());
+    }
+
+    #[test]
+    fn test_resolve_loader_defaults() {
+        let fs = crate::MemoryFileSystem::new("/app").file("/app/.env", "PORT=9090\n");
+        let loader = EnvLoader::new()
+            .file_system(fs)
+            .defaults(vec![("PORT", "8080"), ("HOST", "localhost")]);
+        let base: EnvMap = vec![("HOST", "example.com")].into_iter().collect();
+        let resolved = Layers::new()
+            .map("base", base)
+            .loader(loader)
+            .resolve()
+            .unwrap();
+
+        assert_eq!(
+            resolved.get("PORT").unwrap().to_string(),
+            "PORT=9090 from /app/.env\n  overrides 8080 from default"
+        );
+        let host = resolved.get("HOST").unwrap();
+        assert_eq!(host.value(), "example.com");
+        assert_eq!(host.definitions()[0].source(), "default");
+    }
+
+    #[test]
+    fn test_refresh_expired_providers() {
+        let rotations = Rc::new(Cell::new(0));
+        let fetches = Rc::new(Cell::new(0));
+        let (rotated, fetched) = (Rc::clone(&rotations), Rc::clone(&fetches));
+        let schema = EnvSchema::new()
+            .var(VarSpec::new("TOKEN").expires_after(Duration::from_secs(0)))
+            .var(VarSpec::new("REGION").expires_after(Duration::from_secs(3600)));
+        let layers = Layers::new()
+            .provider("vault", move || {
+                rotated.set(rotated.get() + 1);
+                let token = format!("token-{}", rotated.get());
+                Ok(vec![("TOKEN", token)].into_iter().collect())
+            })
+            .provider("config", move || {
+                fetched.set(fetched.get() + 1);
+                Ok(vec![("REGION", "eu"), ("NAME", "app")]
+                    .into_iter()
+                    .collect())
+            })
+            .ttls(&schema);
+
+        let mut resolved = layers.resolve().unwrap();
+        assert_eq!(resolved.value("TOKEN"), Some("token-1"));
+        assert_eq!(resolved.get("TOKEN").unwrap().source(), "vault");
+        assert!(resolved.get("NAME").unwrap().expires_at().is_none());
+        let region = resolved.get("REGION").unwrap().expires_at().unwrap();
+        assert_eq!(
+            resolved.next_expiry(),
+            resolved.get("TOKEN").unwrap().expires_at()
+        );
+        assert!(resolved.next_expiry().unwrap() < region);
+
+        assert_eq!(resolved.refresh_expired(&layers).unwrap(), ["TOKEN"]);
+        assert_eq!(resolved.value("TOKEN"), Some("token-2"));
+        // only the provider with an expired value is fetched again
+        assert_eq!((rotations.get(), fetches.get()), (2, 1));
+        assert_eq!(resolved.get("REGION").unwrap().expires_at(), Some(region));
+    }
+
+    #[test]
+    fn test_refresh_without_expiry() {
+        let map: EnvMap = vec![("A", "1")].into_iter().collect();
+        let layers = Layers::new().provider("static", move || Ok(map.clone()));
+        let mut resolved = layers.resolve().unwrap();
+        assert_eq!(resolved.next_expiry(), None);
+        assert!(resolved.refresh_expired(&layers).unwrap().is_empty());
+        assert!(Layers::new()
+            .provider("failing", || Err(Error::EnvVar(env::VarError::NotPresent)))
+            .resolve()
+            .is_err());
+    }
+
+    #[test]
+    fn test_resolve_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        fs::write(&path, "A B\n").unwrap();
+        assert!(Layers::new().file(&path).resolve().is_err());
+    }
+
+    #[test]
+    fn test_to_map_keeps_first_definition_order() {
+        let first: EnvMap = vec![("A", "1"), ("B", "1")].into_iter().collect();
+        let second: EnvMap = vec![("C", "2"), ("A", "2")].into_iter().collect();
+        let resolved = Layers::new()
+            .map("first", first)
+            .map("second", second)
+            .resolve()
+            .unwrap();
+        let map = resolved.to_map();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [("A", "2"), ("B", "1"), ("C", "2")]
+        );
+        assert_eq!(resolved.value("A"), Some("2"));
+        assert_eq!(resolved.len(), 3);
+    }
+}
@@ -3,27 +3,94 @@
 //!
 //! This library loads environment variables from a *.env* file. This is convenient for dev environments.
 
+mod apply;
+mod bootstrap;
+#[cfg(feature = "clap")]
+mod clap;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+mod credentials;
+mod decoding;
+mod document;
+#[cfg(feature = "encryption")]
+mod encryption;
 mod errors;
+#[cfg(feature = "figment")]
+mod figment;
+mod filesystem;
 mod find;
+mod format;
+mod incremental;
 mod iter;
+mod layers;
+mod loader;
+mod map;
+mod matcher;
 mod parse;
+mod schema;
+mod spawn;
+mod suggest;
+mod syntax;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod watch;
+mod writer;
 
 use std::env::{self, Vars};
-use std::ffi::OsStr;
-use std::fs::File;
-use std::io;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Once;
 
+pub use crate::apply::{apply_missing, AppliedReport, LoadReport};
+pub use crate::bootstrap::{bootstrap, Bootstrap, BootstrapReport};
+#[cfg(feature = "clap")]
+pub use crate::clap::{ArgOrigin, ClapEnv, ClapValueSource};
+#[cfg(feature = "config")]
+pub use crate::config::DotenvSource;
+pub use crate::credentials::{SecretsDir, SystemdCredentials};
+pub use crate::decoding::Decoding;
+pub use crate::document::{Annotation, Document, Entry, Line, QuoteStyle};
+#[cfg(feature = "encryption")]
+pub use crate::encryption::{PrivateKey, PublicKey, ENCRYPTED_PREFIX, PRIVATE_KEY_VAR};
 pub use crate::errors::*;
+#[cfg(feature = "figment")]
+pub use crate::figment::DotenvProvider;
+use crate::filesystem::open_file;
+pub use crate::filesystem::{FileSystem, MemoryFileSystem, StdFileSystem};
 use crate::find::Finder;
-pub use crate::iter::Iter;
+#[cfg(feature = "json")]
+pub use crate::format::Json;
+#[cfg(feature = "toml")]
+pub use crate::format::Toml;
+#[cfg(feature = "yaml")]
+pub use crate::format::Yaml;
+pub use crate::format::{
+    escape_value, escape_value_with_syntax, unescape_value, Dotenv, EnvFormat, Properties,
+};
+pub use crate::incremental::IncrementalParser;
+pub use crate::iter::{EnvIterator, FilterKeys, Iter, MapValues, TakePrefix};
+pub use crate::layers::{Definition, Layers, ResolvedEnv, ResolvedVar};
+pub use crate::loader::{EmptyValues, EnvLoader};
+pub use crate::map::{EnvMap, EnvMapIntoIter, KeyInterner};
+pub use crate::matcher::KeyMatcher;
+pub use crate::schema::{EnvSchema, VarSpec, VarType, Violation, ViolationKind};
+pub use crate::spawn::{spawn_matrix, spawn_matrix_override, MatrixChild};
+pub use crate::syntax::{BareKeys, Syntax, Trim};
+pub use crate::watch::Watcher;
+pub use crate::writer::{EnvWriter, GeneratedStatus};
 
 static START: Once = Once::new();
 
 /// Gets the value for an environment variable.
 ///
-/// The value is `Ok(s)` if the environment variable is present and valid unicode.
+/// The value is `Ok(s)` if the environment variable is present and valid unicode. If it is not
+/// present, [`Error::VarNotFound`] names the missing variable and suggests similarly named
+/// variables which are set, to help spot typos.
 ///
 /// Note: this function gets values from any visible environment variable key,
 /// regardless of whether a *.env* file was loaded.
@@ -38,10 +105,72 @@ static START: Once = Once::new();
 /// # }
 /// ```
 pub fn var<K: AsRef<OsStr>>(key: K) -> Result<String> {
+    let key = key.as_ref();
+    var_opt(key)?.ok_or_else(|| {
+        let key = key.to_string_lossy().into_owned();
+        let vars: Vec<_> = env::vars_os()
+            .filter_map(|(key, _)| key.into_string().ok())
+            .collect();
+        Error::VarNotFound {
+            suggestions: suggest::suggestions(&key, vars.iter().map(String::as_str)),
+            key,
+        }
+    })
+}
+
+/// Gets the value for an environment variable, or `None` if it is not set.
+///
+/// Unlike [`var`], a missing variable is not an error, but a value which is not valid unicode
+/// still is.
+///
+/// # Examples:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// if dotenvy::var_opt("FEATURE_FLAG")?.is_some() {
+///     println!("feature enabled");
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn var_opt<K: AsRef<OsStr>>(key: K) -> Result<Option<String>> {
     START.call_once(|| {
         dotenv().ok();
     });
-    env::var(key).map_err(Error::EnvVar)
+    match env::var(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(err) => Err(Error::EnvVar(err)),
+    }
+}
+
+/// Gets the value for an environment variable, or `default` if it is not set.
+///
+/// # Examples:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let port = dotenvy::var_or("PORT", "8080")?;
+/// #     Ok(())
+/// # }
+/// ```
+pub fn var_or<K: AsRef<OsStr>, V: Into<String>>(key: K, default: V) -> Result<String> {
+    var_or_else(key, || default.into())
+}
+
+/// Gets the value for an environment variable, or computes a default with `f` if it is not set.
+///
+/// # Examples:
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let workers = dotenvy::var_or_else("WORKERS", || num_cpus().to_string())?;
+/// #     Ok(())
+/// # }
+/// # fn num_cpus() -> usize { 4 }
+/// ```
+pub fn var_or_else<K: AsRef<OsStr>, F: FnOnce() -> String>(key: K, f: F) -> Result<String> {
+    var_opt(key).map(|value| value.unwrap_or_else(f))
 }
 
 /// Returns an iterator of `(key, value)` pairs for all environment variables of the current process.
@@ -61,6 +190,58 @@ pub fn vars() -> Vars {
     env::vars()
 }
 
+/// Returns a snapshot of the environment variables whose keys start with `prefix`.
+///
+/// Keys keep their prefix. Like [`vars`], the *.env* file is loaded first if this is the first
+/// access to the environment through this crate.
+///
+/// # Examples:
+///
+/// ```no_run
+/// let app_vars = dotenvy::vars_with_prefix("APP_");
+/// for (key, value) in app_vars.iter() {
+///     println!("{}={}", key, value);
+/// }
+/// ```
+pub fn vars_with_prefix(prefix: &str) -> EnvMap {
+    vars_filtered(|key| key.starts_with(prefix))
+}
+
+/// Returns a snapshot of the environment variables whose keys match `pattern`.
+///
+/// The pattern may match anywhere in the key; anchor it with `^` and `$` to match whole keys.
+///
+/// # Examples:
+///
+/// ```no_run
+/// let pattern = regex::Regex::new("^(APP|DB)_").unwrap();
+/// let vars = dotenvy::vars_matching(&pattern);
+/// ```
+#[cfg(feature = "regex")]
+pub fn vars_matching(pattern: &regex::Regex) -> EnvMap {
+    vars_filtered(|key| pattern.is_match(key))
+}
+
+/// Returns a snapshot of the environment variables whose keys satisfy `predicate`.
+///
+/// Unlike [`vars`], this does not panic: variables whose key or value is not valid unicode are
+/// skipped.
+///
+/// # Examples:
+///
+/// ```no_run
+/// let vars = dotenvy::vars_filtered(|key| key.ends_with("_URL"));
+/// ```
+pub fn vars_filtered<F: FnMut(&str) -> bool>(mut predicate: F) -> EnvMap {
+    START.call_once(|| {
+        dotenv().ok();
+    });
+    env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(key, _)| predicate(key))
+        .collect()
+}
+
 /// Loads environment variables from the specified path.
 ///
 /// If variables with the same names already exist in the environment, then their values will be
@@ -83,8 +264,27 @@ pub fn vars() -> Vars {
 /// # }
 /// ```
 pub fn from_path<P: AsRef<Path>>(path: P) -> Result<()> {
-    let iter = Iter::new(File::open(path).map_err(Error::Io)?);
-    iter.load()
+    load_path(path.as_ref(), Iter::load)
+}
+
+/// Loads environment variables from the specified path like [`from_path`], returning which
+/// keys were set and which were skipped because they already existed.
+///
+/// A key which is declared multiple times is only reported once.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let report = dotenvy::from_path_report("path/to/.env")?;
+/// println!("set {:?}, skipped {:?}", report.applied(), report.skipped());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn from_path_report<P: AsRef<Path>>(path: P) -> Result<LoadReport> {
+    let path = path.as_ref();
+    let keys = load_path(path, Iter::load_report)?;
+    Ok(LoadReport::new(path.to_owned(), keys))
 }
 
 /// Loads environment variables from the specified path,
@@ -108,8 +308,7 @@ pub fn from_path<P: AsRef<Path>>(path: P) -> Result<()> {
 /// # }
 /// ```
 pub fn from_path_override<P: AsRef<Path>>(path: P) -> Result<()> {
-    let iter = Iter::new(File::open(path).map_err(Error::Io)?);
-    iter.load_override()
+    load_path(path.as_ref(), Iter::load_override)
 }
 
 /// Returns an iterator over environment variables from the specified path.
@@ -128,7 +327,63 @@ pub fn from_path_override<P: AsRef<Path>>(path: P) -> Result<()> {
 /// # }
 /// ```
 pub fn from_path_iter<P: AsRef<Path>>(path: P) -> Result<Iter<File>> {
-    Ok(Iter::new(File::open(path).map_err(Error::Io)?))
+    let path = path.as_ref();
+    Ok(Iter::new(open_file(path).map_err(|err| err.in_file(path))?))
+}
+
+/// Opens the file at `path` and loads it with `load`, wrapping any error in [`Error::File`].
+fn load_path<T>(path: &Path, load: impl FnOnce(Iter<File>) -> Result<T>) -> Result<T> {
+    let file = open_file(path).map_err(|err| err.in_file(path))?;
+    load(Iter::new(file)).map_err(|err| err.in_file(path))
+}
+
+/// Loads environment variables from the JSON object at the specified path.
+///
+/// Nested objects are flattened by joining keys with `_`. Use [`EnvLoader`] with
+/// [`Json`] to configure the separator.
+///
+/// If variables with the same names already exist in the environment, then their values will be
+/// preserved.
+///
+/// If you wish to ensure all variables are loaded from your JSON file, ignoring variables
+/// already existing in the environment, then use [`from_json_path_override`] instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// dotenvy::from_json_path("secrets.json")?;
+/// #     Ok(())
+/// # }
+/// ```
+#[cfg(feature = "json")]
+pub fn from_json_path<P: AsRef<Path>>(path: P) -> Result<()> {
+    EnvLoader::new().path(path).format(Json::new()).load()?;
+    Ok(())
+}
+
+/// Loads environment variables from the JSON object at the specified path,
+/// overriding existing environment variables.
+///
+/// If you want the existing environment to take precedence,
+/// or if you want to be able to override environment variables on the command line,
+/// then use [`from_json_path`] instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// dotenvy::from_json_path_override("secrets.json")?;
+/// #     Ok(())
+/// # }
+/// ```
+#[cfg(feature = "json")]
+pub fn from_json_path_override<P: AsRef<Path>>(path: P) -> Result<()> {
+    EnvLoader::new()
+        .path(path)
+        .format(Json::new())
+        .load_override()?;
+    Ok(())
 }
 
 /// Loads environment variables from the specified file.
@@ -160,10 +415,27 @@ pub fn from_path_iter<P: AsRef<Path>>(path: P) -> Result<Iter<File>> {
 /// ```
 pub fn from_filename<P: AsRef<Path>>(filename: P) -> Result<PathBuf> {
     let (path, iter) = Finder::new().filename(filename.as_ref()).find()?;
-    iter.load()?;
+    iter.load().map_err(|err| err.in_file(&path))?;
     Ok(path)
 }
 
+/// Loads environment variables from the specified file like [`from_filename`], returning
+/// which keys were set and which were skipped because they already existed.
+///
+/// # Examples
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let report = dotenvy::from_filename_report("custom.env")?;
+/// println!("loaded {}", report.path().display());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn from_filename_report<P: AsRef<Path>>(filename: P) -> Result<LoadReport> {
+    let (path, iter) = Finder::new().filename(filename.as_ref()).find()?;
+    let keys = iter.load_report().map_err(|err| err.in_file(&path))?;
+    Ok(LoadReport::new(path, keys))
+}
+
 /// Loads environment variables from the specified file,
 /// overriding existing environment variables.
 ///
@@ -192,10 +464,64 @@ pub fn from_filename<P: AsRef<Path>>(filename: P) -> Result<PathBuf> {
 /// ```
 pub fn from_filename_override<P: AsRef<Path>>(filename: P) -> Result<PathBuf> {
     let (path, iter) = Finder::new().filename(filename.as_ref()).find()?;
-    iter.load_override()?;
+    iter.load_override().map_err(|err| err.in_file(&path))?;
     Ok(path)
 }
 
+/// Deserializes the variables in the specified file into `T`, without modifying the
+/// environment.
+///
+/// The file is searched for like [`from_filename`] does and its variables are fed to an
+/// [`envy`](https://crates.io/crates/envy)-compatible deserializer, so a struct which works
+/// with `envy::from_env` works here as well: field names match variable names
+/// case-insensitively and values are parsed into the field types. Where multiple declarations
+/// for the same environment variable exist in the file, the *last one* is used.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[derive(serde::Deserialize)]
+/// struct Config {
+///     port: u16,
+///     database_url: String,
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let config: Config = dotenvy::from_filename_into(".env")?;
+/// println!("listening on {}", config.port);
+/// #     Ok(())
+/// # }
+/// ```
+#[cfg(feature = "deserialize")]
+pub fn from_filename_into<T, P>(filename: P) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    P: AsRef<Path>,
+{
+    deserialize_map(EnvLoader::new().filename(filename).parse()?)
+}
+
+/// Deserializes the variables in the file at the specified path into `T`, without modifying
+/// the environment.
+///
+/// This works like [`from_filename_into`], without searching parent directories.
+#[cfg(feature = "deserialize")]
+pub fn from_path_into<T, P>(path: P) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    P: AsRef<Path>,
+{
+    deserialize_map(EnvLoader::new().path(path).parse()?)
+}
+
+#[cfg(feature = "deserialize")]
+fn deserialize_map<T: serde::de::DeserializeOwned>(map: EnvMap) -> Result<T> {
+    envy::from_iter(map).map_err(|err| Error::InvalidFormat {
+        format: "environment",
+        source: Box::new(err),
+    })
+}
+
 ///  Returns an iterator over environment variables from the specified file.
 ///
 /// # Examples
@@ -209,7 +535,6 @@ pub fn from_filename_override<P: AsRef<Path>>(filename: P) -> Result<PathBuf> {
 /// #     Ok(())
 /// # }
 /// ```
-
 pub fn from_filename_iter<P: AsRef<Path>>(filename: P) -> Result<Iter<File>> {
     let (_, iter) = Finder::new().filename(filename.as_ref()).find()?;
     Ok(iter)
@@ -327,10 +652,57 @@ pub fn from_read_iter<R: io::Read>(reader: R) -> Iter<R> {
 /// ```
 pub fn dotenv() -> Result<PathBuf> {
     let (path, iter) = Finder::new().find()?;
-    iter.load()?;
+    iter.load().map_err(|err| err.in_file(&path))?;
     Ok(path)
 }
 
+/// Loads the *.env* file from the current directory or parents like [`dotenv`], returning
+/// which keys were set and which were skipped because they already existed.
+///
+/// This is useful for startup logging and for precise assertions in tests.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let report = dotenvy::dotenv_report()?;
+/// for key in report.skipped() {
+///     println!("{} is already set, ignoring the .env value", key);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn dotenv_report() -> Result<LoadReport> {
+    let (path, iter) = Finder::new().find()?;
+    let keys = iter.load_report().map_err(|err| err.in_file(&path))?;
+    Ok(LoadReport::new(path, keys))
+}
+
+/// Loads the *.env* file from the current directory or parents like [`dotenv_report`], if
+/// there is one.
+///
+/// A missing file yields `Ok(None)`, while parse and permission errors are still returned.
+/// Prefer this over `dotenv().ok()`, which also hides mistakes in the file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// match dotenvy::load_optional()? {
+///     Some(report) => println!("loaded {}", report.path().display()),
+///     None => println!("no .env file found"),
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn load_optional() -> Result<Option<LoadReport>> {
+    match dotenv_report() {
+        Ok(report) => Ok(Some(report)),
+        Err(err) if err.not_found() => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
 /// Loads all variables found in the `reader` into the environment,
 /// overriding any existing environment variables of the same name.
 ///
@@ -350,7 +722,7 @@ pub fn dotenv() -> Result<PathBuf> {
 /// ```
 pub fn dotenv_override() -> Result<PathBuf> {
     let (path, iter) = Finder::new().find()?;
-    iter.load_override()?;
+    iter.load_override().map_err(|err| err.in_file(&path))?;
     Ok(path)
 }
 
@@ -371,3 +743,207 @@ pub fn dotenv_iter() -> Result<iter::Iter<File>> {
     let (_, iter) = Finder::new().find()?;
     Ok(iter)
 }
+
+/// Formats the *.env* file at the specified path in place, much like `cargo fmt`.
+///
+/// Spacing around `=`, quoting and trailing whitespace are normalized, while comments and the
+/// order of variables are preserved. See [`Document::normalize`] for the exact rules.
+///
+/// Returns `true` if the file was changed. The file is left untouched if it cannot be parsed,
+/// and is otherwise replaced atomically, following symbolic links.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// if dotenvy::fmt(".env")? {
+///     println!("formatted .env");
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn fmt<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let path = path.as_ref();
+    filesystem::check_is_file(&StdFileSystem, path)?;
+    let input = fs::read_to_string(path).map_err(Error::Io)?;
+    let mut document = Document::parse(&input)?;
+    document.normalize();
+    let output = document.to_string();
+    if output == input {
+        return Ok(false);
+    }
+    write_atomic(path, output.as_bytes())?;
+    Ok(true)
+}
+
+/// Sets a single variable in the *.env* file at the specified path, leaving every other line
+/// untouched.
+///
+/// The existing declaration of `key` keeps its position, spacing, comment and, where the new
+/// value allows it, its quote style. If the file does not declare `key`, it is appended to
+/// the end; if the file does not exist, it is created. See [`Document::set`] for details.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// dotenvy::update_key(".env", "API_TOKEN", "s3cr3t")?;
+/// #     Ok(())
+/// # }
+/// ```
+pub fn update_key<P: AsRef<Path>>(path: P, key: &str, value: &str) -> Result<()> {
+    let path = path.as_ref();
+    filesystem::check_is_file(&StdFileSystem, path)?;
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(Error::Io(err)),
+    };
+    let mut document = Document::parse(&input)?;
+    document.set(key, value)?;
+    write_atomic(path, document.to_string().as_bytes())
+}
+
+/// Appends `key` with the value `default` to the *.env* file at the specified path, unless
+/// the file already declares it.
+///
+/// Existing values are never changed, so setup scripts can seed defaults without clobbering
+/// the user's customizations. If the file does not exist, it is created.
+///
+/// Returns `true` if the key was added. The file is replaced atomically, so other processes
+/// see either the old or the new contents, never a partial write. Reading, checking and
+/// writing the file is not atomic as a whole, though: no lock is taken, so if another process
+/// changes the file at the same time, one of the changes is lost.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// if dotenvy::ensure_key(".env", "PORT", "8080")? {
+///     println!("added PORT to .env");
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn ensure_key<P: AsRef<Path>>(path: P, key: &str, default: &str) -> Result<bool> {
+    let path = path.as_ref();
+    filesystem::check_is_file(&StdFileSystem, path)?;
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(Error::Io(err)),
+    };
+    let mut document = Document::parse(&input)?;
+    if document.get(key).is_some() {
+        return Ok(false);
+    }
+    document.set(key, default)?;
+    write_atomic(path, document.to_string().as_bytes())?;
+    Ok(true)
+}
+
+/// Writes the current environment variables whose keys satisfy `filter` to a *.env* file at the
+/// specified path, replacing its contents.
+///
+/// Variables are sorted by key and quoted so that loading the file restores the same values,
+/// which makes it easy to capture a working environment for debugging. Variables whose key or
+/// value is not valid unicode, or whose key cannot be declared in a *.env* file, are left out.
+///
+/// Returns the variables which were written, in the same order. The file is replaced
+/// atomically, so other processes never see a partial write, and symbolic links are followed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let vars = dotenvy::dump_process_env("captured.env", |key| key.starts_with("APP_"))?;
+/// println!("captured {} variables", vars.len());
+/// #     Ok(())
+/// # }
+/// ```
+pub fn dump_process_env<P: AsRef<Path>, F: FnMut(&str) -> bool>(
+    path: P,
+    mut filter: F,
+) -> Result<EnvMap> {
+    let path = path.as_ref();
+    filesystem::check_is_file(&StdFileSystem, path)?;
+    let mut vars: Vec<_> = env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(key, _)| format::is_valid_key(key) && filter(key))
+        .collect();
+    vars.sort_unstable();
+    let map: EnvMap = vars.into_iter().collect();
+    let mut output = Vec::new();
+    EnvWriter::new().write(&map, &mut output)?;
+    write_atomic(path, &output)?;
+    Ok(map)
+}
+
+/// Replaces the file at `path` by writing a temporary file next to it and renaming it.
+///
+/// Symbolic links are followed, so the file they point to is replaced rather than the link.
+/// Each call uses its own temporary file, which is flushed to disk before the rename.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+    let path = resolve_symlinks(path).map_err(Error::Io)?;
+    let file_name = path.file_name().ok_or_else(|| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path does not name a file",
+        ))
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(Error::Io)?;
+    let result = file
+        .write_all(contents)
+        .and_then(|()| match fs::metadata(&path) {
+            Ok(metadata) => fs::set_permissions(&temp_path, metadata.permissions()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        })
+        .and_then(|()| file.sync_all())
+        .and_then(|()| {
+            drop(file);
+            fs::rename(&temp_path, &path)
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.map_err(Error::Io)
+}
+
+/// Returns the path of the file `path` refers to, following symbolic links, including one to
+/// a file which does not exist yet.
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_owned();
+    // the limit most systems place on chains of links
+    for _ in 0..40 {
+        match fs::canonicalize(&path) {
+            Ok(resolved) => return Ok(resolved),
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            Err(_) => {}
+        }
+        match fs::read_link(&path) {
+            Ok(target) => path = path.parent().unwrap_or_else(|| Path::new("")).join(target),
+            // the file does not exist, and is not a link
+            Err(_) => return Ok(path),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "too many levels of symbolic links",
+    ))
+}
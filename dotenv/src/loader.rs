@@ -0,0 +1,512 @@
+use std::borrow::Cow;
+use std::env;
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::apply;
+use crate::decoding::{self, Decoding};
+#[cfg(feature = "encryption")]
+use crate::encryption::PrivateKey;
+use crate::errors::*;
+use crate::filesystem::{self, FileSystem, StdFileSystem};
+use crate::find;
+use crate::format::{Dotenv, EnvFormat};
+use crate::map::{EnvMap, KeyInterner};
+use crate::matcher::KeyMatcher;
+use crate::watch::Watcher;
+use crate::writer::{self, GeneratedStatus};
+
+/// Where an [`EnvLoader`] reads its variables from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Source {
+    /// Searched for in the current directory and its parents.
+    Filename(PathBuf),
+    /// Opened directly.
+    Path(PathBuf),
+}
+
+impl Source {
+    /// Returns the path of the file, searching for it if necessary.
+    #[cfg(any(feature = "config", feature = "figment"))]
+    pub(crate) fn resolve(&self) -> Result<PathBuf> {
+        self.resolve_in(&StdFileSystem)
+    }
+
+    /// Returns the path of the file, searching for it in `fs` if necessary.
+    pub(crate) fn resolve_in(&self, fs: &dyn FileSystem) -> Result<PathBuf> {
+        match self {
+            Source::Filename(filename) => {
+                find::find_in(fs, &fs.current_dir().map_err(Error::Io)?, filename)
+            }
+            Source::Path(path) => Ok(path.clone()),
+        }
+    }
+}
+
+/// Maps a variable name to a configuration key for the `config` and `figment` integrations.
+///
+/// Keys without the `prefix` and a `_` are skipped. The rest are lowercased, and `separator`
+/// is replaced with `.` to form a nested path.
+#[cfg(any(feature = "config", feature = "figment"))]
+pub(crate) fn config_key(
+    key: &str,
+    prefix: Option<&str>,
+    separator: Option<&str>,
+) -> Option<String> {
+    let key = match prefix {
+        Some(prefix) => key.strip_prefix(prefix)?.strip_prefix('_')?,
+        None => key,
+    };
+    let key = key.to_lowercase();
+    Some(match separator {
+        Some(separator) => key.replace(separator, "."),
+        None => key,
+    })
+}
+
+/// What an [`EnvLoader`] does with variables declared with an empty value, as in `KEY=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyValues {
+    /// Sets the variable to an empty string. This is the default.
+    Set,
+    /// Leaves the variable out, as if it was not declared.
+    Skip,
+    /// Leaves the variable out, and removes it from the environment when loading with
+    /// [`load_override`](EnvLoader::load_override).
+    Unset,
+}
+
+/// Called with the old and new key when a file uses a renamed key.
+type OnRenamed = dyn Fn(&str, &str);
+
+/// Called with the path of a file which is not valid UTF-8.
+type OnInvalidUtf8 = dyn Fn(&Path);
+
+/// Called with the path of a generated file which was modified since it was written.
+type OnHandModified = dyn Fn(&Path);
+
+/// The variables of a file, before the defaults are added.
+pub(crate) struct Parsed {
+    pub(crate) map: EnvMap,
+    /// The keys of empty values left out of `map`.
+    empty: Vec<String>,
+    /// The raw values of variables decoded with [`Decoding::Raw`], which `map` has lossy
+    /// versions of.
+    raw: Vec<(String, OsString)>,
+}
+
+/// A configurable loader for environment files.
+///
+/// By default, the loader searches for a *.env* file in the current directory or its
+/// parents and parses it with the [`Dotenv`] format. Any other [`EnvFormat`] can be plugged
+/// in with [`format`](EnvLoader::format).
+///
+/// Where multiple declarations for the same environment variable exist in the file, the
+/// *last one* is applied.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::EnvLoader;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = EnvLoader::new().filename("custom.env").load()?;
+/// println!("loaded {} variables", map.len());
+/// #     Ok(())
+/// # }
+/// ```
+pub struct EnvLoader {
+    source: Source,
+    format: Box<dyn EnvFormat>,
+    file_system: Box<dyn FileSystem>,
+    interner: Option<KeyInterner>,
+    empty_values: EmptyValues,
+    decoding: Decoding,
+    on_invalid_utf8: Box<OnInvalidUtf8>,
+    on_hand_modified: Box<OnHandModified>,
+    renames: Vec<(String, String)>,
+    on_renamed: Box<OnRenamed>,
+    apply_only: Vec<KeyMatcher>,
+    apply_except: Vec<KeyMatcher>,
+    defaults: EnvMap,
+    #[cfg(feature = "encryption")]
+    private_key: Option<PrivateKey>,
+}
+
+impl EnvLoader {
+    /// Creates a loader for the *.env* file in the current directory or its parents.
+    pub fn new() -> Self {
+        EnvLoader {
+            source: Source::Filename(PathBuf::from(".env")),
+            format: Box::new(Dotenv::new()),
+            file_system: Box::new(StdFileSystem),
+            interner: None,
+            empty_values: EmptyValues::Set,
+            decoding: Decoding::Strict,
+            on_invalid_utf8: Box::new(|_| {}),
+            on_hand_modified: Box::new(|_| {}),
+            renames: Vec::new(),
+            on_renamed: Box::new(|_, _| {}),
+            apply_only: Vec::new(),
+            apply_except: Vec::new(),
+            defaults: EnvMap::new(),
+            #[cfg(feature = "encryption")]
+            private_key: None,
+        }
+    }
+
+    /// Searches for `filename` in the current directory and its parents.
+    pub fn filename<P: AsRef<Path>>(mut self, filename: P) -> Self {
+        self.source = Source::Filename(filename.as_ref().to_owned());
+        self
+    }
+
+    /// Reads the file at `path`, without searching parent directories.
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.source = Source::Path(path.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the format used to parse the file. Defaults to [`Dotenv`].
+    pub fn format<F: EnvFormat + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+
+    /// Sets the file system used to find and read the file. Defaults to [`StdFileSystem`].
+    pub fn file_system<F: FileSystem + 'static>(mut self, file_system: F) -> Self {
+        self.file_system = Box::new(file_system);
+        self
+    }
+
+    /// Sets the key used to decrypt `encrypted:` values. Defaults to the key in the
+    /// [`DOTENV_PRIVATE_KEY`](crate::PRIVATE_KEY_VAR) environment variable.
+    #[cfg(feature = "encryption")]
+    pub fn private_key(mut self, private_key: PrivateKey) -> Self {
+        self.private_key = Some(private_key);
+        self
+    }
+
+    /// Shares the keys of parsed maps through `interner`, so that loading the same files
+    /// repeatedly, or files which declare the same keys, keeps one allocation per key.
+    pub fn interner(mut self, interner: KeyInterner) -> Self {
+        self.interner = Some(interner);
+        self
+    }
+
+    /// Sets what is done with variables declared with an empty value. Defaults to
+    /// [`EmptyValues::Set`].
+    pub fn empty_values(mut self, empty_values: EmptyValues) -> Self {
+        self.empty_values = empty_values;
+        self
+    }
+
+    /// Sets how files which are not valid UTF-8 are decoded. Defaults to
+    /// [`Decoding::Strict`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::{Decoding, EnvLoader};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let map = EnvLoader::new().decoding(Decoding::Lossy).load()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn decoding(mut self, decoding: Decoding) -> Self {
+        self.decoding = decoding;
+        self
+    }
+
+    /// Sets the function called with the path of a file decoded with [`Decoding::Lossy`] which
+    /// is not valid UTF-8, for example to log a warning. Does nothing by default.
+    pub fn on_invalid_utf8<F: Fn(&Path) + 'static>(mut self, on_invalid_utf8: F) -> Self {
+        self.on_invalid_utf8 = Box::new(on_invalid_utf8);
+        self
+    }
+
+    /// Sets the function called with the path of a file which was generated by an
+    /// [`EnvWriter`](crate::EnvWriter) with a checksum, but modified since, as reported by
+    /// [`generated_status`](EnvLoader::generated_status). The file is still read, so this is
+    /// the place to warn that the changes may be lost when it is generated again. Does nothing
+    /// by default.
+    pub fn on_hand_modified<F: Fn(&Path) + 'static>(mut self, on_hand_modified: F) -> Self {
+        self.on_hand_modified = Box::new(on_hand_modified);
+        self
+    }
+
+    /// Reads the variable `old` as `new`, so that files written before a configuration key
+    /// was renamed keep working during a transition period.
+    ///
+    /// If a file declares both, the value of `new` is kept. Each use of `old` is reported
+    /// through [`on_renamed`](EnvLoader::on_renamed). Renames are applied in the order they
+    /// were added.
+    pub fn rename(mut self, old: impl Into<String>, new: impl Into<String>) -> Self {
+        self.renames.push((old.into(), new.into()));
+        self
+    }
+
+    /// Sets the function called with the old and new key whenever a file uses a key renamed
+    /// with [`rename`](EnvLoader::rename), for example to log a deprecation warning. Does
+    /// nothing by default.
+    pub fn on_renamed<F: Fn(&str, &str) + 'static>(mut self, on_renamed: F) -> Self {
+        self.on_renamed = Box::new(on_renamed);
+        self
+    }
+
+    /// Only sets variables whose keys match `matcher` when loading, keeping the rest in the
+    /// returned map only, so a file shared by several services can be loaded by each of them.
+    ///
+    /// May be called repeatedly to allow several patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::EnvLoader;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let map = EnvLoader::new()
+    ///     .apply_only("API_*")
+    ///     .apply_except("API_DEBUG_*")
+    ///     .load()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn apply_only(mut self, matcher: impl Into<KeyMatcher>) -> Self {
+        self.apply_only.push(matcher.into());
+        self
+    }
+
+    /// Does not set variables whose keys match `matcher` when loading, keeping them in the
+    /// returned map only. Takes precedence over [`apply_only`](EnvLoader::apply_only).
+    ///
+    /// May be called repeatedly to exclude several patterns.
+    pub fn apply_except(mut self, matcher: impl Into<KeyMatcher>) -> Self {
+        self.apply_except.push(matcher.into());
+        self
+    }
+
+    /// Sets default values from code, used for keys which neither the file nor the process
+    /// environment define.
+    ///
+    /// With defaults, a missing file is not an error: the defaults are used on their own, so
+    /// applications can start without a *.env* file. May be called repeatedly, with later
+    /// defaults replacing earlier ones for the same key. [`Layers`](crate::Layers) attributes
+    /// these values to `default`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::EnvLoader;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let map = EnvLoader::new()
+    ///     .defaults(vec![("PORT", "8080"), ("HOST", "localhost")])
+    ///     .load()?;
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn defaults<'a, I, K, V>(mut self, defaults: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Cow<'a, str>>,
+        V: Into<Cow<'a, str>>,
+    {
+        self.defaults.extend(defaults);
+        self
+    }
+
+    /// Returns the path of the file which will be read.
+    ///
+    /// An error is returned if the file cannot be found.
+    pub fn resolve_path(&self) -> Result<PathBuf> {
+        self.source.resolve_in(self.file_system.as_ref())
+    }
+
+    /// Returns a [`Watcher`] for the file which will be read.
+    ///
+    /// A file given by [`path`](EnvLoader::path) is watched even if it does not exist yet. An
+    /// error is returned if a file searched for by [`filename`](EnvLoader::filename) cannot be
+    /// found.
+    pub fn watcher(&self) -> Result<Watcher> {
+        Ok(Watcher::new(vec![self.resolve_path()?]))
+    }
+
+    /// Returns whether the file was generated by an [`EnvWriter`](crate::EnvWriter) with a
+    /// checksum, and if so whether it was edited since, so applications can warn about
+    /// hand-modified generated files.
+    pub fn generated_status(&self) -> Result<GeneratedStatus> {
+        let path = self.resolve_path()?;
+        let mut contents = Vec::new();
+        self.file_system
+            .open(&path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(Error::Io)?;
+        Ok(writer::generated_status(&contents))
+    }
+
+    /// Parses the file without modifying the environment.
+    ///
+    /// With the `encryption` feature, values written by `EnvMap::encrypt_value` are decrypted.
+    /// An error is returned if the file has encrypted values but no private key is available.
+    pub fn parse(&self) -> Result<EnvMap> {
+        self.parse_with_defaults().map(|parsed| parsed.map)
+    }
+
+    /// Parses the file and adds the defaults.
+    fn parse_with_defaults(&self) -> Result<Parsed> {
+        let mut parsed = match self.parse_file() {
+            Err(err) if err.not_found() && !self.defaults.is_empty() => Parsed {
+                map: EnvMap::new(),
+                empty: Vec::new(),
+                raw: Vec::new(),
+            },
+            result => result?,
+        };
+        for (key, value) in self.defaults.iter() {
+            if !parsed.map.contains_key(key) && env::var_os(key).is_none() {
+                parsed.map.insert(key, value);
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the defaults set with [`defaults`](EnvLoader::defaults).
+    pub(crate) fn default_values(&self) -> &EnvMap {
+        &self.defaults
+    }
+
+    /// Parses the file without the defaults.
+    pub(crate) fn parse_file(&self) -> Result<Parsed> {
+        let mut path = self.resolve_path()?;
+        if path.is_relative() {
+            path = self
+                .file_system
+                .current_dir()
+                .map_err(Error::Io)?
+                .join(path);
+        }
+        filesystem::check_is_file(self.file_system.as_ref(), &path)?;
+        let (mut map, invalid) = self.read_file(&path).map_err(|err| Error::File {
+            path: path.clone(),
+            source: Box::new(err),
+        })?;
+        if let Some(interner) = &self.interner {
+            map.intern_keys(interner);
+        }
+        #[cfg(feature = "encryption")]
+        map.decrypt_values(self.private_key.as_ref())?;
+        for (old, new) in &self.renames {
+            if let Some(value) = map.remove(old) {
+                (self.on_renamed)(old, new);
+                if !map.contains_key(new) {
+                    map.insert(new.as_str(), value);
+                }
+            }
+        }
+        let empty = match self.empty_values {
+            EmptyValues::Set => Vec::new(),
+            EmptyValues::Skip | EmptyValues::Unset => map.remove_empty_values(),
+        };
+        let mut raw = Vec::new();
+        #[cfg(unix)]
+        if invalid && self.decoding == Decoding::Raw {
+            for (key, value) in map.iter() {
+                if let Some(value) = decoding::raw_value(value) {
+                    raw.push((key.to_owned(), value));
+                }
+            }
+            for (key, _) in &raw {
+                let lossy = map.get(key).map(decoding::lossy_value).unwrap_or_default();
+                map.insert(key.as_str(), lossy);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = invalid;
+        Ok(Parsed { map, empty, raw })
+    }
+
+    /// Reads and decodes the file at `path`, returning its variables and whether it contained
+    /// invalid UTF-8.
+    fn read_file(&self, path: &Path) -> Result<(EnvMap, bool)> {
+        let mut bytes = Vec::new();
+        self.file_system
+            .open(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(Error::Io)?;
+        if writer::generated_status(&bytes) == GeneratedStatus::Modified {
+            (self.on_hand_modified)(path);
+        }
+        match self.decoding {
+            Decoding::Strict => Ok((self.format.parse(&mut bytes.as_slice())?, false)),
+            decoding => {
+                let decoded = decoding::decode(bytes, decoding)?;
+                if decoded.invalid && decoding == Decoding::Lossy {
+                    (self.on_invalid_utf8)(path);
+                }
+                let map = self.format.parse(&mut decoded.text.as_bytes())?;
+                Ok((map, decoded.invalid))
+            }
+        }
+    }
+
+    /// Loads the file into the environment, preserving any existing environment variables
+    /// of the same name.
+    ///
+    /// Returns all variables parsed from the file.
+    pub fn load(&self) -> Result<EnvMap> {
+        let parsed = self.parse_with_defaults()?;
+        let report = apply::apply_missing(&self.selected(&parsed.map));
+        for (key, value) in parsed.raw {
+            if report.applied().contains(&key) {
+                env::set_var(key, value);
+            }
+        }
+        Ok(parsed.map)
+    }
+
+    /// Loads the file into the environment, overriding any existing environment variables of
+    /// the same name.
+    ///
+    /// Returns all variables parsed from the file.
+    pub fn load_override(&self) -> Result<EnvMap> {
+        let parsed = self.parse_with_defaults()?;
+        let report = apply::apply_all(&self.selected(&parsed.map));
+        for (key, value) in parsed.raw {
+            if report.is_applied(&key) {
+                env::set_var(key, value);
+            }
+        }
+        if self.empty_values == EmptyValues::Unset {
+            for key in parsed.empty.into_iter().filter(|key| self.is_selected(key)) {
+                env::remove_var(key);
+            }
+        }
+        Ok(parsed.map)
+    }
+
+    /// Returns the variables in `map` which are set when loading.
+    fn selected<'a>(&self, map: &'a EnvMap) -> Cow<'a, EnvMap> {
+        if self.apply_only.is_empty() && self.apply_except.is_empty() {
+            return Cow::Borrowed(map);
+        }
+        Cow::Owned(
+            map.iter()
+                .filter(|(key, _)| self.is_selected(key))
+                .collect(),
+        )
+    }
+
+    fn is_selected(&self, key: &str) -> bool {
+        (self.apply_only.is_empty() || self.apply_only.iter().any(|m| m.matches(key)))
+            && !self.apply_except.iter().any(|m| m.matches(key))
+    }
+}
+
+impl Default for EnvLoader {
+    fn default() -> Self {
+        EnvLoader::new()
+    }
+}
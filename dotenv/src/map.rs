@@ -0,0 +1,443 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::FromIterator;
+use std::sync::{Arc, Mutex};
+use std::vec;
+
+/// An ordered map of environment variables.
+///
+/// Keys keep the position of their first insertion, so maps parsed from a file iterate in
+/// file order. Inserting an existing key replaces its value in place.
+///
+/// Each key is allocated once and shared between the map's order and its index, so cloning a
+/// map does not copy its keys. Short values are stored inline without a heap allocation. Maps
+/// created with [`with_interner`](EnvMap::with_interner) also share keys with each other.
+#[derive(Clone, Default)]
+pub struct EnvMap {
+    entries: Vec<(Arc<str>, SmallString)>,
+    index: HashMap<Arc<str>, usize>,
+    interner: Option<KeyInterner>,
+}
+
+impl EnvMap {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map which takes its keys from `interner`.
+    ///
+    /// Maps sharing an interner hold a single allocation for each distinct key, which reduces
+    /// memory use when the same files are loaded repeatedly or many files declare the same
+    /// keys.
+    pub fn with_interner(interner: &KeyInterner) -> Self {
+        EnvMap {
+            interner: Some(interner.clone()),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the number of variables in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map contains no variables.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the value of `key`, if present.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.index
+            .get(key)
+            .map(|&index| self.entries[index].1.as_str())
+    }
+
+    /// Returns `true` if the map contains `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the value of `key`, if present and not empty.
+    ///
+    /// Use this for fallbacks which should also apply to `KEY=`, and [`get`](EnvMap::get) for
+    /// those which should only apply when the key is absent.
+    pub fn get_non_empty(&self, key: &str) -> Option<&str> {
+        self.get(key).filter(|value| !value.is_empty())
+    }
+
+    /// Returns `true` if the map contains `key` with an empty value, as declared by `KEY=`.
+    ///
+    /// Returns `false` if the key is absent.
+    pub fn is_empty_value(&self, key: &str) -> bool {
+        self.get(key).map_or(false, str::is_empty)
+    }
+
+    /// Inserts a variable, returning the previous value of `key` if there was one.
+    ///
+    /// Borrowed keys are only copied if they are new to the map and to its interner, and
+    /// owned values are moved rather than copied unless they are stored inline.
+    pub fn insert<'a>(
+        &mut self,
+        key: impl Into<Cow<'a, str>>,
+        value: impl Into<Cow<'a, str>>,
+    ) -> Option<String> {
+        let key: Cow<str> = key.into();
+        let value = SmallString::from(value.into());
+        match self.index.get(key.as_ref()) {
+            Some(&index) => Some(std::mem::replace(&mut self.entries[index].1, value).into()),
+            None => {
+                let key = match &self.interner {
+                    Some(interner) => interner.intern(&key),
+                    None => Arc::from(key.as_ref()),
+                };
+                self.index.insert(Arc::clone(&key), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes `key` from the map, returning its value if it was present.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+        for (key, _) in &self.entries[index..] {
+            if let Some(position) = self.index.get_mut(key) {
+                *position -= 1;
+            }
+        }
+        Some(value.into())
+    }
+
+    /// Removes every variable with an empty value, returning their keys.
+    pub(crate) fn remove_empty_values(&mut self) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.entries.retain(|(key, value)| {
+            let empty = value.as_str().is_empty();
+            if empty {
+                removed.push(key.to_string());
+            }
+            !empty
+        });
+        if !removed.is_empty() {
+            self.index = self
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(index, (key, _))| (Arc::clone(key), index))
+                .collect();
+        }
+        removed
+    }
+
+    /// Returns an iterator over the `(key, value)` pairs in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_str()))
+    }
+
+    /// Returns an iterator over the keys in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(key, _)| key.as_ref())
+    }
+
+    /// Replaces the keys with those of `interner`, which is used for later insertions too.
+    pub(crate) fn intern_keys(&mut self, interner: &KeyInterner) {
+        let mut keys = interner.lock();
+        for (key, _) in &mut self.entries {
+            match keys.get(key.as_ref()) {
+                Some(interned) => *key = Arc::clone(interned),
+                None => {
+                    keys.insert(Arc::clone(key));
+                }
+            }
+        }
+        drop(keys);
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, (key, _))| (Arc::clone(key), index))
+            .collect();
+        self.interner = Some(interner.clone());
+    }
+}
+
+impl PartialEq for EnvMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for EnvMap {}
+
+impl fmt::Debug for EnvMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>> FromIterator<(K, V)> for EnvMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = EnvMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>> Extend<(K, V)> for EnvMap {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl IntoIterator for EnvMap {
+    type Item = (String, String);
+    type IntoIter = EnvMapIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        EnvMapIntoIter {
+            entries: self.entries.into_iter(),
+        }
+    }
+}
+
+/// An owning iterator over the variables of an [`EnvMap`], in insertion order.
+pub struct EnvMapIntoIter {
+    entries: vec::IntoIter<(Arc<str>, SmallString)>,
+}
+
+impl Iterator for EnvMapIntoIter {
+    type Item = (String, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next()
+            .map(|(key, value)| (key.as_ref().to_owned(), value.into()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl ExactSizeIterator for EnvMapIntoIter {}
+
+/// A set of keys shared between [`EnvMap`]s, so that each distinct key is allocated once.
+///
+/// Cloning an interner is cheap, and clones share the same keys. Keys are kept until every
+/// clone of the interner and every map using it is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvMap, KeyInterner};
+///
+/// let interner = KeyInterner::new();
+/// let mut base = EnvMap::with_interner(&interner);
+/// base.insert("DATABASE_URL", "postgres://localhost/app");
+/// let mut local = EnvMap::with_interner(&interner);
+/// local.insert("DATABASE_URL", "postgres://localhost/app_test");
+///
+/// // both maps share the allocation of `DATABASE_URL`
+/// assert_eq!(interner.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KeyInterner {
+    keys: Arc<Mutex<HashSet<Arc<str>>>>,
+}
+
+impl KeyInterner {
+    /// Creates an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if no keys have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn intern(&self, key: &str) -> Arc<str> {
+        let mut keys = self.lock();
+        match keys.get(key) {
+            Some(interned) => Arc::clone(interned),
+            None => {
+                let key: Arc<str> = Arc::from(key);
+                keys.insert(Arc::clone(&key));
+                key
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<Arc<str>>> {
+        // the set is only modified by inserting complete keys, so it is valid after a panic
+        self.keys.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The longest string stored inline, chosen so that a `SmallString` is as large as a `String`.
+const INLINE_CAPACITY: usize = 22;
+
+/// A string which is stored inline if it is short, or in an exactly sized heap allocation.
+///
+/// Strings of up to [`INLINE_CAPACITY`] bytes are always inline, so equal strings have the
+/// same representation.
+#[derive(Clone, PartialEq, Eq)]
+enum SmallString {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+impl SmallString {
+    fn as_str(&self) -> &str {
+        match self {
+            SmallString::Inline { len, bytes } => {
+                let bytes = &bytes[..usize::from(*len)];
+                // SAFETY: inline bytes are only ever copied from a whole `str`, in `from`
+                unsafe { std::str::from_utf8_unchecked(bytes) }
+            }
+            SmallString::Heap(s) => s,
+        }
+    }
+}
+
+impl From<Cow<'_, str>> for SmallString {
+    fn from(s: Cow<'_, str>) -> Self {
+        if s.len() <= INLINE_CAPACITY {
+            let mut bytes = [0; INLINE_CAPACITY];
+            bytes[..s.len()].copy_from_slice(s.as_bytes());
+            SmallString::Inline {
+                len: s.len() as u8,
+                bytes,
+            }
+        } else {
+            SmallString::Heap(s.into_owned().into_boxed_str())
+        }
+    }
+}
+
+impl From<SmallString> for String {
+    fn from(s: SmallString) -> Self {
+        match s {
+            SmallString::Heap(s) => s.into(),
+            inline => inline.as_str().to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert_replaces_in_place() {
+        let mut map = EnvMap::new();
+        assert_eq!(map.insert("A", "1"), None);
+        assert_eq!(map.insert("B", "2"), None);
+        assert_eq!(map.insert("A", "3"), Some("1".to_string()));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("A"), Some("3"));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["A", "B"]);
+    }
+
+    #[test]
+    fn test_remove_keeps_order() {
+        let mut map: EnvMap = vec![("A", "1"), ("B", "2"), ("C", "3")]
+            .into_iter()
+            .collect();
+        assert_eq!(map.remove("A"), Some("1".to_string()));
+        assert_eq!(map.remove("A"), None);
+
+        assert!(!map.contains_key("A"));
+        assert_eq!(map.get("C"), Some("3"));
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("B", "2"), ("C", "3")]);
+    }
+
+    #[test]
+    fn test_empty() {
+        let map = EnvMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get("A"), None);
+    }
+
+    #[test]
+    fn test_empty_values() {
+        let mut map: EnvMap = vec![("A", ""), ("B", "2"), ("C", "")].into_iter().collect();
+        assert!(map.is_empty_value("A"));
+        assert!(!map.is_empty_value("B"));
+        assert!(!map.is_empty_value("D"));
+        assert_eq!(map.get("A"), Some(""));
+        assert_eq!(map.get_non_empty("A"), None);
+        assert_eq!(map.get_non_empty("B"), Some("2"));
+
+        assert_eq!(map.remove_empty_values(), ["A", "C"]);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("B", "2")]);
+        assert_eq!(map.get("B"), Some("2"));
+    }
+
+    #[test]
+    fn test_small_strings() {
+        let short = "a".repeat(INLINE_CAPACITY);
+        let long = "b".repeat(INLINE_CAPACITY + 1);
+        let mut map = EnvMap::new();
+        map.insert("SHORT", short.clone());
+        map.insert("LONG", long.clone());
+        map.insert("UNICODE", "Grüße, 世界");
+        assert_eq!(map.get("SHORT"), Some(short.as_str()));
+        assert_eq!(map.get("LONG"), Some(long.as_str()));
+        assert_eq!(map.get("UNICODE"), Some("Grüße, 世界"));
+        assert_eq!(map.remove("LONG"), Some(long));
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [
+                ("SHORT".to_string(), short),
+                ("UNICODE".to_string(), "Grüße, 世界".to_string())
+            ]
+        );
+        assert_eq!(
+            std::mem::size_of::<SmallString>(),
+            std::mem::size_of::<String>()
+        );
+    }
+
+    #[test]
+    fn test_interned_keys_are_shared() {
+        let interner = KeyInterner::new();
+        let mut first = EnvMap::with_interner(&interner);
+        first.insert("A", "1");
+        let mut second: EnvMap = vec![("A", "2"), ("B", "2")].into_iter().collect();
+        second.intern_keys(&interner);
+        second.insert("C", "3");
+
+        assert_eq!(interner.len(), 3);
+        assert!(Arc::ptr_eq(&first.entries[0].0, &second.entries[0].0));
+        assert_eq!(second.get("A"), Some("2"));
+        assert_eq!(second.keys().collect::<Vec<_>>(), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_eq_and_debug_ignore_interner() {
+        let interner = KeyInterner::new();
+        let mut interned = EnvMap::with_interner(&interner);
+        interned.insert("A", "1");
+        let plain: EnvMap = vec![("A", "1")].into_iter().collect();
+        assert_eq!(interned, plain);
+        assert_eq!(format!("{:?}", plain), r#"{"A": "1"}"#);
+    }
+}
@@ -0,0 +1,126 @@
+/// A pattern for selecting variables by key.
+///
+/// Glob patterns match the whole key, with `*` matching any run of characters and `?`
+/// matching a single character. With the `regex` feature, a `regex::Regex` may match anywhere
+/// in the key; anchor it with `^` and `$` to match whole keys.
+///
+/// Strings convert into glob patterns, so `"APP_*"` can be passed wherever a `KeyMatcher` is
+/// expected.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::KeyMatcher;
+///
+/// let matcher = KeyMatcher::glob("DB_*_URL");
+/// assert!(matcher.matches("DB_MAIN_URL"));
+/// assert!(!matcher.matches("DB_MAIN_URL_OLD"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyMatcher(Pattern);
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Glob(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl KeyMatcher {
+    /// Creates a matcher from a glob pattern.
+    pub fn glob(pattern: impl Into<String>) -> Self {
+        KeyMatcher(Pattern::Glob(pattern.into()))
+    }
+
+    /// Creates a matcher from a regular expression.
+    #[cfg(feature = "regex")]
+    pub fn regex(regex: regex::Regex) -> Self {
+        KeyMatcher(Pattern::Regex(regex))
+    }
+
+    /// Returns `true` if `key` matches the pattern.
+    pub fn matches(&self, key: &str) -> bool {
+        match &self.0 {
+            Pattern::Glob(pattern) => glob_matches(pattern, key),
+            #[cfg(feature = "regex")]
+            Pattern::Regex(regex) => regex.is_match(key),
+        }
+    }
+}
+
+impl From<&str> for KeyMatcher {
+    fn from(pattern: &str) -> Self {
+        KeyMatcher::glob(pattern)
+    }
+}
+
+impl From<String> for KeyMatcher {
+    fn from(pattern: String) -> Self {
+        KeyMatcher::glob(pattern)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Regex> for KeyMatcher {
+    fn from(regex: regex::Regex) -> Self {
+        KeyMatcher::regex(regex)
+    }
+}
+
+/// Matches `text` against a glob `pattern` of literal characters, `*` and `?`.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` and the text it has consumed up to, for backtracking
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        assert!(glob_matches("APP_*", "APP_PORT"));
+        assert!(glob_matches("APP_*", "APP_"));
+        assert!(!glob_matches("APP_*", "MY_APP_PORT"));
+        assert!(glob_matches("*_URL", "DATABASE_URL"));
+        assert!(glob_matches("DB_?", "DB_1"));
+        assert!(!glob_matches("DB_?", "DB_10"));
+        assert!(glob_matches("*A*B*", "xxAyyBzz"));
+        assert!(!glob_matches("*A*B", "xxAyyBzz"));
+        assert!(glob_matches("PORT", "PORT"));
+        assert!(!glob_matches("PORT", "PORTS"));
+        assert!(glob_matches("*", ""));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex() {
+        let matcher = KeyMatcher::from(regex::Regex::new("^(API|WEB)_").unwrap());
+        assert!(matcher.matches("API_PORT"));
+        assert!(!matcher.matches("WORKER_PORT"));
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::env;
 
 use crate::errors::*;
+use crate::syntax::{BareKeys, Syntax, Trim};
 
 // for readability's sake
 pub type ParsedLine = Result<Option<(String, String)>>;
@@ -9,28 +10,42 @@ pub type ParsedLine = Result<Option<(String, String)>>;
 pub fn parse_line(
     line: &str,
     substitution_data: &mut HashMap<String, Option<String>>,
+    syntax: &Syntax,
 ) -> ParsedLine {
-    let mut parser = LineParser::new(line, substitution_data);
+    let mut parser = LineParser::new(line, substitution_data, syntax);
     parser.parse_line()
 }
 
+/// Checks that `line` parses, without reading substituted variables from the environment.
+pub(crate) fn check_line(line: &str, syntax: &Syntax) -> Result<()> {
+    let mut substitution_data = HashMap::new();
+    let mut parser = LineParser::new(line, &mut substitution_data, syntax);
+    parser.read_env = false;
+    parser.parse_line().map(|_| ())
+}
+
 struct LineParser<'a> {
     original_line: &'a str,
     substitution_data: &'a mut HashMap<String, Option<String>>,
+    syntax: &'a Syntax,
     line: &'a str,
     pos: usize,
+    read_env: bool,
 }
 
 impl<'a> LineParser<'a> {
     fn new(
         line: &'a str,
         substitution_data: &'a mut HashMap<String, Option<String>>,
+        syntax: &'a Syntax,
     ) -> LineParser<'a> {
         LineParser {
             original_line: line,
             substitution_data,
+            syntax,
             line: line.trim_end(), // we don’t want trailing whitespace
             pos: 0,
+            read_env: true,
         }
     }
 
@@ -41,7 +56,7 @@ impl<'a> LineParser<'a> {
     fn parse_line(&mut self) -> ParsedLine {
         self.skip_whitespace();
         // if its an empty line or a comment, skip it
-        if self.line.is_empty() || self.line.starts_with('#') {
+        if self.line.is_empty() || self.syntax.starts_with_comment(self.line) {
             return Ok(None);
         }
 
@@ -49,30 +64,84 @@ impl<'a> LineParser<'a> {
         self.skip_whitespace();
 
         // export can be either an optional prefix or a key itself
-        if key == "export" {
+        if key == "export" && self.syntax.allow_export && !self.at_end() {
             // here we check for an optional `=`, below we throw directly when it’s not found.
             if self.expect_equal().is_err() {
                 key = self.parse_key()?;
                 self.skip_whitespace();
+                if self.at_end() {
+                    return self.inherit(key);
+                }
                 self.expect_equal()?;
             }
+        } else if self.at_end() {
+            return self.inherit(key);
         } else {
             self.expect_equal()?;
         }
+        let before_whitespace = self.line;
         self.skip_whitespace();
-
-        if self.line.is_empty() || self.line.starts_with('#') {
+        let leading = &before_whitespace[..before_whitespace.len() - self.line.len()];
+        let trailing = &self.original_line[self.original_line.trim_end().len()..];
+
+        if self.line.is_empty() || self.syntax.starts_with_comment(self.line) {
+            if self.line.is_empty() && self.syntax.trim == Trim::Preserve {
+                let value = format!("{}{}", leading, trailing);
+                self.substitution_data
+                    .insert(key.clone(), Some(value.clone()));
+                return Ok(Some((key, value)));
+            }
             self.substitution_data.insert(key.clone(), None);
             return Ok(Some((key, String::new())));
         }
 
-        let parsed_value = parse_value(self.line, self.substitution_data)?;
+        let quoted = self
+            .line
+            .starts_with(|c| self.syntax.is_single_quote(c) || self.syntax.is_double_quote(c));
+        let (mut parsed_value, reached_end) = parse_value_in(
+            self.line,
+            self.substitution_data,
+            self.syntax,
+            self.read_env,
+        )?;
+        if !quoted {
+            if self.syntax.trim == Trim::Preserve {
+                parsed_value.insert_str(0, leading);
+            }
+            if self.syntax.trim != Trim::Both && reached_end {
+                parsed_value.push_str(trailing);
+            }
+        }
         self.substitution_data
             .insert(key.clone(), Some(parsed_value.clone()));
 
         Ok(Some((key, parsed_value)))
     }
 
+    /// Returns `true` if nothing but a comment is left on the line.
+    fn at_end(&self) -> bool {
+        self.line.is_empty() || self.syntax.starts_with_comment(self.line)
+    }
+
+    /// Reads the value of a bare `key`, with no `=`, from the environment.
+    fn inherit(&mut self, key: String) -> ParsedLine {
+        if !self.read_env {
+            return match self.syntax.bare_keys {
+                BareKeys::Reject => Err(self.err()),
+                _ => Ok(None),
+            };
+        }
+        match (self.syntax.bare_keys, env::var(&key)) {
+            (BareKeys::Reject, _) | (BareKeys::Require, Err(_)) => Err(self.err()),
+            (BareKeys::Inherit, Err(_)) => Ok(None),
+            (_, Ok(value)) => {
+                self.substitution_data
+                    .insert(key.clone(), Some(value.clone()));
+                Ok(Some((key, value)))
+            }
+        }
+    }
+
     fn parse_key(&mut self) -> Result<String> {
         if !self
             .line
@@ -113,6 +182,70 @@ impl<'a> LineParser<'a> {
     }
 }
 
+/// Guesses the mistake which made `line` fail to parse at `index`, as reported by
+/// [`Error::LineParse`].
+pub(crate) fn classify(line: &str, index: usize) -> Option<ParseErrorKind> {
+    let is_key_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+    if has_unclosed_quote(line) {
+        return Some(ParseErrorKind::UnbalancedQuote);
+    }
+    let before = line.get(..index)?;
+    let at = line[index..].chars().next();
+    if before.ends_with('\\') {
+        return Some(ParseErrorKind::InvalidEscape);
+    }
+    let after_word = before.ends_with(char::is_whitespace);
+    if !before.contains('=')
+        && (before.is_empty() || after_word || at.map_or(true, |c| !is_key_char(c)))
+    {
+        let mut words = before.split_whitespace();
+        let key = match (words.next(), words.next(), words.next()) {
+            (Some("export"), Some(key), None) | (Some(key), None, None) => key,
+            (None, ..) => "",
+            _ => return None,
+        };
+        let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && key.chars().all(is_key_char);
+        // errors in values only report the value, so `hello world` could be either a value
+        // with whitespace or a key without `=`; keys are conventionally uppercase
+        let conventional_key = valid_key && !key.chars().any(|c| c.is_ascii_lowercase());
+        return match at {
+            Some(c) if !c.is_whitespace() && line[index..].contains('=') => {
+                Some(ParseErrorKind::InvalidKey)
+            }
+            _ if conventional_key || (valid_key && !after_word) => {
+                Some(ParseErrorKind::MissingEquals)
+            }
+            Some(c) if after_word && !c.is_whitespace() => Some(ParseErrorKind::UnquotedWhitespace),
+            _ => None,
+        };
+    }
+    match at {
+        Some(c) if !c.is_whitespace() && before.ends_with(|c: char| c == ' ' || c == '\t') => {
+            Some(ParseErrorKind::UnquotedWhitespace)
+        }
+        _ => None,
+    }
+}
+
+/// Returns `true` if a quote in `line` is opened but not closed.
+fn has_unclosed_quote(line: &str) -> bool {
+    let mut quote = None;
+    let mut escaped = false;
+    for c in line.chars() {
+        match quote {
+            _ if escaped => escaped = false,
+            Some('\'') if c == '\'' => quote = None,
+            Some('\'') => {}
+            Some(_) if c == '"' => quote = None,
+            _ if c == '\\' => escaped = true,
+            None if c == '\'' || c == '"' => quote = Some(c),
+            _ => {}
+        }
+    }
+    quote.is_some()
+}
+
 #[derive(Eq, PartialEq)]
 enum SubstitutionMode {
     None,
@@ -120,10 +253,24 @@ enum SubstitutionMode {
     EscapedBlock,
 }
 
-fn parse_value(
+/// Parses a value, returning it with whether it runs to the end of `input`, rather than being
+/// followed by whitespace or a comment.
+pub(crate) fn parse_value(
+    input: &str,
+    substitution_data: &mut HashMap<String, Option<String>>,
+    syntax: &Syntax,
+) -> Result<(String, bool)> {
+    parse_value_in(input, substitution_data, syntax, true)
+}
+
+/// Parses a value like [`parse_value`], only looking up substituted variables in the
+/// environment if `read_env` is set.
+fn parse_value_in(
     input: &str,
     substitution_data: &mut HashMap<String, Option<String>>,
-) -> Result<String> {
+    syntax: &Syntax,
+    read_env: bool,
+) -> Result<(String, bool)> {
     let mut strong_quote = false; // '
     let mut weak_quote = false; // "
     let mut escaped = false;
@@ -135,7 +282,14 @@ fn parse_value(
     let mut substitution_mode = SubstitutionMode::None;
     let mut substitution_name = String::new();
 
-    for (index, c) in input.chars().enumerate() {
+    // characters of a `%KEY%` reference which have already been handled
+    let mut skip = 0;
+
+    for (index, (offset, c)) in input.char_indices().enumerate() {
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
         //the regex _should_ already trim whitespace off the end
         //expecting_end is meant to permit: k=v #comment
         //without affecting: k=v#comment
@@ -143,7 +297,7 @@ fn parse_value(
         if expecting_end {
             if c == ' ' || c == '\t' {
                 continue;
-            } else if c == '#' {
+            } else if syntax.is_comment(c) {
                 break;
             } else {
                 return Err(Error::LineParse(input.to_owned(), index));
@@ -180,8 +334,9 @@ fn parse_value(
                         } else {
                             apply_substitution(
                                 substitution_data,
-                                &substitution_name.drain(..).collect::<String>(),
+                                &std::mem::take(&mut substitution_name),
                                 &mut output,
+                                read_env,
                             );
                             if c == '$' {
                                 substitution_mode = if !strong_quote && !escaped {
@@ -200,16 +355,59 @@ fn parse_value(
                             substitution_mode = SubstitutionMode::None;
                             apply_substitution(
                                 substitution_data,
-                                &substitution_name.drain(..).collect::<String>(),
+                                &std::mem::take(&mut substitution_name),
                                 &mut output,
+                                read_env,
                             );
+                        } else if syntax.is_substitution(c) {
+                            // one level of indirection, as in `${${PREFIX}_URL}`
+                            let rest = &input[offset + 1..];
+                            let (name, len) = if let Some(block) = rest.strip_prefix('{') {
+                                match block.find('}') {
+                                    Some(end) if !block[..end].contains(c) => {
+                                        (&block[..end], end + 2)
+                                    }
+                                    _ => return Err(Error::LineParse(input.to_owned(), index)),
+                                }
+                            } else {
+                                let end = rest
+                                    .find(|c: char| !c.is_alphanumeric())
+                                    .unwrap_or(rest.len());
+                                (&rest[..end], end)
+                            };
+                            if name.is_empty() {
+                                return Err(Error::LineParse(input.to_owned(), index));
+                            }
+                            apply_substitution(
+                                substitution_data,
+                                name,
+                                &mut substitution_name,
+                                read_env,
+                            );
+                            skip = rest[..len].chars().count();
                         } else {
                             substitution_name.push(c);
                         }
                     }
                 }
             }
-        } else if c == '$' {
+        } else if syntax.is_percent_substitution(c) {
+            let rest = &input[offset + 1..];
+            let name_len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if rest[name_len..].starts_with('%') {
+                let name = &rest[..name_len];
+                if !name.is_empty() {
+                    apply_substitution(substitution_data, name, &mut output, read_env);
+                } else {
+                    output.push('%');
+                }
+                skip = name.chars().count() + 1;
+            } else {
+                output.push(c);
+            }
+        } else if syntax.is_substitution(c) {
             substitution_mode = if !strong_quote && !escaped {
                 SubstitutionMode::Block
             } else {
@@ -218,16 +416,16 @@ fn parse_value(
         } else if weak_quote {
             if c == '"' {
                 weak_quote = false;
-            } else if c == '\\' {
+            } else if syntax.is_escape(c) {
                 escaped = true;
             } else {
                 output.push(c);
             }
-        } else if c == '\'' {
+        } else if syntax.is_single_quote(c) {
             strong_quote = true;
-        } else if c == '"' {
+        } else if syntax.is_double_quote(c) {
             weak_quote = true;
-        } else if c == '\\' {
+        } else if syntax.is_escape(c) {
             escaped = true;
         } else if c == ' ' || c == '\t' {
             expecting_end = true;
@@ -250,10 +448,11 @@ fn parse_value(
     } else {
         apply_substitution(
             substitution_data,
-            &substitution_name.drain(..).collect::<String>(),
+            &std::mem::take(&mut substitution_name),
             &mut output,
+            read_env,
         );
-        Ok(output)
+        Ok((output, !expecting_end))
     }
 }
 
@@ -261,8 +460,9 @@ fn apply_substitution(
     substitution_data: &mut HashMap<String, Option<String>>,
     substitution_name: &str,
     output: &mut String,
+    read_env: bool,
 ) {
-    if let Ok(environment_value) = env::var(substitution_name) {
+    if let Some(environment_value) = read_env.then(|| env::var(substitution_name).ok()).flatten() {
         output.push_str(&environment_value);
     } else {
         let stored_value = substitution_data
@@ -360,6 +560,25 @@ very bacon = yes indeed
         assert_eq!(count, 3);
     }
 
+    #[test]
+    fn test_check_line() {
+        let syntax = Syntax::new().percent_interpolation(true);
+        for line in [
+            "A=$HOME",
+            "A=\"${HOME}/x\"",
+            "A=%PATH%",
+            "export A=1",
+            "# comment",
+        ] {
+            assert!(check_line(line, &syntax).is_ok(), "{}", line);
+        }
+        for line in ["A=${HOME", "A=\"${HOME", "A B", "A=\\8"] {
+            assert!(check_line(line, &syntax).is_err(), "{}", line);
+        }
+        assert!(check_line("HOME", &syntax).is_err());
+        assert!(check_line("HOME", &syntax.clone().bare_keys(BareKeys::Inherit)).is_ok());
+    }
+
     #[test]
     fn test_parse_value_escapes() {
         let actual_iter = Iter::new(
@@ -503,6 +722,26 @@ mod variable_substitution_tests {
         );
     }
 
+    #[test]
+    fn indirect_substitution() {
+        assert_parsed_string(
+            r#"
+            TENANT=ACME
+            ACME_URL=https://acme.example.com
+            URL=${${TENANT}_URL}
+            BARE_URL="${$TENANT_URL}"
+            MISSING=>${${UNSET}_URL}<
+            "#,
+            vec![
+                ("TENANT", "ACME"),
+                ("ACME_URL", "https://acme.example.com"),
+                ("URL", "https://acme.example.com"),
+                ("BARE_URL", "https://acme.example.com"),
+                ("MISSING", "><"),
+            ],
+        );
+    }
+
     #[test]
     fn variable_without_parenthesis_is_substituted_before_separators() {
         assert_parsed_string(
@@ -570,8 +809,33 @@ mod variable_substitution_tests {
 #[cfg(test)]
 mod error_tests {
     use crate::errors::Error::LineParse;
+    use crate::errors::ParseErrorKind;
     use crate::iter::Iter;
 
+    #[test]
+    fn should_classify_common_mistakes() {
+        let cases = [
+            ("A=\"never closed\n", Some(ParseErrorKind::UnbalancedQuote)),
+            ("A='it's'\n", Some(ParseErrorKind::UnbalancedQuote)),
+            ("API_KEY secret\n", Some(ParseErrorKind::MissingEquals)),
+            ("export API_KEY\n", Some(ParseErrorKind::MissingEquals)),
+            (
+                "GREETING=hello world\n",
+                Some(ParseErrorKind::UnquotedWhitespace),
+            ),
+            ("PATH=C:\\Users\n", Some(ParseErrorKind::InvalidEscape)),
+            ("API-KEY=1\n", Some(ParseErrorKind::InvalidKey)),
+            ("1KEY=1\n", Some(ParseErrorKind::InvalidKey)),
+            ("<><><>\n", None),
+        ];
+        for (input, expected) in &cases {
+            let err = Iter::new(input.as_bytes())
+                .find_map(Result::err)
+                .unwrap_or_else(|| panic!("{:?} should not parse", input));
+            assert_eq!(err.parse_error_kind(), *expected, "{:?}: {}", input, err);
+        }
+    }
+
     #[test]
     fn should_not_parse_unfinished_substitutions() {
         let wrong_value = ">${KEY{<";
@@ -604,6 +868,21 @@ mod error_tests {
         }
     }
 
+    #[test]
+    fn should_not_parse_nested_indirection() {
+        for &(wrong_value, wrong_index) in &[("${${${A}}}", 2), ("${$}", 2), ("${${A}", 5)] {
+            let parsed_values: Vec<_> =
+                Iter::new(format!("KEY={}", wrong_value).as_bytes()).collect();
+
+            if let Err(LineParse(value, index)) = &parsed_values[0] {
+                assert_eq!(value, wrong_value);
+                assert_eq!(*index, wrong_index)
+            } else {
+                panic!("Expected {} not to be parsed", wrong_value)
+            }
+        }
+    }
+
     #[test]
     fn should_not_allow_dot_as_first_character_of_key() {
         let wrong_key_value = ".Key=VALUE";
@@ -0,0 +1,686 @@
+use std::env;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::document::{Document, Line};
+use crate::errors::*;
+use crate::filesystem::{self, StdFileSystem};
+use crate::map::EnvMap;
+use crate::suggest;
+
+/// A contract for environment variables, checked with [`validate`](EnvSchema::validate).
+///
+/// A schema is usually read from an annotated example file, so that one file documents the
+/// variables and defines which of them are expected:
+///
+/// ```text
+/// # @required
+/// # @type: u16
+/// PORT=8080
+///
+/// # @required
+/// API_TOKEN= # @secret
+/// ```
+///
+/// The annotations understood are `@required`, `@type: <type>`, `@secret` and
+/// `@ttl: <duration>`; see [`Annotation`](crate::Annotation) for where they may be written.
+/// Other annotations are ignored. Every declared variable is part of the schema, with or without
+/// annotations.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{Document, EnvMap, EnvSchema};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let document = Document::parse("# @required\n# @type: u16\nPORT=8080\n")?;
+/// let schema = EnvSchema::from_document(&document)?;
+///
+/// let mut map = EnvMap::new();
+/// map.insert("PORT", "http");
+/// let err = schema.validate(&map).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "Invalid environment: `PORT` must be a u16, got `http`"
+/// );
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvSchema {
+    vars: Vec<VarSpec>,
+}
+
+/// The expectations for a single variable in an [`EnvSchema`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarSpec {
+    key: String,
+    required: bool,
+    var_type: VarType,
+    secret: bool,
+    ttl: Option<Duration>,
+}
+
+/// The type a variable's value must parse as.
+///
+/// Values are checked with Rust's own [`str::parse`], so `bool` accepts `true` and `false`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum VarType {
+    String,
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+/// A variable which does not satisfy its [`VarSpec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    key: String,
+    kind: ViolationKind,
+    suggestions: Vec<String>,
+}
+
+/// The ways a variable can fail validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// A required variable is unset or empty.
+    Missing,
+    /// The value does not parse as the expected type. The value is `None` for secrets.
+    InvalidType {
+        expected: VarType,
+        value: Option<String>,
+    },
+}
+
+impl EnvSchema {
+    /// Creates an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a schema from the annotations in `document`.
+    ///
+    /// An error is returned if a `@type` annotation is missing its value or names an
+    /// unknown type, or if a `@ttl` annotation is not a duration such as `300s`, `5m` or
+    /// `1h`.
+    pub fn from_document(document: &Document) -> Result<Self> {
+        let mut schema = EnvSchema::new();
+        for entry in document.entries() {
+            let mut spec = VarSpec::new(entry.key())
+                .required(entry.annotation("required").is_some())
+                .secret(entry.annotation("secret").is_some());
+            if let Some(annotation) = entry.annotation("type") {
+                let name = annotation.value().ok_or_else(|| {
+                    invalid_schema(format!("`{}` has an empty @type", entry.key()))
+                })?;
+                spec = spec.of_type(name.parse()?);
+            }
+            if let Some(annotation) = entry.annotation("ttl") {
+                let ttl = annotation.value().and_then(parse_duration).ok_or_else(|| {
+                    invalid_schema(format!("`{}` has an invalid @ttl", entry.key()))
+                })?;
+                spec = spec.expires_after(ttl);
+            }
+            schema = schema.var(spec);
+        }
+        Ok(schema)
+    }
+
+    /// Reads an annotated *.env* file and builds a schema from it.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let input = fs::read_to_string(path).map_err(Error::Io)?;
+        EnvSchema::from_document(&Document::parse(&input)?)
+    }
+
+    /// Adds a variable, replacing any existing spec for the same key.
+    pub fn var(mut self, spec: VarSpec) -> Self {
+        match self.vars.iter_mut().find(|var| var.key == spec.key) {
+            Some(var) => *var = spec,
+            None => self.vars.push(spec),
+        }
+        self
+    }
+
+    /// Returns the variables in the order they were added.
+    pub fn vars(&self) -> &[VarSpec] {
+        &self.vars
+    }
+
+    /// Returns the spec for `key`, if the schema declares it.
+    pub fn get(&self, key: &str) -> Option<&VarSpec> {
+        self.vars.iter().find(|var| var.key == key)
+    }
+
+    /// Checks the variables in `map` against the schema.
+    ///
+    /// Empty values are treated as unset. All violations are collected into a single
+    /// [`Error::Validation`].
+    ///
+    /// When a required variable is absent, similarly named variables in `map` which the schema
+    /// does not declare are suggested, to help spot typos such as `DATBASE_URL`.
+    pub fn validate(&self, map: &EnvMap) -> Result<()> {
+        self.check(|key| map.get(key).map(str::to_owned), map.keys())
+    }
+
+    /// Checks the current process environment against the schema.
+    pub fn validate_env(&self) -> Result<()> {
+        let keys: Vec<_> = env::vars_os()
+            .filter_map(|(key, _)| key.into_string().ok())
+            .collect();
+        self.check(|key| env::var(key).ok(), keys.iter().map(String::as_str))
+    }
+
+    /// Appends a placeholder for every variable in the schema which the *.env* file at `path`
+    /// does not declare, leaving the rest of the file untouched.
+    ///
+    /// Each placeholder is a commented out declaration, such as `# HOST=`, under annotation
+    /// comments describing the variable, so that a new developer can see what to fill in.
+    /// Placeholders do not set anything until they are uncommented, so fallbacks for unset
+    /// variables still apply. Variables with a placeholder count as declared, so scaffolding
+    /// again adds nothing. If the file does not exist, it is created.
+    ///
+    /// Returns the keys which were added. The file is replaced atomically, so other processes
+    /// never see a partial write, and symbolic links are followed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use dotenvy::EnvSchema;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let schema = EnvSchema::from_path(".env.example")?;
+    /// for key in schema.scaffold(".env")? {
+    ///     println!("added a placeholder for {}", key);
+    /// }
+    /// #     Ok(())
+    /// # }
+    /// ```
+    pub fn scaffold<P: AsRef<Path>>(&self, path: P) -> Result<Vec<String>> {
+        let path = path.as_ref();
+        filesystem::check_is_file(&StdFileSystem, path)?;
+        let mut input = match fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::Io(err)),
+        };
+        let (placeholders, added) = self.placeholders(&Document::parse(&input)?);
+        if added.is_empty() {
+            return Ok(added);
+        }
+        if !input.is_empty() {
+            if !input.ends_with('\n') {
+                input.push('\n');
+            }
+            input.push('\n');
+        }
+        input.push_str(&placeholders);
+        crate::write_atomic(path, input.as_bytes())?;
+        Ok(added)
+    }
+
+    /// Returns the placeholders for the variables `document` does not declare, with their keys.
+    fn placeholders(&self, document: &Document) -> (String, Vec<String>) {
+        let mut output =
+            String::from("# Placeholders for undeclared variables, fill in their values\n");
+        let mut added = Vec::new();
+        for spec in self.vars.iter().filter(|spec| {
+            document.get(&spec.key).is_none() && !has_placeholder(document, &spec.key)
+        }) {
+            output.push('\n');
+            if spec.required {
+                output.push_str("# @required\n");
+            }
+            if spec.var_type != VarType::String {
+                let _ = writeln!(output, "# @type: {}", spec.var_type);
+            }
+            if spec.secret {
+                output.push_str("# @secret\n");
+            }
+            if let Some(ttl) = spec.ttl {
+                let _ = writeln!(output, "# @ttl: {}", format_duration(ttl));
+            }
+            let _ = writeln!(output, "# {}=", spec.key);
+            added.push(spec.key.clone());
+        }
+        (output, added)
+    }
+
+    fn check<'a, F, I>(&self, lookup: F, keys: I) -> Result<()>
+    where
+        F: Fn(&str) -> Option<String>,
+        I: Iterator<Item = &'a str>,
+    {
+        let mut violations: Vec<_> = self
+            .vars
+            .iter()
+            .filter_map(|spec| spec.check(lookup(&spec.key).as_deref()))
+            .collect();
+        let mut keys = Some(keys);
+        let mut undeclared = Vec::new();
+        for violation in &mut violations {
+            // an empty value is not a typo
+            if violation.kind != ViolationKind::Missing || lookup(&violation.key).is_some() {
+                continue;
+            }
+            if let Some(keys) = keys.take() {
+                undeclared = keys.filter(|key| self.get(key).is_none()).collect();
+            }
+            violation.suggestions =
+                suggest::suggestions(&violation.key, undeclared.iter().copied());
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(violations))
+        }
+    }
+}
+
+impl VarSpec {
+    /// Creates an optional string variable.
+    pub fn new(key: impl Into<String>) -> Self {
+        VarSpec {
+            key: key.into(),
+            required: false,
+            var_type: VarType::String,
+            secret: false,
+            ttl: None,
+        }
+    }
+
+    /// Sets whether the variable must be set to a non-empty value. Defaults to `false`.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Sets the type the value must parse as. Defaults to [`VarType::String`].
+    pub fn of_type(mut self, var_type: VarType) -> Self {
+        self.var_type = var_type;
+        self
+    }
+
+    /// Sets whether the value is kept out of error messages. Defaults to `false`.
+    pub fn secret(mut self, secret: bool) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// Sets how long a value fetched from a provider stays valid. Defaults to forever.
+    ///
+    /// See [`Layers::ttls`](crate::Layers::ttls) for how values are refreshed.
+    pub fn expires_after(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the name of the variable.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns whether the variable must be set to a non-empty value.
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Returns the type the value must parse as.
+    pub fn var_type(&self) -> VarType {
+        self.var_type
+    }
+
+    /// Returns whether the value is kept out of error messages.
+    pub fn is_secret(&self) -> bool {
+        self.secret
+    }
+
+    /// Returns how long a value fetched from a provider stays valid, or `None` for forever.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    fn check(&self, value: Option<&str>) -> Option<Violation> {
+        let kind = match value.filter(|value| !value.is_empty()) {
+            None if self.required => ViolationKind::Missing,
+            None => return None,
+            Some(value) if !self.var_type.accepts(value) => ViolationKind::InvalidType {
+                expected: self.var_type,
+                value: Some(value.to_owned()).filter(|_| !self.secret),
+            },
+            Some(_) => return None,
+        };
+        Some(Violation {
+            key: self.key.clone(),
+            kind,
+            suggestions: Vec::new(),
+        })
+    }
+}
+
+impl VarType {
+    /// Returns the name used in `@type` annotations.
+    pub fn name(self) -> &'static str {
+        match self {
+            VarType::String => "string",
+            VarType::Bool => "bool",
+            VarType::I8 => "i8",
+            VarType::I16 => "i16",
+            VarType::I32 => "i32",
+            VarType::I64 => "i64",
+            VarType::U8 => "u8",
+            VarType::U16 => "u16",
+            VarType::U32 => "u32",
+            VarType::U64 => "u64",
+            VarType::F32 => "f32",
+            VarType::F64 => "f64",
+        }
+    }
+
+    /// Returns `true` if `value` parses as this type.
+    pub fn accepts(self, value: &str) -> bool {
+        match self {
+            VarType::String => true,
+            VarType::Bool => value.parse::<bool>().is_ok(),
+            VarType::I8 => value.parse::<i8>().is_ok(),
+            VarType::I16 => value.parse::<i16>().is_ok(),
+            VarType::I32 => value.parse::<i32>().is_ok(),
+            VarType::I64 => value.parse::<i64>().is_ok(),
+            VarType::U8 => value.parse::<u8>().is_ok(),
+            VarType::U16 => value.parse::<u16>().is_ok(),
+            VarType::U32 => value.parse::<u32>().is_ok(),
+            VarType::U64 => value.parse::<u64>().is_ok(),
+            VarType::F32 => value.parse::<f32>().is_ok(),
+            VarType::F64 => value.parse::<f64>().is_ok(),
+        }
+    }
+}
+
+impl FromStr for VarType {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        const TYPES: [VarType; 12] = [
+            VarType::String,
+            VarType::Bool,
+            VarType::I8,
+            VarType::I16,
+            VarType::I32,
+            VarType::I64,
+            VarType::U8,
+            VarType::U16,
+            VarType::U32,
+            VarType::U64,
+            VarType::F32,
+            VarType::F64,
+        ];
+        TYPES
+            .iter()
+            .copied()
+            .find(|var_type| var_type.name() == name)
+            .ok_or_else(|| invalid_schema(format!("unknown type `{}`", name)))
+    }
+}
+
+impl fmt::Display for VarType {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.name())
+    }
+}
+
+impl Violation {
+    /// Returns the name of the offending variable.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns what is wrong with the variable.
+    pub fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+
+    /// Returns similarly named variables which were set, if a required variable was absent.
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ViolationKind::Missing => {
+                write!(fmt, "`{}` is required but not set", self.key)?;
+                if !self.suggestions.is_empty() {
+                    fmt.write_str(", ")?;
+                    suggest::write_suggestions(fmt, &self.suggestions)?;
+                }
+                Ok(())
+            }
+            ViolationKind::InvalidType {
+                expected,
+                value: Some(value),
+            } => write!(
+                fmt,
+                "`{}` must be a {}, got `{}`",
+                self.key, expected, value
+            ),
+            ViolationKind::InvalidType {
+                expected,
+                value: None,
+            } => write!(fmt, "`{}` must be a {}", self.key, expected),
+        }
+    }
+}
+
+/// Parses a duration such as `500ms`, `300s`, `5m`, `1h` or `1d`. A bare number is in seconds.
+fn parse_duration(input: &str) -> Option<Duration> {
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit.trim() {
+        "ms" => return Some(Duration::from_millis(number)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(seconds).map(Duration::from_secs)
+}
+
+/// Formats `duration` so that [`parse_duration`] reads it back.
+fn format_duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+fn invalid_schema(message: String) -> Error {
+    Error::InvalidFormat {
+        format: "schema",
+        source: message.into(),
+    }
+}
+
+/// Returns `true` if `document` has a commented out declaration of `key`, as written by
+/// [`EnvSchema::scaffold`].
+fn has_placeholder(document: &Document, key: &str) -> bool {
+    let syntax = document.syntax();
+    document.lines().any(|line| match line {
+        Line::Comment(comment) => comment
+            .trim_start_matches(|c| syntax.is_comment(c))
+            .trim_start()
+            .strip_prefix(key)
+            .map_or(false, |rest| rest.starts_with('=')),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema(input: &str) -> EnvSchema {
+        EnvSchema::from_document(&Document::parse(input).unwrap()).unwrap()
+    }
+
+    fn violations(schema: &EnvSchema, map: &[(&str, &str)]) -> Vec<String> {
+        let map: EnvMap = map.iter().copied().collect();
+        match schema.validate(&map) {
+            Ok(()) => Vec::new(),
+            Err(Error::Validation(violations)) => {
+                violations.iter().map(ToString::to_string).collect()
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_from_document() {
+        let schema = schema("# @required\n# @type: u16\nPORT=8080\nTOKEN= # @secret\nNAME=x\n");
+        assert_eq!(
+            schema.vars(),
+            &[
+                VarSpec::new("PORT").required(true).of_type(VarType::U16),
+                VarSpec::new("TOKEN").secret(true),
+                VarSpec::new("NAME"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_document_invalid_type() {
+        let document = Document::parse("# @type: uint\nPORT=8080\n").unwrap();
+        assert!(EnvSchema::from_document(&document).is_err());
+        let document = Document::parse("# @type\nPORT=8080\n").unwrap();
+        assert!(EnvSchema::from_document(&document).is_err());
+    }
+
+    #[test]
+    fn test_ttl_annotation() {
+        let schema =
+            schema("# @ttl: 300s\nTOKEN=\n# @ttl: 5m\nKEY=\n# @ttl: 250ms\nNONCE=\nNAME=\n");
+        let ttls: Vec<_> = schema.vars().iter().map(VarSpec::ttl).collect();
+        assert_eq!(
+            ttls,
+            [
+                Some(Duration::from_secs(300)),
+                Some(Duration::from_secs(300)),
+                Some(Duration::from_millis(250)),
+                None
+            ]
+        );
+        for input in &["# @ttl\nA=\n", "# @ttl: soon\nA=\n", "# @ttl: 5w\nA=\n"] {
+            let document = Document::parse(input).unwrap();
+            assert!(EnvSchema::from_document(&document).is_err(), "{}", input);
+        }
+
+        let placeholders = schema.placeholders(&Document::parse("").unwrap()).0;
+        assert!(placeholders.contains("# @ttl: 300s\n# TOKEN=\n"));
+        assert!(placeholders.contains("# @ttl: 250ms\n# NONCE=\n"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = schema("# @required\n# @type: u16\nPORT=\n# @type: bool\nDEBUG=\nNAME=\n");
+        assert!(violations(&schema, &[("PORT", "80"), ("DEBUG", "true")]).is_empty());
+        assert!(violations(&schema, &[("PORT", "80"), ("DEBUG", "")]).is_empty());
+        assert_eq!(
+            violations(&schema, &[("PORT", "99999"), ("DEBUG", "yes")]),
+            [
+                "`PORT` must be a u16, got `99999`",
+                "`DEBUG` must be a bool, got `yes`"
+            ]
+        );
+        assert_eq!(
+            violations(&schema, &[("PORT", "")]),
+            ["`PORT` is required but not set"]
+        );
+    }
+
+    #[test]
+    fn test_validate_suggests_keys() {
+        let schema = schema("# @required\nDATABASE_URL=\n# @required\nDATABASE_USER=\n");
+        assert_eq!(
+            violations(
+                &schema,
+                &[("DATBASE_URL", "postgres://"), ("DATABASE_USER", "")]
+            ),
+            [
+                "`DATABASE_URL` is required but not set, did you mean `DATBASE_URL`?",
+                "`DATABASE_USER` is required but not set"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_masks_secrets() {
+        let schema = EnvSchema::new().var(VarSpec::new("PIN").of_type(VarType::U8).secret(true));
+        assert_eq!(
+            violations(&schema, &[("PIN", "hunter2")]),
+            ["`PIN` must be a u8"]
+        );
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let schema =
+            schema("# @required\n# @type: u16\nPORT=8080\nHOST=\n\nAPI_TOKEN= # @secret\n");
+        let document = Document::parse("HOST=localhost\n").unwrap();
+        let (placeholders, added) = schema.placeholders(&document);
+        assert_eq!(added, ["PORT", "API_TOKEN"]);
+        assert_eq!(
+            placeholders,
+            "# Placeholders for undeclared variables, fill in their values\n\n\
+             # @required\n# @type: u16\n# PORT=\n\n# @secret\n# API_TOKEN=\n"
+        );
+        let with_placeholders = Document::parse(&placeholders).unwrap();
+        assert!(EnvSchema::from_document(&with_placeholders)
+            .unwrap()
+            .vars()
+            .is_empty());
+        assert_eq!(schema.placeholders(&with_placeholders).1, ["HOST"]);
+
+        // once uncommented, the placeholders describe the same schema
+        let uncommented = placeholders
+            .replace("# PORT=", "PORT=")
+            .replace("# API_TOKEN=", "API_TOKEN=");
+        let scaffolded = EnvSchema::from_document(&Document::parse(&uncommented).unwrap()).unwrap();
+        assert_eq!(scaffolded.get("PORT"), schema.get("PORT"));
+        assert_eq!(scaffolded.get("API_TOKEN"), schema.get("API_TOKEN"));
+    }
+
+    #[test]
+    fn test_var_replaces_spec() {
+        let schema = EnvSchema::new()
+            .var(VarSpec::new("A"))
+            .var(VarSpec::new("B"))
+            .var(VarSpec::new("A").required(true));
+        assert_eq!(schema.vars().len(), 2);
+        assert!(schema.get("A").unwrap().is_required());
+    }
+
+    #[test]
+    fn test_var_type_names() {
+        for name in &["string", "bool", "i64", "u16", "f64"] {
+            assert_eq!(name.parse::<VarType>().unwrap().to_string(), *name);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+use crate::errors::*;
+use crate::loader::EnvLoader;
+use crate::map::EnvMap;
+
+/// A child process spawned by [`spawn_matrix`], with the command and file it was spawned
+/// from.
+#[derive(Debug)]
+pub struct MatrixChild {
+    command: usize,
+    env_file: PathBuf,
+    child: Child,
+}
+
+impl MatrixChild {
+    /// Returns the index of the command in the slice given to [`spawn_matrix`].
+    pub fn command(&self) -> usize {
+        self.command
+    }
+
+    /// Returns the path of the file the child's variables were read from.
+    pub fn env_file(&self) -> &Path {
+        &self.env_file
+    }
+
+    /// Returns the child process.
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Returns the child process, consuming the `MatrixChild`.
+    pub fn into_child(self) -> Child {
+        self.child
+    }
+}
+
+/// Spawns every command once for each of the `env_files`, with the variables of that file.
+///
+/// Each file is parsed once, before anything is spawned, so a parse error spawns no children.
+/// The children are returned grouped by file, in the order of `env_files`, then `commands`.
+/// If a command fails to spawn, the children already spawned are killed and the error is
+/// returned.
+///
+/// The environment of the current process is not modified. As with [`dotenv`](crate::dotenv),
+/// variables already present in it take precedence over the files. The commands are reused
+/// for every file, so variables the files define should not also be set on the commands.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::process::Command;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut commands = [Command::new("./run-tests"), Command::new("./run-benchmarks")];
+/// let children = dotenvy::spawn_matrix(&mut commands, &[".env.sqlite", ".env.postgres"])?;
+/// for mut spawned in children {
+///     let status = spawned.child().wait()?;
+///     println!("{} #{}: {}", spawned.env_file().display(), spawned.command(), status);
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+pub fn spawn_matrix<P: AsRef<Path>>(
+    commands: &mut [Command],
+    env_files: &[P],
+) -> Result<Vec<MatrixChild>> {
+    spawn(commands, env_files, false)
+}
+
+/// Spawns every command once for each of the `env_files`, with the variables of that file
+/// overriding those of the current process.
+///
+/// See [`spawn_matrix`] for details.
+pub fn spawn_matrix_override<P: AsRef<Path>>(
+    commands: &mut [Command],
+    env_files: &[P],
+) -> Result<Vec<MatrixChild>> {
+    spawn(commands, env_files, true)
+}
+
+fn spawn<P: AsRef<Path>>(
+    commands: &mut [Command],
+    env_files: &[P],
+    override_existing: bool,
+) -> Result<Vec<MatrixChild>> {
+    let mut files = Vec::with_capacity(env_files.len());
+    for path in env_files {
+        let path = path.as_ref();
+        let map = EnvLoader::new().path(path).parse()?;
+        let map: EnvMap = map
+            .iter()
+            .filter(|(key, _)| override_existing || env::var_os(key).is_none())
+            .collect();
+        files.push((path, map));
+    }
+
+    let mut children = Vec::with_capacity(files.len() * commands.len());
+    for (path, map) in &files {
+        for (index, command) in commands.iter_mut().enumerate() {
+            let spawned = command.envs(map.iter()).spawn();
+            // restore the inherited values for the next file
+            for key in map.keys() {
+                match env::var_os(key) {
+                    Some(value) => command.env(key, value),
+                    None => command.env_remove(key),
+                };
+            }
+            match spawned {
+                Ok(child) => children.push(MatrixChild {
+                    command: index,
+                    env_file: path.to_path_buf(),
+                    child,
+                }),
+                Err(err) => {
+                    for mut spawned in children {
+                        let _ = spawned.child.kill();
+                        let _ = spawned.child.wait();
+                    }
+                    return Err(Error::Io(err));
+                }
+            }
+        }
+    }
+    Ok(children)
+}
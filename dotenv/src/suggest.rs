@@ -0,0 +1,104 @@
+//! "Did you mean" suggestions for misspelled keys.
+
+use std::fmt;
+
+/// The most suggestions given for one key.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns the `candidates` which are close enough to `key` to be likely typos of it, closest
+/// first.
+///
+/// Keys are compared case-insensitively, and may differ by one edit for every three
+/// characters.
+pub(crate) fn suggestions<'a, I: IntoIterator<Item = &'a str>>(
+    key: &str,
+    candidates: I,
+) -> Vec<String> {
+    let key = key.to_uppercase();
+    let max_distance = (key.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let distance = edit_distance(&key, &candidate.to_uppercase());
+            Some((distance, candidate)).filter(|_| distance <= max_distance)
+        })
+        .collect();
+    close.sort_unstable();
+    close.dedup();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_owned())
+        .collect()
+}
+
+/// Writes `suggestions` as a sentence, such as "did you mean `A` or `B`?".
+pub(crate) fn write_suggestions(fmt: &mut fmt::Formatter, suggestions: &[String]) -> fmt::Result {
+    for (i, suggestion) in suggestions.iter().enumerate() {
+        let separator = match i {
+            0 => "did you mean ",
+            i if i + 1 == suggestions.len() => " or ",
+            _ => ", ",
+        };
+        write!(fmt, "{}`{}`", separator, suggestion)?;
+    }
+    if !suggestions.is_empty() {
+        fmt.write_str("?")?;
+    }
+    Ok(())
+}
+
+/// Returns the Levenshtein distance between `a` and `b`, counting a swap of two adjacent
+/// characters as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // three rows of the distance matrix: two rows back, the previous and the current row
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("DATABASE_URL", "DATABASE_URL"), 0);
+        assert_eq!(edit_distance("DATBASE_URL", "DATABASE_URL"), 1);
+        assert_eq!(edit_distance("DATABSAE_URL", "DATABASE_URL"), 1);
+        assert_eq!(edit_distance("PORT", "HOST"), 2);
+        assert_eq!(edit_distance("", "ABC"), 3);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let keys = [
+            "DATABASE_URLS",
+            "DATABASE_USER",
+            "DATABASE_URL",
+            "database_url",
+            "PORT",
+        ];
+        assert_eq!(
+            suggestions("DATBASE_URL", keys.iter().copied()),
+            ["DATABASE_URL", "database_url", "DATABASE_URLS"]
+        );
+        assert_eq!(suggestions("PROT", keys.iter().copied()), ["PORT"]);
+        assert!(suggestions("HOST", keys.iter().copied()).is_empty());
+    }
+}
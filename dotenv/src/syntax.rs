@@ -0,0 +1,345 @@
+/// The dialect understood by the parser.
+///
+/// The default `Syntax` matches the behaviour of [`dotenv`](crate::dotenv) and friends. Each
+/// builder method toggles one parser feature, so users with bespoke formats can describe
+/// their dialect instead of pre-processing their files.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{Iter, Syntax};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let syntax = Syntax::new()
+///     .comment_chars(&['#', ';'])
+///     .allow_export(false)
+///     .interpolation(false);
+///
+/// for item in Iter::with_syntax("; a comment\nPRICE=$5".as_bytes(), syntax) {
+///     let (key, val) = item?;
+///     assert_eq!((key.as_str(), val.as_str()), ("PRICE", "$5"));
+/// }
+/// #     Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syntax {
+    pub(crate) comment_chars: Vec<char>,
+    pub(crate) allow_export: bool,
+    pub(crate) single_quotes: bool,
+    pub(crate) double_quotes: bool,
+    pub(crate) escapes: bool,
+    pub(crate) interpolation: bool,
+    pub(crate) percent_interpolation: bool,
+    pub(crate) trim: Trim,
+    pub(crate) bare_keys: BareKeys,
+}
+
+/// How lines with only a key name, such as `API_KEY`, are treated, set with
+/// [`Syntax::bare_keys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BareKeys {
+    /// Rejects them as a parse error. This is the default.
+    Reject,
+    /// Inherits the variable's value from the environment, as docker-compose does, and skips
+    /// the line if the variable is not set.
+    Inherit,
+    /// Inherits the variable's value from the environment, and fails with a parse error if the
+    /// variable is not set.
+    Require,
+}
+
+/// How whitespace around unquoted values is treated, set with [`Syntax::trim`].
+///
+/// Whitespace between a value and a trailing comment is never part of the value, and quoted
+/// values always keep exactly what is between their quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trim {
+    /// Removes leading and trailing whitespace, so `KEY=  value  ` is `value`.
+    Both,
+    /// Removes only leading whitespace, so `KEY=  value  ` is `value  `.
+    Leading,
+    /// Keeps all whitespace, so `KEY=  value  ` is `  value  `.
+    Preserve,
+}
+
+impl Syntax {
+    /// Creates the default dialect.
+    pub fn new() -> Self {
+        Syntax {
+            comment_chars: vec!['#'],
+            allow_export: true,
+            single_quotes: true,
+            double_quotes: true,
+            escapes: true,
+            interpolation: true,
+            percent_interpolation: false,
+            trim: Trim::Both,
+            bare_keys: BareKeys::Reject,
+        }
+    }
+
+    /// Sets the characters which start a comment. Defaults to `#`.
+    ///
+    /// Comments may fill a whole line, or follow a value after whitespace.
+    pub fn comment_chars(mut self, chars: &[char]) -> Self {
+        self.comment_chars = chars.to_vec();
+        self
+    }
+
+    /// Sets whether lines may be prefixed with `export`, as in shell scripts. Defaults to `true`.
+    ///
+    /// When disabled, `export KEY=value` is a parse error. `export=value` is always allowed.
+    pub fn allow_export(mut self, allow: bool) -> Self {
+        self.allow_export = allow;
+        self
+    }
+
+    /// Sets whether single quotes delimit strong quoted values. Defaults to `true`.
+    ///
+    /// When disabled, `'` is an ordinary character.
+    pub fn single_quotes(mut self, enabled: bool) -> Self {
+        self.single_quotes = enabled;
+        self
+    }
+
+    /// Sets whether double quotes delimit weak quoted values. Defaults to `true`.
+    ///
+    /// When disabled, `"` is an ordinary character.
+    pub fn double_quotes(mut self, enabled: bool) -> Self {
+        self.double_quotes = enabled;
+        self
+    }
+
+    /// Sets whether `\` escapes the following character. Defaults to `true`.
+    ///
+    /// When disabled, `\` is an ordinary character.
+    pub fn escapes(mut self, enabled: bool) -> Self {
+        self.escapes = enabled;
+        self
+    }
+
+    /// Sets whether `$KEY` and `${KEY}` are substituted. Defaults to `true`.
+    ///
+    /// A `${...}` block may itself contain one reference, which is substituted first to compute
+    /// the name, so `${${TENANT}_URL}` reads `ACME_URL` when `TENANT` is `ACME`. Deeper nesting
+    /// is a parse error.
+    ///
+    /// When disabled, `$` is an ordinary character.
+    pub fn interpolation(mut self, enabled: bool) -> Self {
+        self.interpolation = enabled;
+        self
+    }
+
+    /// Sets whether Windows batch style `%KEY%` references are substituted. Defaults to `false`.
+    ///
+    /// This is independent of [`interpolation`](Syntax::interpolation), so files shared with
+    /// `cmd` scripts can use either style. `%%` is a literal `%`, and a `%` which does not start
+    /// a reference, as in `50% off`, is kept as is. References are not substituted within
+    /// single quotes.
+    pub fn percent_interpolation(mut self, enabled: bool) -> Self {
+        self.percent_interpolation = enabled;
+        self
+    }
+
+    /// Sets how whitespace around unquoted values is treated. Defaults to [`Trim::Both`].
+    ///
+    /// Tools disagree on this, so files migrated from another loader can match its behaviour.
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Sets how lines with only a key name are treated. Defaults to [`BareKeys::Reject`].
+    ///
+    /// Inherited values pass through the environment of the parent process, so a file can
+    /// list the variables a command needs without repeating their values.
+    pub fn bare_keys(mut self, bare_keys: BareKeys) -> Self {
+        self.bare_keys = bare_keys;
+        self
+    }
+
+    pub(crate) fn is_comment(&self, c: char) -> bool {
+        self.comment_chars.contains(&c)
+    }
+
+    pub(crate) fn starts_with_comment(&self, s: &str) -> bool {
+        s.starts_with(|c| self.is_comment(c))
+    }
+
+    pub(crate) fn is_single_quote(&self, c: char) -> bool {
+        self.single_quotes && c == '\''
+    }
+
+    pub(crate) fn is_double_quote(&self, c: char) -> bool {
+        self.double_quotes && c == '"'
+    }
+
+    pub(crate) fn is_escape(&self, c: char) -> bool {
+        self.escapes && c == '\\'
+    }
+
+    pub(crate) fn is_substitution(&self, c: char) -> bool {
+        self.interpolation && c == '$'
+    }
+
+    pub(crate) fn is_percent_substitution(&self, c: char) -> bool {
+        self.percent_interpolation && c == '%'
+    }
+}
+
+impl Default for Syntax {
+    fn default() -> Self {
+        Syntax::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use crate::errors::Error;
+    use crate::iter::Iter;
+
+    use super::*;
+
+    fn parse(input: &str, syntax: Syntax) -> crate::Result<Vec<(String, String)>> {
+        Iter::with_syntax(input.as_bytes(), syntax).collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_default_matches_iter_new() {
+        let input = "export A='1'\nB=\"$A 2\" # two\n";
+        let default: crate::Result<Vec<_>> = Iter::new(input.as_bytes()).collect();
+        assert_eq!(default.unwrap(), parse(input, Syntax::default()).unwrap());
+    }
+
+    #[test]
+    fn test_comment_chars() {
+        let syntax = Syntax::new().comment_chars(&[';']);
+        let actual = parse("; comment\nA=1 ; trailing\nB=#2\n", syntax).unwrap();
+        assert_eq!(actual, pairs(&[("A", "1"), ("B", "#2")]));
+    }
+
+    #[test]
+    fn test_comment_char_in_multiline_value() {
+        let syntax = Syntax::new().comment_chars(&[';']);
+        let actual = parse("A=\"one\n; not a comment\"\n", syntax).unwrap();
+        assert_eq!(actual, pairs(&[("A", "one\n; not a comment")]));
+    }
+
+    #[test]
+    fn test_disallow_export() {
+        let syntax = Syntax::new().allow_export(false);
+        assert!(parse("export A=1\n", syntax.clone()).is_err());
+        assert_eq!(
+            parse("export=1\n", syntax).unwrap(),
+            pairs(&[("export", "1")])
+        );
+    }
+
+    #[test]
+    fn test_single_quotes_disabled() {
+        let syntax = Syntax::new().single_quotes(false);
+        let actual = parse("A='1'\nB=it's\n", syntax).unwrap();
+        assert_eq!(actual, pairs(&[("A", "'1'"), ("B", "it's")]));
+    }
+
+    #[test]
+    fn test_double_quotes_disabled() {
+        let syntax = Syntax::new().double_quotes(false);
+        let actual = parse("A=\"1\"\nB='\"'\n", syntax).unwrap();
+        assert_eq!(actual, pairs(&[("A", "\"1\""), ("B", "\"")]));
+    }
+
+    #[test]
+    fn test_escapes_disabled() {
+        let syntax = Syntax::new().escapes(false);
+        let actual = parse("PATH=C:\\Users\\me\nQ=\"a\\nb\"\n", syntax).unwrap();
+        assert_eq!(actual, pairs(&[("PATH", "C:\\Users\\me"), ("Q", "a\\nb")]));
+    }
+
+    #[test]
+    fn test_interpolation_disabled() {
+        let syntax = Syntax::new().interpolation(false);
+        let actual = parse("A=1\nB=$A${A}\n", syntax).unwrap();
+        assert_eq!(actual, pairs(&[("A", "1"), ("B", "$A${A}")]));
+    }
+
+    #[test]
+    fn test_percent_interpolation() {
+        let syntax = Syntax::new().percent_interpolation(true).escapes(false);
+        let input = r#"
+DIR=C:\app
+LOG="%DIR%\logs"
+SALE="50% off%%"
+RAW='%DIR%'
+BOTH=%DIR%$DIR
+"#;
+        let actual = parse(input, syntax).unwrap();
+        assert_eq!(
+            actual,
+            pairs(&[
+                ("DIR", r"C:\app"),
+                ("LOG", r"C:\app\logs"),
+                ("SALE", "50% off%"),
+                ("RAW", "%DIR%"),
+                ("BOTH", r"C:\appC:\app"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_percent_interpolation_disabled() {
+        let actual = parse("A=1\nB=%A%\n", Syntax::new()).unwrap();
+        assert_eq!(actual, pairs(&[("A", "1"), ("B", "%A%")]));
+    }
+
+    #[test]
+    fn test_trim() {
+        let input = "A=  one  \nB=  two  # comment\nC=  \"  three  \"  \nD=   \n";
+        let both = parse(input, Syntax::new().trim(Trim::Both)).unwrap();
+        assert_eq!(
+            both,
+            pairs(&[("A", "one"), ("B", "two"), ("C", "  three  "), ("D", "")])
+        );
+        let leading = parse(input, Syntax::new().trim(Trim::Leading)).unwrap();
+        assert_eq!(
+            leading,
+            pairs(&[("A", "one  "), ("B", "two"), ("C", "  three  "), ("D", "")])
+        );
+        let preserve = parse(input, Syntax::new().trim(Trim::Preserve)).unwrap();
+        assert_eq!(
+            preserve,
+            pairs(&[
+                ("A", "  one  "),
+                ("B", "  two"),
+                ("C", "  three  "),
+                ("D", "   ")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_bare_keys() {
+        env::set_var("SYNTAX_BARE_SET", "inherited");
+        env::remove_var("SYNTAX_BARE_UNSET");
+        let input = "A=1\nSYNTAX_BARE_SET\nexport SYNTAX_BARE_UNSET # comment\n";
+
+        assert!(parse(input, Syntax::new()).is_err());
+        let inherit = parse(input, Syntax::new().bare_keys(BareKeys::Inherit)).unwrap();
+        assert_eq!(
+            inherit,
+            pairs(&[("A", "1"), ("SYNTAX_BARE_SET", "inherited")])
+        );
+        let err = parse(input, Syntax::new().bare_keys(BareKeys::Require)).unwrap_err();
+        assert!(matches!(err, Error::LineParse(line, _) if line.contains("SYNTAX_BARE_UNSET")));
+    }
+}
@@ -0,0 +1,291 @@
+//! Helpers for testing code which reads *.env* files.
+//!
+//! [`EnvFileFixture`] writes a temporary file from a list of variables and comments, so that
+//! tests do not need their own temporary file plumbing. [`assert_env_unchanged`] checks that
+//! code has no side effects on the process environment.
+//!
+//! # Examples
+//!
+//! ```
+//! use dotenvy::testing::EnvFileFixture;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let envfile = EnvFileFixture::new()
+//!     .comment("database settings")
+//!     .set("DATABASE_URL", "postgres://localhost/test")
+//!     .build()?;
+//!
+//! let map = dotenvy::EnvLoader::new().path(envfile.path()).parse()?;
+//! assert_eq!(map.get("DATABASE_URL"), Some("postgres://localhost/test"));
+//! #     Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tempfile::TempDir;
+
+use crate::errors::*;
+use crate::format::{Dotenv, EnvFormat};
+
+/// A builder for a temporary *.env* file.
+///
+/// Lines are written in the order they were added. Values are quoted as [`Dotenv`] writes
+/// them, so any value reads back unchanged.
+#[derive(Debug, Clone)]
+pub struct EnvFileFixture {
+    filename: String,
+    lines: Vec<FixtureLine>,
+}
+
+#[derive(Debug, Clone)]
+enum FixtureLine {
+    Entry(String, String),
+    Comment(String),
+    Raw(String),
+}
+
+/// A temporary *.env* file written by [`EnvFileFixture::build`].
+///
+/// The file and its directory are deleted when this is dropped.
+#[derive(Debug)]
+pub struct TempEnvFile {
+    dir: TempDir,
+    path: PathBuf,
+}
+
+impl EnvFileFixture {
+    /// Creates an empty fixture named `.env`.
+    pub fn new() -> Self {
+        EnvFileFixture {
+            filename: ".env".to_owned(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Sets the name of the file within its temporary directory. Defaults to `.env`.
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = filename.into();
+        self
+    }
+
+    /// Adds a variable declaration.
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.lines
+            .push(FixtureLine::Entry(key.into(), value.into()));
+        self
+    }
+
+    /// Adds a comment line. `text` is written after `# `.
+    pub fn comment(mut self, text: impl Into<String>) -> Self {
+        self.lines.push(FixtureLine::Comment(text.into()));
+        self
+    }
+
+    /// Adds a blank line.
+    pub fn blank(self) -> Self {
+        self.raw("")
+    }
+
+    /// Adds a line exactly as given, for testing unusual or invalid input.
+    pub fn raw(mut self, line: impl Into<String>) -> Self {
+        self.lines.push(FixtureLine::Raw(line.into()));
+        self
+    }
+
+    /// Returns the contents the file will be written with.
+    ///
+    /// An error is returned if a key is not a valid variable name.
+    pub fn contents(&self) -> Result<String> {
+        let format = Dotenv::new();
+        let mut contents = Vec::new();
+        for line in &self.lines {
+            match line {
+                FixtureLine::Entry(key, value) => format.write_entry(key, value, &mut contents)?,
+                FixtureLine::Comment(text) => {
+                    writeln!(contents, "# {}", text).map_err(Error::Io)?
+                }
+                FixtureLine::Raw(line) => writeln!(contents, "{}", line).map_err(Error::Io)?,
+            }
+        }
+        Ok(String::from_utf8(contents).expect("fixture lines are UTF-8"))
+    }
+
+    /// Writes the file into a new temporary directory.
+    pub fn build(&self) -> Result<TempEnvFile> {
+        let contents = self.contents()?;
+        let dir = tempfile::tempdir().map_err(Error::Io)?;
+        let path = dir.path().join(&self.filename);
+        fs::write(&path, contents).map_err(Error::Io)?;
+        Ok(TempEnvFile { dir, path })
+    }
+}
+
+impl Default for EnvFileFixture {
+    fn default() -> Self {
+        EnvFileFixture::new()
+    }
+}
+
+impl TempEnvFile {
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the temporary directory containing the file.
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl AsRef<Path> for TempEnvFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Runs `f` and returns its result, panicking if it added, changed or removed any environment
+/// variables.
+///
+/// The panic message lists each difference, and the environment is restored to its previous
+/// state before panicking so that later tests are not affected. Other threads changing the
+/// environment while `f` runs are reported too, so tests using this should not run
+/// concurrently with such code.
+///
+/// # Panics
+///
+/// Panics if the environment after running `f` differs from before.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::testing::{assert_env_unchanged, EnvFileFixture};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let envfile = EnvFileFixture::new().set("TESTING_DOC_KEY", "1").build()?;
+/// // parsing must not touch the environment
+/// let map = assert_env_unchanged(|| dotenvy::EnvLoader::new().path(envfile.path()).parse())?;
+/// assert_eq!(map.get("TESTING_DOC_KEY"), Some("1"));
+/// #     Ok(())
+/// # }
+/// ```
+pub fn assert_env_unchanged<F: FnOnce() -> R, R>(f: F) -> R {
+    let before = snapshot();
+    let result = f();
+    let after = snapshot();
+    let diff = env_diff(&before, &after);
+    if !diff.is_empty() {
+        for key in after.keys().filter(|key| !before.contains_key(*key)) {
+            env::remove_var(key);
+        }
+        for (key, value) in &before {
+            if after.get(key) != Some(value) {
+                env::set_var(key, value);
+            }
+        }
+        panic!("the environment was changed:\n{}", diff);
+    }
+    result
+}
+
+type Snapshot = BTreeMap<OsString, OsString>;
+
+fn snapshot() -> Snapshot {
+    env::vars_os().collect()
+}
+
+/// Describes the variables which differ between `before` and `after`, one per line.
+fn env_diff(before: &Snapshot, after: &Snapshot) -> String {
+    let mut diff = String::new();
+    for (key, value) in after {
+        let old = before.get(key);
+        let key = key.to_string_lossy();
+        match old {
+            None => {
+                let _ = writeln!(diff, "  added {}=`{}`", key, value.to_string_lossy());
+            }
+            Some(old) if old != value => {
+                let _ = writeln!(
+                    diff,
+                    "  changed {} from `{}` to `{}`",
+                    key,
+                    old.to_string_lossy(),
+                    value.to_string_lossy()
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, value) in before.iter().filter(|(key, _)| !after.contains_key(*key)) {
+        let _ = writeln!(
+            diff,
+            "  removed {} (was `{}`)",
+            key.to_string_lossy(),
+            value.to_string_lossy()
+        );
+    }
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_env_diff() {
+        let snapshot = |pairs: &[(&str, &str)]| -> Snapshot {
+            pairs
+                .iter()
+                .map(|(key, value)| (OsString::from(key), OsString::from(value)))
+                .collect()
+        };
+        let before = snapshot(&[("KEPT", "1"), ("CHANGED", "old"), ("REMOVED", "x")]);
+        let after = snapshot(&[("KEPT", "1"), ("CHANGED", "new"), ("ADDED", "y")]);
+        assert_eq!(
+            env_diff(&before, &after),
+            "  added ADDED=`y`\n  changed CHANGED from `old` to `new`\n  removed REMOVED (was `x`)\n"
+        );
+        assert_eq!(env_diff(&before, &before), "");
+    }
+
+    #[test]
+    fn test_contents() {
+        let fixture = EnvFileFixture::new()
+            .comment("settings")
+            .set("A", "1")
+            .blank()
+            .set("B", "two words")
+            .raw("export C = $A");
+        assert_eq!(
+            fixture.contents().unwrap(),
+            "# settings\nA=1\n\nB='two words'\nexport C = $A\n"
+        );
+    }
+
+    #[test]
+    fn test_build_and_drop() {
+        let envfile = EnvFileFixture::new()
+            .filename("test.env")
+            .set("KEY", "value")
+            .build()
+            .unwrap();
+        let path = envfile.path().to_owned();
+        assert_eq!(path.file_name().unwrap(), "test.env");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "KEY=value\n");
+
+        drop(envfile);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(EnvFileFixture::new().set("A B", "1").build().is_err());
+    }
+}
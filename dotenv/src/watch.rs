@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Watches *.env* files for changes, by polling their modification time and size.
+///
+/// Files are checked on disk, so a [`FileSystem`](crate::FileSystem) given to a loader is not
+/// used. A file which is created, modified or removed counts as changed. Polling needs no
+/// platform support and a file is only read when it changes, which is enough for files edited
+/// by hand or regenerated by tools.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dotenvy::EnvLoader;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let loader = EnvLoader::new();
+/// let mut watcher = loader.watcher()?;
+/// loop {
+///     let changed = watcher.wait();
+///     println!("{:?} changed", changed);
+///     let map = loader.parse()?;
+///     // ...
+/// #   let _ = map;
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<Stamp>)>,
+    interval: Duration,
+}
+
+/// What is compared to tell whether a file changed.
+type Stamp = (SystemTime, u64);
+
+impl Watcher {
+    /// Creates a watcher for `paths`, which need not exist yet.
+    ///
+    /// Changes are reported relative to the state of the files when this is called.
+    pub fn new<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref().to_owned();
+                let stamp = stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+        Watcher {
+            files,
+            interval: Duration::from_millis(500),
+        }
+    }
+
+    /// Sets how often [`wait`](Watcher::wait) checks the files. Defaults to 500 milliseconds.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns how often [`wait`](Watcher::wait) checks the files.
+    pub fn poll_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the paths of the watched files.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Returns the paths of the files which changed since the last call, or since the watcher
+    /// was created, without blocking.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last) in &mut self.files {
+            let current = stamp(path);
+            if current != *last {
+                *last = current;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    /// Blocks until at least one file changes, returning the paths of the files which did.
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        loop {
+            let changed = self.poll();
+            if !changed.is_empty() {
+                return changed;
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+/// Returns the modification time and size of `path`, or `None` if it cannot be read.
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
@@ -0,0 +1,404 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::errors::*;
+use crate::format::{Dotenv, EnvFormat};
+use crate::map::EnvMap;
+
+/// A configurable writer for environment files.
+///
+/// By default, variables are written in the map's order using the [`Dotenv`] format.
+/// Sorting the keys produces stable output for generated files which are checked in, so
+/// that regenerating them only shows the values which actually changed.
+///
+/// # Examples
+///
+/// ```
+/// use dotenvy::{EnvMap, EnvWriter};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut map = EnvMap::new();
+/// map.insert("PORT", "8080");
+/// map.insert("DB_USER", "app");
+/// map.insert("DB_HOST", "localhost");
+///
+/// let mut output = Vec::new();
+/// EnvWriter::new()
+///     .sorted(true)
+///     .group_by_prefix("_")
+///     .write(&map, &mut output)?;
+/// assert_eq!(output, b"DB_HOST=localhost\nDB_USER=app\n\nPORT=8080\n");
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// # Generated files
+///
+/// Files written by tools can carry a header comment naming the generator, the time and the
+/// source of the values, and a checksum trailer. The checksum lets
+/// [`EnvLoader::generated_status`](crate::EnvLoader::generated_status) tell whether the file
+/// was edited by hand since it was generated. Both use `#` comments, so they suit formats in
+/// which `#` starts a comment, such as [`Dotenv`] and [`Properties`](crate::Properties).
+///
+/// ```
+/// use dotenvy::{EnvMap, EnvWriter};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut map = EnvMap::new();
+/// map.insert("PORT", "8080");
+///
+/// let mut output = Vec::new();
+/// EnvWriter::new()
+///     .generated_by("configgen")
+///     .provenance("deploy/production.toml")
+///     .checksum(true)
+///     .write(&map, &mut output)?;
+/// let output = String::from_utf8(output)?;
+/// assert!(output.starts_with("# Generated by configgen\n# Source: deploy/production.toml\n"));
+/// assert!(output.contains("\nPORT=8080\n# checksum: fnv1a64:"));
+/// #     Ok(())
+/// # }
+/// ```
+pub struct EnvWriter {
+    format: Box<dyn EnvFormat>,
+    sorted: bool,
+    group_separator: Option<String>,
+    generator: Option<String>,
+    provenance: Option<String>,
+    timestamp: bool,
+    checksum: bool,
+}
+
+/// Whether a file was written by an [`EnvWriter`] with a checksum, and if so whether it was
+/// modified since. See [`EnvLoader::generated_status`](crate::EnvLoader::generated_status).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratedStatus {
+    /// The file has no checksum trailer, so it is assumed to be written by hand.
+    HandWritten,
+    /// The file matches its checksum.
+    Unmodified,
+    /// The file has a checksum trailer which does not match its contents.
+    Modified,
+}
+
+const CHECKSUM_PREFIX: &str = "# checksum: fnv1a64:";
+
+impl EnvWriter {
+    /// Creates a writer for the *.env* format which keeps the map's order.
+    pub fn new() -> Self {
+        EnvWriter {
+            format: Box::new(Dotenv::new()),
+            sorted: false,
+            group_separator: None,
+            generator: None,
+            provenance: None,
+            timestamp: false,
+            checksum: false,
+        }
+    }
+
+    /// Sets the format used to write variables. Defaults to [`Dotenv`].
+    pub fn format<F: EnvFormat + 'static>(mut self, format: F) -> Self {
+        self.format = Box::new(format);
+        self
+    }
+
+    /// Sets whether keys are written in alphabetical order. Defaults to `false`.
+    ///
+    /// Keys are compared byte by byte, so the order does not depend on the locale.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Separates groups of keys with a blank line.
+    ///
+    /// A key's group is the text before the first `separator`, or the whole key if it does
+    /// not contain the separator. A blank line is written whenever consecutive keys belong to
+    /// different groups, so this is typically combined with [`sorted`](EnvWriter::sorted).
+    pub fn group_by_prefix(mut self, separator: impl Into<String>) -> Self {
+        self.group_separator = Some(separator.into());
+        self
+    }
+
+    /// Writes a header comment naming the program which generated the file.
+    pub fn generated_by(mut self, generator: impl Into<String>) -> Self {
+        self.generator = Some(generator.into());
+        self
+    }
+
+    /// Writes a header comment describing where the values came from, such as a path or URL.
+    pub fn provenance(mut self, source: impl Into<String>) -> Self {
+        self.provenance = Some(source.into());
+        self
+    }
+
+    /// Sets whether the header includes the time of writing, in UTC. Defaults to `false`.
+    pub fn timestamp(mut self, enabled: bool) -> Self {
+        self.timestamp = enabled;
+        self
+    }
+
+    /// Sets whether a checksum of the file is written as its last line. Defaults to `false`.
+    ///
+    /// The checksum is meant to detect accidental edits, not tampering.
+    pub fn checksum(mut self, enabled: bool) -> Self {
+        self.checksum = enabled;
+        self
+    }
+
+    /// Writes all variables in `map` to `writer`.
+    pub fn write<W: Write>(&self, map: &EnvMap, mut writer: W) -> Result<()> {
+        let mut output = Vec::new();
+        self.write_header(&mut output)?;
+        self.write_entries(map, &mut output)?;
+        if self.checksum {
+            writeln!(output, "{}{:016x}", CHECKSUM_PREFIX, fnv1a64(&output)).map_err(Error::Io)?;
+        }
+        writer.write_all(&output).map_err(Error::Io)?;
+        writer.flush().map_err(Error::Io)
+    }
+
+    fn write_header(&self, writer: &mut dyn Write) -> Result<()> {
+        let timestamp = if self.timestamp {
+            Some(utc_timestamp(SystemTime::now()))
+        } else {
+            None
+        };
+        if self.generator.is_none() && self.provenance.is_none() && timestamp.is_none() {
+            return Ok(());
+        }
+
+        let mut generated = String::from("Generated");
+        if let Some(generator) = &self.generator {
+            generated.push_str(" by ");
+            generated.push_str(generator);
+        }
+        if let Some(timestamp) = &timestamp {
+            generated.push_str(" at ");
+            generated.push_str(timestamp);
+        }
+        write_comment(writer, &generated)?;
+        if let Some(provenance) = &self.provenance {
+            write_comment(writer, &format!("Source: {}", provenance))?;
+        }
+        write_comment(writer, "Do not edit this file by hand.")?;
+        writer.write_all(b"\n").map_err(Error::Io)
+    }
+
+    fn write_entries(&self, map: &EnvMap, writer: &mut dyn Write) -> Result<()> {
+        let mut entries: Vec<_> = map.iter().collect();
+        if self.sorted {
+            entries.sort_unstable_by_key(|&(key, _)| key);
+        }
+
+        let mut previous_group = None;
+        for (key, value) in entries {
+            if let Some(separator) = &self.group_separator {
+                let group = key.split(separator.as_str()).next().unwrap_or(key);
+                if previous_group.map_or(false, |previous| previous != group) {
+                    writer.write_all(b"\n").map_err(Error::Io)?;
+                }
+                previous_group = Some(group);
+            }
+            self.format.write_entry(key, value, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all variables in `map` to the file at `path`, replacing its contents.
+    pub fn write_path<P: AsRef<Path>>(&self, map: &EnvMap, path: P) -> Result<()> {
+        let file = File::create(path).map_err(Error::Io)?;
+        self.write(map, BufWriter::new(file))
+    }
+}
+
+impl Default for EnvWriter {
+    fn default() -> Self {
+        EnvWriter::new()
+    }
+}
+
+/// Writes a `#` comment, replacing line breaks so that `text` stays on one line.
+fn write_comment(writer: &mut dyn Write, text: &str) -> Result<()> {
+    let text = text.replace(|c| c == '\n' || c == '\r', " ");
+    writeln!(writer, "# {}", text).map_err(Error::Io)
+}
+
+/// Checks the checksum trailer written by [`EnvWriter::checksum`].
+pub(crate) fn generated_status(contents: &[u8]) -> GeneratedStatus {
+    let body = contents.strip_suffix(b"\n").unwrap_or(contents);
+    let body = body.strip_suffix(b"\r").unwrap_or(body);
+    let start = body
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |pos| pos + 1);
+    let checksum = std::str::from_utf8(&body[start..])
+        .ok()
+        .and_then(|line| line.strip_prefix(CHECKSUM_PREFIX))
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok());
+    match checksum {
+        None => GeneratedStatus::HandWritten,
+        Some(checksum) if checksum == fnv1a64(&contents[..start]) => GeneratedStatus::Unmodified,
+        Some(_) => GeneratedStatus::Modified,
+    }
+}
+
+/// The 64-bit FNV-1a hash, which is small and stable across platforms and releases.
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp with second precision.
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // civil date from days since the epoch, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::format::Properties;
+
+    fn map() -> EnvMap {
+        vec![
+            ("PORT", "8080"),
+            ("DB_USER", "app"),
+            ("APP", "demo"),
+            ("DB_HOST", "localhost"),
+            ("APP_NAME", "demo"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn write(writer: EnvWriter) -> String {
+        let mut output = Vec::new();
+        writer.write(&map(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_map_order() {
+        assert_eq!(
+            write(EnvWriter::new()),
+            "PORT=8080\nDB_USER=app\nAPP=demo\nDB_HOST=localhost\nAPP_NAME=demo\n"
+        );
+    }
+
+    #[test]
+    fn test_sorted() {
+        assert_eq!(
+            write(EnvWriter::new().sorted(true)),
+            "APP=demo\nAPP_NAME=demo\nDB_HOST=localhost\nDB_USER=app\nPORT=8080\n"
+        );
+    }
+
+    #[test]
+    fn test_sorted_is_byte_order() {
+        let map: EnvMap = vec![("b", "1"), ("B", "2"), ("_", "3"), ("a", "4")]
+            .into_iter()
+            .collect();
+        let mut output = Vec::new();
+        EnvWriter::new()
+            .sorted(true)
+            .write(&map, &mut output)
+            .unwrap();
+        assert_eq!(output, b"B=2\n_=3\na=4\nb=1\n");
+    }
+
+    #[test]
+    fn test_sorted_groups() {
+        assert_eq!(
+            write(EnvWriter::new().sorted(true).group_by_prefix("_")),
+            "APP=demo\nAPP_NAME=demo\n\nDB_HOST=localhost\nDB_USER=app\n\nPORT=8080\n"
+        );
+    }
+
+    #[test]
+    fn test_unsorted_groups() {
+        assert_eq!(
+            write(EnvWriter::new().group_by_prefix("_")),
+            "PORT=8080\n\nDB_USER=app\n\nAPP=demo\n\nDB_HOST=localhost\n\nAPP_NAME=demo\n"
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let map: EnvMap = vec![("b.key", "x y"), ("a.key", "1")].into_iter().collect();
+        let mut output = Vec::new();
+        EnvWriter::new()
+            .format(Properties::new())
+            .sorted(true)
+            .write(&map, &mut output)
+            .unwrap();
+        assert_eq!(output, b"a.key=1\nb.key=x y\n");
+    }
+
+    #[test]
+    fn test_header_and_checksum() {
+        let output = write(
+            EnvWriter::new()
+                .generated_by("gen\ntool")
+                .provenance("vault")
+                .checksum(true),
+        );
+        let (body, trailer) = output.split_at(output.rfind("# checksum").unwrap());
+        assert_eq!(
+            body,
+            "# Generated by gen tool\n# Source: vault\n# Do not edit this file by hand.\n\n\
+             PORT=8080\nDB_USER=app\nAPP=demo\nDB_HOST=localhost\nAPP_NAME=demo\n"
+        );
+        assert_eq!(
+            trailer,
+            format!("{}{:016x}\n", CHECKSUM_PREFIX, fnv1a64(body.as_bytes()))
+        );
+        assert_eq!(
+            generated_status(output.as_bytes()),
+            GeneratedStatus::Unmodified
+        );
+
+        let edited = output.replace("PORT=8080", "PORT=9090");
+        assert_eq!(
+            generated_status(edited.as_bytes()),
+            GeneratedStatus::Modified
+        );
+        assert_eq!(
+            generated_status(b"PORT=8080\n"),
+            GeneratedStatus::HandWritten
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert!(write(EnvWriter::new().timestamp(true)).starts_with("# Generated at "));
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096);
+        assert_eq!(utc_timestamp(time), "2024-02-29T12:34:56Z");
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs;
+
+use dotenvy::{Bootstrap, EnvSchema, VarSpec};
+
+use crate::util::*;
+
+#[test]
+fn bootstrap_reads_cascade() {
+    let test_env = TestEnv::init_with_envfile("A=base\nB=base\nC=base\n");
+    let dir = test_env.temp_path().to_owned();
+    fs::write(dir.join(".env.local"), "B=local\n").unwrap();
+    fs::write(dir.join(".env.test"), "C=test\n").unwrap();
+    fs::write(dir.join(".env.other"), "C=other\n").unwrap();
+    test_in_env(test_env, || {
+        let report = Bootstrap::new().profile("test").run();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.files().len(), 3);
+        assert_eq!(report.applied(), ["A", "B", "C"]);
+        assert_env_var("A", "base");
+        assert_env_var("B", "local");
+        assert_env_var("C", "test");
+    })
+}
+
+#[test]
+fn bootstrap_reports_all_failures() {
+    let test_env = TestEnv::init_with_envfile("A=base\n");
+    let dir = test_env.temp_path().to_owned();
+    fs::write(dir.join(".env.local"), "not a declaration\n").unwrap();
+    test_in_env(test_env, || {
+        let schema = EnvSchema::new().var(VarSpec::new("TOKEN").required(true));
+        let report = Bootstrap::new().schema(schema).run();
+        assert!(!report.is_ok());
+        assert_eq!(report.errors().len(), 2);
+        assert!(report.applied().is_empty());
+        assert_env_var_unset("A");
+        assert!(report
+            .to_string()
+            .ends_with("\n  Invalid environment: `TOKEN` is required but not set"));
+    })
+}
+
+#[test]
+fn bootstrap_requires_base_file() {
+    test_in_env(TestEnv::init(), || {
+        assert!(Bootstrap::new().run().is_ok());
+        let report = Bootstrap::new().required(true).run();
+        assert!(report.errors()[0].not_found());
+    })
+}
@@ -0,0 +1,41 @@
+use dotenvy::{from_filename_into, from_path_into};
+use serde::Deserialize;
+
+use crate::util::*;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Config {
+    port: u16,
+    debug: bool,
+    #[serde(default)]
+    hosts: Vec<String>,
+    name: Option<String>,
+}
+
+#[test]
+fn from_filename_into_deserializes_without_setting_vars() {
+    let test_env = TestEnv::init_with_envfile("PORT=8080\nDEBUG=true\nHOSTS=a,b\nPORT=9090\n");
+    test_in_env(test_env, || {
+        let config: Config = from_filename_into(".env").expect("from_filename_into");
+        assert_eq!(
+            config,
+            Config {
+                port: 9090,
+                debug: true,
+                hosts: vec!["a".to_string(), "b".to_string()],
+                name: None,
+            }
+        );
+        assert_env_var_unset("PORT");
+    })
+}
+
+#[test]
+fn from_path_into_reports_missing_fields() {
+    let test_env = TestEnv::init_with_envfile("DEBUG=false\n");
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        let err = from_path_into::<Config, _>(&path).unwrap_err();
+        assert!(err.to_string().contains("port"), "{}", err);
+    })
+}
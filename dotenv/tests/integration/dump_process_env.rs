@@ -0,0 +1,48 @@
+use std::{env, fs};
+
+use dotenvy::{dump_process_env, EnvLoader};
+
+use crate::util::*;
+
+#[test]
+fn dump_process_env_round_trips() {
+    let mut test_env = TestEnv::init();
+    test_env
+        .add_env_var("DUMP_PLAIN", "value")
+        .add_env_var("DUMP_QUOTED", "it's a \"test\" $HOME")
+        .add_env_var("DUMP_MULTILINE", "line 1\nline 2")
+        .add_env_var("OTHER", "skipped");
+    let path = test_env.envfile_path().with_file_name("captured.env");
+    test_in_env(test_env, || {
+        let map = dump_process_env(&path, |key| key.starts_with("DUMP_")).expect("dump");
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            ["DUMP_MULTILINE", "DUMP_PLAIN", "DUMP_QUOTED"]
+        );
+
+        let loaded = EnvLoader::new().path(&path).parse().expect("parse");
+        assert_eq!(loaded, map);
+        assert_eq!(
+            loaded.get("DUMP_QUOTED").map(str::to_owned),
+            env::var("DUMP_QUOTED").ok()
+        );
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn dump_process_env_follows_symlinks() {
+    let mut test_env = TestEnv::init_with_envfile("OLD=1\n");
+    test_env.add_env_var("DUMP_LINKED", "value");
+    let target = test_env.envfile_path().to_owned();
+    let link = test_env.temp_path().join("link.env");
+    test_in_env(test_env, || {
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        dump_process_env(&link, |key| key == "DUMP_LINKED").expect("dump");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "DUMP_LINKED=value\n");
+    })
+}
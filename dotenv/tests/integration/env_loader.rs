@@ -0,0 +1,343 @@
+use std::cell::RefCell;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+
+use dotenvy::{Decoding, EmptyValues, EnvFormat, EnvLoader, EnvMap, MemoryFileSystem};
+
+use crate::util::*;
+
+struct Upper;
+
+impl EnvFormat for Upper {
+    fn parse(&self, reader: &mut dyn Read) -> dotenvy::Result<EnvMap> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .map_err(dotenvy::Error::Io)?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.to_uppercase()))
+            .collect())
+    }
+}
+
+#[test]
+fn parse_does_not_modify_env() {
+    test_in_default_env(|| {
+        let map = EnvLoader::new().parse().expect("parse");
+        assert_eq!(map.get(TEST_KEY), Some(TEST_VALUE));
+        assert_env_var_unset(TEST_KEY);
+        assert_env_var(TEST_EXISTING_KEY, TEST_EXISTING_VALUE);
+    })
+}
+
+#[test]
+fn load_preserves_existing() {
+    test_in_default_env(|| {
+        let map = EnvLoader::new().load().expect("load");
+        assert_eq!(map.get(TEST_EXISTING_KEY), Some(TEST_OVERRIDING_VALUE));
+        assert_env_var(TEST_KEY, TEST_VALUE);
+        assert_env_var(TEST_EXISTING_KEY, TEST_EXISTING_VALUE);
+    })
+}
+
+#[test]
+fn load_override_replaces_existing() {
+    test_in_default_env(|| {
+        EnvLoader::new().load_override().expect("load");
+        assert_env_var(TEST_KEY, TEST_VALUE);
+        assert_env_var(TEST_EXISTING_KEY, TEST_OVERRIDING_VALUE);
+    })
+}
+
+#[test]
+fn filename_searches_parents() {
+    let mut test_env = TestEnv::init_with_envfile("CHILD=found");
+    test_env.set_envfile_name("custom.env");
+    let child_dir = test_env.add_child_dir_all("child");
+    test_env.set_work_dir(child_dir);
+    test_in_env(test_env, || {
+        let loader = EnvLoader::new().filename("custom.env");
+        assert!(loader.resolve_path().expect("path").ends_with("custom.env"));
+        assert_eq!(loader.parse().expect("parse").get("CHILD"), Some("found"));
+    })
+}
+
+#[test]
+fn path_does_not_search_parents() {
+    let mut test_env = TestEnv::default();
+    let child_dir = test_env.add_child_dir_all("child");
+    test_env.set_work_dir(child_dir);
+    test_in_env(test_env, || {
+        let err = EnvLoader::new().path(".env").parse().unwrap_err();
+        assert!(err.not_found());
+    })
+}
+
+#[test]
+fn custom_format() {
+    test_in_env(TestEnv::init_with_envfile("shout=loud"), || {
+        EnvLoader::new().format(Upper).load().expect("load");
+        assert_env_var("SHOUT", "LOUD");
+    })
+}
+
+#[test]
+fn invalid_file() {
+    test_in_env(TestEnv::init_with_envfile(create_invalid_envfile()), || {
+        assert!(EnvLoader::new().load().is_err());
+        assert_env_var_unset(TEST_KEY);
+    })
+}
+
+#[test]
+fn loader_searches_memory_file_system() {
+    let fs = MemoryFileSystem::new("/app/src/bin")
+        .file("/app/custom.env", "A=parent")
+        .file("/app/src/custom.env", "A=closest");
+    let loader = EnvLoader::new().filename("custom.env").file_system(fs);
+    assert_eq!(
+        loader.resolve_path().unwrap(),
+        Path::new("/app/src/custom.env")
+    );
+    assert_eq!(loader.parse().unwrap().get("A"), Some("closest"));
+
+    let fs = MemoryFileSystem::new("/app");
+    let err = EnvLoader::new().file_system(fs).parse().unwrap_err();
+    assert!(err.not_found());
+}
+
+#[test]
+fn loader_renames_keys() {
+    let fs = MemoryFileSystem::new("/app").file(
+        "/app/.env",
+        "DB_URL=postgres://old\nOLD_PORT=1\nPORT=2\nHOST=localhost\n",
+    );
+    let renamed = Rc::new(RefCell::new(Vec::new()));
+    let reported = Rc::clone(&renamed);
+    let map = EnvLoader::new()
+        .file_system(fs)
+        .rename("DB_URL", "DATABASE_URL")
+        .rename("OLD_PORT", "PORT")
+        .rename("OLD_HOST", "HOST")
+        .on_renamed(move |old, new| reported.borrow_mut().push(format!("{}->{}", old, new)))
+        .parse()
+        .unwrap();
+    assert_eq!(map.get("DATABASE_URL"), Some("postgres://old"));
+    assert_eq!(map.get("PORT"), Some("2"));
+    assert!(!map.contains_key("DB_URL"));
+    assert!(!map.contains_key("OLD_PORT"));
+    assert_eq!(
+        *renamed.borrow(),
+        ["DB_URL->DATABASE_URL", "OLD_PORT->PORT"]
+    );
+}
+
+#[test]
+fn load_applies_selected_keys() {
+    let test_env = TestEnv::init_with_envfile("API_PORT=1\nAPI_DEBUG=1\nWEB_PORT=2\n");
+    test_in_env(test_env, || {
+        let map = EnvLoader::new()
+            .apply_only("API_*")
+            .apply_except("*_DEBUG")
+            .load()
+            .expect("load");
+        assert_eq!(map.len(), 3);
+        assert_env_var("API_PORT", "1");
+        assert_env_var_unset("API_DEBUG");
+        assert_env_var_unset("WEB_PORT");
+    })
+}
+
+#[test]
+fn loader_defaults() {
+    let mut test_env = TestEnv::init_with_envfile("PORT=9090\n");
+    test_env.add_env_var("HOST", "from_env");
+    test_in_env(test_env, || {
+        let defaults = vec![("PORT", "8080"), ("HOST", "localhost"), ("WORKERS", "4")];
+        let map = EnvLoader::new()
+            .defaults(defaults.clone())
+            .load_override()
+            .expect("load");
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [("PORT", "9090"), ("WORKERS", "4")]
+        );
+        assert_env_var("HOST", "from_env");
+        assert_env_var("WORKERS", "4");
+
+        let missing = EnvLoader::new().filename("missing.env");
+        assert!(missing.parse().unwrap_err().not_found());
+        // only defaults for variables which are still unset are used
+        let map = missing
+            .defaults(defaults)
+            .defaults(vec![("TIMEOUT", "30")])
+            .parse()
+            .expect("parse");
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("TIMEOUT", "30")]);
+    })
+}
+
+#[test]
+fn loader_errors_carry_absolute_path() {
+    let fs = MemoryFileSystem::new("/app/src").file("/app/.env", "A=1\nB='unterminated\n");
+    let err = EnvLoader::new().file_system(fs).parse().unwrap_err();
+    assert_eq!(err.path(), Some(Path::new("/app/.env")));
+    let relative = err.display_relative(Path::new("/app")).to_string();
+    assert!(
+        relative.starts_with(".env: Error parsing line"),
+        "{}",
+        relative
+    );
+
+    let fs = MemoryFileSystem::new("/app").file("/app/config/.env", "A=1\n");
+    let err = EnvLoader::new()
+        .path("config/missing.env")
+        .file_system(fs)
+        .parse()
+        .unwrap_err();
+    assert!(err.not_found());
+    assert_eq!(err.path(), Some(Path::new("/app/config/missing.env")));
+    assert!(err
+        .display_relative(Path::new("/app"))
+        .to_string()
+        .starts_with("config/missing.env: "));
+}
+
+#[test]
+fn loader_decoding_policy() {
+    let fs = || MemoryFileSystem::new("/app").file("/app/.env", &b"NAME=caf\xe9\nOK=1\n"[..]);
+    let err = EnvLoader::new().file_system(fs()).parse().unwrap_err();
+    let source = match err {
+        dotenvy::Error::File { source, .. } => *source,
+        err => panic!("unexpected error: {}", err),
+    };
+    assert!(
+        matches!(source, dotenvy::Error::Io(err) if err.kind() == std::io::ErrorKind::InvalidData)
+    );
+
+    let warned = Rc::new(RefCell::new(Vec::new()));
+    let reported = Rc::clone(&warned);
+    let map = EnvLoader::new()
+        .file_system(fs())
+        .decoding(Decoding::Lossy)
+        .on_invalid_utf8(move |path| reported.borrow_mut().push(path.to_owned()))
+        .parse()
+        .expect("parse");
+    assert_eq!(
+        map.iter().collect::<Vec<_>>(),
+        [("NAME", "caf\u{FFFD}"), ("OK", "1")]
+    );
+    assert_eq!(*warned.borrow(), [Path::new("/app/.env")]);
+
+    #[cfg(unix)]
+    test_in_env(TestEnv::init(), || {
+        use std::os::unix::ffi::OsStrExt;
+
+        let map = EnvLoader::new()
+            .file_system(fs())
+            .decoding(Decoding::Raw)
+            .load_override()
+            .expect("load");
+        assert_eq!(map.get("NAME"), Some("caf\u{FFFD}"));
+        assert_eq!(std::env::var_os("NAME").unwrap().as_bytes(), b"caf\xe9");
+        assert_env_var("OK", "1");
+        // the harness reads the environment as unicode
+        std::env::remove_var("NAME");
+    });
+}
+
+#[test]
+fn loader_warns_about_hand_modified_files() {
+    let mut map = EnvMap::new();
+    map.insert("PORT", "8080");
+    let mut generated = Vec::new();
+    dotenvy::EnvWriter::new()
+        .checksum(true)
+        .write(&map, &mut generated)
+        .unwrap();
+    let generated = String::from_utf8(generated).unwrap();
+    let edited = generated.replace("PORT=8080", "PORT=9090");
+
+    let warned = Rc::new(RefCell::new(Vec::new()));
+    let loader = |contents: &str| {
+        let reported = Rc::clone(&warned);
+        EnvLoader::new()
+            .file_system(MemoryFileSystem::new("/app").file("/app/.env", contents))
+            .on_hand_modified(move |path| reported.borrow_mut().push(path.to_owned()))
+    };
+    assert_eq!(
+        loader(&generated).parse().unwrap().get("PORT"),
+        Some("8080")
+    );
+    assert_eq!(loader("PORT=1\n").parse().unwrap().get("PORT"), Some("1"));
+    assert!(warned.borrow().is_empty());
+
+    assert_eq!(loader(&edited).parse().unwrap().get("PORT"), Some("9090"));
+    assert_eq!(*warned.borrow(), [Path::new("/app/.env")]);
+}
+
+#[test]
+fn empty_values_policy() {
+    let mut test_env = TestEnv::init_with_envfile("EMPTY=\nFULL=1\n");
+    test_env.add_env_var("EMPTY", "from_env");
+    test_in_env(test_env, || {
+        let map = EnvLoader::new().parse().expect("parse");
+        assert!(map.is_empty_value("EMPTY"));
+
+        let loader = EnvLoader::new().empty_values(EmptyValues::Skip);
+        let map = loader.load_override().expect("load");
+        assert!(!map.contains_key("EMPTY"));
+        assert_env_var("EMPTY", "from_env");
+
+        let loader = EnvLoader::new().empty_values(EmptyValues::Unset);
+        assert!(!loader.load().expect("load").contains_key("EMPTY"));
+        assert_env_var("EMPTY", "from_env");
+        loader.load_override().expect("load");
+        assert_env_var_unset("EMPTY");
+        assert_env_var("FULL", "1");
+    })
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn loader_decrypts_values() {
+    let private_key = dotenvy::PrivateKey::generate();
+    let mut map = EnvMap::new();
+    map.insert("PLAIN", "visible");
+    map.insert("SECRET", "hunter2");
+    map.encrypt_value("SECRET", &private_key.public_key())
+        .unwrap();
+    let mut contents = Vec::new();
+    dotenvy::EnvWriter::new()
+        .write(&map, &mut contents)
+        .unwrap();
+
+    let fs = MemoryFileSystem::new("/app").file("/app/.env", contents);
+    let map = EnvLoader::new()
+        .file_system(fs)
+        .private_key(private_key)
+        .parse()
+        .unwrap();
+    assert_eq!(map.get("PLAIN"), Some("visible"));
+    assert_eq!(map.get("SECRET"), Some("hunter2"));
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn loader_reads_private_key_only_for_encrypted_values() {
+    let mut test_env = TestEnv::init_with_envfile("PLAIN=visible\n");
+    test_env.add_env_var(dotenvy::PRIVATE_KEY_VAR, "not a key");
+    test_in_env(test_env, || {
+        let map = EnvLoader::new().parse().expect("parse");
+        assert_eq!(map.get("PLAIN"), Some("visible"));
+
+        let fs = MemoryFileSystem::new("/app").file(
+            "/app/.env",
+            format!("SECRET={}abc\n", dotenvy::ENCRYPTED_PREFIX),
+        );
+        assert!(EnvLoader::new().file_system(fs).parse().is_err());
+    })
+}
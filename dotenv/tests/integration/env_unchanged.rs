@@ -0,0 +1,31 @@
+use std::panic;
+
+use dotenvy::testing::assert_env_unchanged;
+use dotenvy::EnvLoader;
+
+use crate::util::*;
+
+#[test]
+fn assert_env_unchanged_reports_and_restores() {
+    let mut test_env = TestEnv::init_with_envfile("UNCHANGED_ADDED=1\nUNCHANGED_KEPT=file\n");
+    test_env.add_env_var("UNCHANGED_KEPT", "process");
+    test_in_env(test_env, || {
+        let map = assert_env_unchanged(|| EnvLoader::new().parse()).expect("parse");
+        assert_eq!(map.len(), 2);
+
+        let result = panic::catch_unwind(|| {
+            assert_env_unchanged(|| EnvLoader::new().load_override().map(|_| ()))
+        });
+        let payload = result.expect_err("loading changes the environment");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("a formatted message");
+        assert_eq!(
+            message,
+            "the environment was changed:\n  added UNCHANGED_ADDED=`1`\n  \
+             changed UNCHANGED_KEPT from `process` to `file`\n"
+        );
+        assert_env_var_unset("UNCHANGED_ADDED");
+        assert_env_var("UNCHANGED_KEPT", "process");
+    })
+}
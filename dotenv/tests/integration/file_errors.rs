@@ -0,0 +1,40 @@
+use std::env;
+
+use dotenvy::{dotenv, from_filename, from_path, from_path_iter};
+
+use crate::util::*;
+
+#[test]
+fn classic_errors_carry_absolute_path() {
+    let test_env = TestEnv::init_with_envfile("A=1\nB='unterminated\n");
+    test_in_env(test_env, || {
+        let expected = env::current_dir().expect("current dir").join(".env");
+
+        for err in [
+            dotenv().unwrap_err(),
+            from_filename(".env").unwrap_err(),
+            from_path(".env").unwrap_err(),
+        ] {
+            assert_eq!(err.path(), Some(expected.as_path()));
+            assert!(err.parse_error_kind().is_some());
+            let relative = err.display_relative(expected.parent().unwrap());
+            assert!(
+                relative.to_string().starts_with(".env: Error parsing line"),
+                "{}",
+                relative
+            );
+        }
+
+        let err = from_path_iter("missing.env").err().expect("missing file");
+        assert!(err.not_found());
+        assert_eq!(
+            err.path(),
+            Some(expected.with_file_name("missing.env").as_path())
+        );
+
+        // a search which finds nothing has no path to report
+        let err = from_filename("missing.env").unwrap_err();
+        assert!(err.not_found());
+        assert_eq!(err.path(), None);
+    })
+}
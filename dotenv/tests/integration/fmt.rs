@@ -0,0 +1,29 @@
+use std::fs;
+
+use dotenvy::fmt;
+
+use crate::util::*;
+
+#[test]
+fn fmt_rewrites_file() {
+    let test_env = TestEnv::init_with_envfile("# keep me\n  A = \"1\"  \n\n\nB='two words' #c\n");
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        assert!(fmt(&path).expect("fmt"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# keep me\nA=1\n\nB='two words' #c\n"
+        );
+        assert!(!fmt(&path).expect("fmt"));
+    })
+}
+
+#[test]
+fn fmt_leaves_invalid_file() {
+    let test_env = TestEnv::init_with_envfile(create_invalid_envfile());
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        assert!(fmt(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), create_invalid_envfile());
+    })
+}
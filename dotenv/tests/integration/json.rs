@@ -0,0 +1,49 @@
+use dotenvy::{from_json_path, from_json_path_override};
+
+use crate::util::*;
+
+fn json_env() -> TestEnv {
+    let mut test_env = TestEnv::init_with_envfile(format!(
+        r#"{{"{}": "{}", "{}": "{}", "NESTED": {{"KEY": 1}}}}"#,
+        TEST_KEY, TEST_VALUE, TEST_EXISTING_KEY, TEST_OVERRIDING_VALUE
+    ));
+    test_env
+        .set_envfile_name("secrets.json")
+        .add_env_var(TEST_EXISTING_KEY, TEST_EXISTING_VALUE);
+    test_env
+}
+
+#[test]
+fn from_json_path_preserves_existing() {
+    let test_env = json_env();
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        from_json_path(&path).expect("load");
+        assert_env_var(TEST_KEY, TEST_VALUE);
+        assert_env_var(TEST_EXISTING_KEY, TEST_EXISTING_VALUE);
+        assert_env_var("NESTED_KEY", "1");
+    })
+}
+
+#[test]
+fn from_json_path_override_replaces_existing() {
+    let test_env = json_env();
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        from_json_path_override(&path).expect("load");
+        assert_env_var(TEST_EXISTING_KEY, TEST_OVERRIDING_VALUE);
+    })
+}
+
+#[test]
+fn from_json_path_rejects_unsettable_variables() {
+    for contents in [r#"{"A=B": "1"}"#, r#"{"": "1"}"#, r#"{"K": "a\u0000b"}"#] {
+        let mut test_env = TestEnv::init_with_envfile(contents);
+        test_env.set_envfile_name("secrets.json");
+        let path = test_env.envfile_path().to_owned();
+        test_in_env(test_env, || {
+            let err = from_json_path(&path).unwrap_err();
+            assert!(err.to_string().contains("JSON"), "{}", err);
+        })
+    }
+}
@@ -0,0 +1,62 @@
+use dotenvy::{dotenv_report, from_filename_report, from_path_report, load_optional};
+
+use crate::util::*;
+
+#[test]
+fn dotenv_report_lists_applied_and_skipped() {
+    test_in_default_env(|| {
+        let report = dotenv_report().expect("dotenv_report");
+        assert_eq!(report.applied(), [TEST_KEY]);
+        assert_eq!(report.skipped(), [TEST_EXISTING_KEY]);
+        assert!(report.path().ends_with(".env"));
+        assert_env_var(TEST_KEY, TEST_VALUE);
+        assert_env_var(TEST_EXISTING_KEY, TEST_EXISTING_VALUE);
+    })
+}
+
+#[test]
+fn from_filename_report_reports_duplicates_once() {
+    let mut test_env = TestEnv::init_with_envfile("A=1\nA=2\nB=3\n");
+    test_env.set_envfile_name("custom.env");
+    test_in_env(test_env, || {
+        let report = from_filename_report("custom.env").expect("from_filename_report");
+        assert_eq!(report.applied(), ["A", "B"]);
+        assert!(report.skipped().is_empty());
+        assert_env_var("A", "1");
+    })
+}
+
+#[test]
+fn from_path_report_keeps_path() {
+    let test_env = TestEnv::init_with_envfile(create_default_envfile());
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        let report = from_path_report(&path).expect("from_path_report");
+        assert_eq!(report.path(), path);
+        assert!(report.is_applied(TEST_KEY));
+        assert!(!report.is_skipped(TEST_KEY));
+    })
+}
+
+#[test]
+fn load_optional_missing_file() {
+    test_in_env(TestEnv::init(), || {
+        assert_eq!(load_optional().expect("load_optional"), None);
+    })
+}
+
+#[test]
+fn load_optional_existing_file() {
+    test_in_default_env(|| {
+        let report = load_optional().expect("load_optional").expect("report");
+        assert!(report.is_applied(TEST_KEY));
+    })
+}
+
+#[test]
+fn load_optional_invalid_file() {
+    let test_env = TestEnv::init_with_envfile(create_invalid_envfile());
+    test_in_env(test_env, || {
+        assert!(load_optional().is_err());
+    })
+}
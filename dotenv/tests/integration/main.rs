@@ -1 +1,19 @@
+mod bootstrap;
+#[cfg(feature = "deserialize")]
+mod deserialize;
+mod dump_process_env;
+mod env_loader;
+#[cfg(feature = "testing")]
+mod env_unchanged;
+mod file_errors;
+mod fmt;
+#[cfg(feature = "json")]
+mod json;
+mod load_report;
+mod not_a_file;
+mod scaffold;
+#[cfg(unix)]
+mod spawn_matrix;
+mod update_key;
 mod util;
+mod watch;
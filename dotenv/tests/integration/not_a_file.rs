@@ -0,0 +1,45 @@
+use dotenvy::{EnvLoader, Error};
+
+use crate::util::*;
+
+fn assert_not_a_file<T>(result: dotenvy::Result<T>, expected: &std::path::Path) {
+    match result {
+        Err(Error::NotAFile { path }) => assert_eq!(path, expected),
+        Err(err) => panic!("expected NotAFile, got {:?}", err),
+        Ok(_) => panic!("expected NotAFile, got success"),
+    }
+}
+
+#[test]
+fn from_path_rejects_directory() {
+    let test_env = TestEnv::init();
+    let dir = test_env.add_child_dir_all("config.env");
+    test_in_env(test_env, || {
+        assert_not_a_file(dotenvy::from_path(&dir), &dir);
+        assert_not_a_file(dotenvy::from_path_iter(&dir), &dir);
+        assert_not_a_file(EnvLoader::new().path(&dir).parse(), &dir);
+    })
+}
+
+#[test]
+fn dotenv_skips_directory_named_envfile() {
+    let test_env = TestEnv::init();
+    test_env.add_child_dir_all(".env");
+    test_in_env(test_env, || {
+        assert!(dotenvy::dotenv().unwrap_err().not_found());
+        assert!(dotenvy::from_filename(".env").unwrap_err().not_found());
+        assert!(dotenvy::load_optional().expect("load").is_none());
+    })
+}
+
+#[test]
+fn dotenv_skips_directory_for_parent_file() {
+    let mut test_env = TestEnv::init_with_envfile(create_default_envfile());
+    let work_dir = test_env.add_child_dir_all("child");
+    test_env.add_child_dir_all("child/.env");
+    test_env.set_work_dir(work_dir);
+    test_in_env(test_env, || {
+        dotenvy::dotenv().expect("dotenv");
+        assert_env_var(TEST_KEY, TEST_VALUE);
+    })
+}
@@ -0,0 +1,62 @@
+use std::fs;
+
+use dotenvy::{EnvSchema, VarSpec};
+
+use crate::util::*;
+
+#[test]
+fn scaffold_appends_missing_keys() {
+    let test_env = TestEnv::init_with_envfile("# local settings\nPORT=9090");
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        let schema = EnvSchema::new()
+            .var(VarSpec::new("PORT").required(true))
+            .var(VarSpec::new("HOST"));
+        assert_eq!(schema.scaffold(&path).expect("scaffold"), ["HOST"]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# local settings\nPORT=9090\n\n\
+             # Placeholders for undeclared variables, fill in their values\n\n# HOST=\n"
+        );
+
+        assert!(schema.scaffold(&path).expect("scaffold").is_empty());
+
+        // placeholders leave the variables unset
+        dotenvy::from_path(&path).expect("load");
+        assert_env_var("PORT", "9090");
+        assert_env_var_unset("HOST");
+    })
+}
+
+#[test]
+fn scaffold_creates_missing_file() {
+    let test_env = TestEnv::init();
+    let path = test_env.envfile_path().with_file_name("new.env");
+    test_in_env(test_env, || {
+        let schema = EnvSchema::new().var(VarSpec::new("HOST"));
+        assert_eq!(schema.scaffold(&path).expect("scaffold"), ["HOST"]);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .ends_with("\n\n# HOST=\n"));
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn scaffold_follows_symlinks() {
+    let test_env = TestEnv::init_with_envfile("PORT=9090\n");
+    let target = test_env.envfile_path().to_owned();
+    let link = test_env.temp_path().join("link.env");
+    test_in_env(test_env, || {
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let schema = EnvSchema::new().var(VarSpec::new("HOST"));
+        assert_eq!(schema.scaffold(&link).expect("scaffold"), ["HOST"]);
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(fs::read_to_string(&target)
+            .unwrap()
+            .ends_with("\n\n# HOST=\n"));
+    })
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use std::process::{Command, Stdio};
+
+use dotenvy::{spawn_matrix, spawn_matrix_override};
+
+use crate::util::*;
+
+fn echo(var: &str) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("echo \"${{{}:-unset}}\"", var))
+        .stdout(Stdio::piped());
+    command
+}
+
+#[test]
+fn spawn_matrix_uses_each_file() {
+    let mut test_env = TestEnv::init();
+    test_env.add_env_var("MATRIX_EXISTING", "process");
+    let first = test_env.temp_path().join("first.env");
+    let second = test_env.temp_path().join("second.env");
+    let invalid = test_env.temp_path().join("invalid.env");
+    test_in_env(test_env, || {
+        fs::write(&first, "MATRIX_NAME=first\nMATRIX_EXISTING=file\n").unwrap();
+        fs::write(&second, "OTHER=2\n").unwrap();
+        fs::write(&invalid, "NOT VALID\n").unwrap();
+
+        let mut commands = [echo("MATRIX_NAME"), echo("MATRIX_EXISTING")];
+        let outputs = |children: Vec<dotenvy::MatrixChild>| {
+            children
+                .into_iter()
+                .map(|spawned| {
+                    let label = (spawned.env_file().to_owned(), spawned.command());
+                    let output = spawned.into_child().wait_with_output().unwrap();
+                    (label, String::from_utf8(output.stdout).unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let children = spawn_matrix(&mut commands, &[&first, &second]).expect("spawn");
+        assert_eq!(
+            outputs(children),
+            [
+                ((first.clone(), 0), "first\n".to_string()),
+                ((first.clone(), 1), "process\n".to_string()),
+                ((second.clone(), 0), "unset\n".to_string()),
+                ((second.clone(), 1), "process\n".to_string()),
+            ]
+        );
+
+        let children = spawn_matrix_override(&mut commands[1..], &[&first, &second]).unwrap();
+        let values: Vec<_> = outputs(children).into_iter().map(|(_, out)| out).collect();
+        assert_eq!(values, ["file\n", "process\n"]);
+
+        assert!(spawn_matrix(&mut commands, &[&first, &invalid]).is_err());
+        assert_env_var_unset("MATRIX_NAME");
+    })
+}
@@ -0,0 +1,112 @@
+use std::fs;
+
+use dotenvy::{ensure_key, update_key};
+
+use crate::util::*;
+
+#[test]
+fn update_key_rewrites_matching_line() {
+    let test_env = TestEnv::init_with_envfile("# port\nPORT = \"8080\" # http\nHOST=localhost\n");
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        update_key(&path, "PORT", "9090").expect("update_key");
+        update_key(&path, "TOKEN", "a b").expect("update_key");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# port\nPORT = \"9090\" # http\nHOST=localhost\nTOKEN='a b'\n"
+        );
+    })
+}
+
+#[test]
+fn update_key_creates_missing_file() {
+    let test_env = TestEnv::init();
+    let path = test_env.envfile_path().with_file_name("new.env");
+    test_in_env(test_env, || {
+        update_key(&path, "A", "1").expect("update_key");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "A=1\n");
+    })
+}
+
+#[test]
+fn ensure_key_appends_only_missing_keys() {
+    let test_env = TestEnv::init_with_envfile("PORT=9090\n");
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        assert!(!ensure_key(&path, "PORT", "8080").expect("ensure_key"));
+        assert!(ensure_key(&path, "HOST", "localhost").expect("ensure_key"));
+        assert!(!ensure_key(&path, "HOST", "example.com").expect("ensure_key"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "PORT=9090\nHOST=localhost\n"
+        );
+
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().collect();
+        assert_eq!(entries.len(), 1, "temporary file left behind");
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn update_key_follows_symlinks() {
+    let test_env = TestEnv::init_with_envfile("A=1\n");
+    let target = test_env.envfile_path().to_owned();
+    let link = test_env.temp_path().join("link.env");
+    let dangling = test_env.temp_path().join("dangling.env");
+    let created = test_env.temp_path().join("created.env");
+    test_in_env(test_env, || {
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        update_key(&link, "A", "2").expect("update_key");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "A=2\n");
+
+        std::os::unix::fs::symlink("created.env", &dangling).unwrap();
+        update_key(&dangling, "B", "1").expect("update_key");
+        assert!(fs::symlink_metadata(&dangling)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&created).unwrap(), "B=1\n");
+    })
+}
+
+#[test]
+fn update_key_from_several_threads() {
+    let test_env = TestEnv::init_with_envfile("A=0\n");
+    let path = test_env.envfile_path().to_owned();
+    test_in_env(test_env, || {
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for j in 0..20 {
+                        update_key(&path, "A", &format!("{}-{}", i, j)).expect("update_key");
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(
+            contents.starts_with("A=") && contents.lines().count() == 1,
+            "{}",
+            contents
+        );
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert!(
+            names
+                .iter()
+                .all(|name| !name.to_string_lossy().ends_with(".tmp")),
+            "{:?}",
+            names
+        );
+    })
+}
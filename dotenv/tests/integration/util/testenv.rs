@@ -24,7 +24,7 @@ static ENV_LOCKER: OnceCell<Arc<Mutex<EnvMap>>> = OnceCell::new();
 /// - [`TestEnv::init`]: blank environment (no envfile)
 /// - [`TestEnv::init_with_envfile`]: blank environment with an envfile
 /// - [`TestEnv::default`]: default testing environment (1 existing var and 2
-///       set in a `.env` file)
+///   set in a `.env` file)
 #[derive(Debug)]
 pub struct TestEnv {
     temp_dir: TempDir,
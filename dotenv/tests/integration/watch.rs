@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use dotenvy::{EnvLoader, Layers, Watcher};
+
+#[test]
+fn watcher_reports_changed_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.env");
+    let second = dir.path().join("second.env");
+    fs::write(&first, "A=1\n").unwrap();
+
+    let mut watcher = Watcher::new(vec![&first, &second]);
+    assert!(watcher.poll().is_empty());
+
+    fs::write(&first, "A=10\n").unwrap();
+    assert_eq!(watcher.poll(), std::slice::from_ref(&first));
+    assert!(watcher.poll().is_empty());
+
+    fs::write(&second, "B=2\n").unwrap();
+    fs::remove_file(&first).unwrap();
+    assert_eq!(watcher.poll(), [first, second.clone()]);
+
+    let mut watcher = watcher.interval(Duration::from_millis(10));
+    fs::write(&second, "B=20\n").unwrap();
+    assert_eq!(watcher.wait(), [second]);
+}
+
+#[test]
+fn watcher_covers_loaded_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join(".env");
+    let local = dir.path().join(".env.local");
+    let missing = dir.path().join("missing.env");
+    fs::write(&base, "A=1\n").unwrap();
+    fs::write(&local, "A=2\n").unwrap();
+
+    let loader = EnvLoader::new().path(&base);
+    let paths = |watcher: &Watcher| watcher.paths().map(Path::to_owned).collect::<Vec<_>>();
+    assert_eq!(
+        paths(&loader.watcher().unwrap()),
+        std::slice::from_ref(&base)
+    );
+
+    let layers = Layers::new()
+        .loader(loader)
+        .file(&local)
+        .file(&missing)
+        .loader(EnvLoader::new().filename("dotenvy-watch-missing.env"))
+        .process_env();
+    assert_eq!(paths(&layers.watcher()), [base, local, missing]);
+
+    let err = EnvLoader::new()
+        .filename("dotenvy-watch-missing.env")
+        .watcher()
+        .unwrap_err();
+    assert!(err.not_found());
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_restarts_command_when_file_changes() {
+    use std::process::Command;
+    use std::thread;
+    use std::time::Instant;
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("output");
+    fs::write(dir.path().join(".env"), "DOTENVY_WATCHED=first\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dotenvy"))
+        .current_dir(dir.path())
+        .env_remove("DOTENVY_WATCHED")
+        .args(["--watch", "sh", "-c", "echo $DOTENVY_WATCHED >> output"])
+        .spawn()
+        .unwrap();
+
+    let wait_for = |expected: &str| {
+        let start = Instant::now();
+        while fs::read_to_string(&output).unwrap_or_default() != expected {
+            if start.elapsed() > Duration::from_secs(10) {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        true
+    };
+    let started = wait_for("first\n");
+    if started {
+        fs::write(dir.path().join(".env"), "DOTENVY_WATCHED=second\n").unwrap();
+    }
+    let restarted = started && wait_for("first\nsecond\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(started && restarted, "{:?}", fs::read_to_string(&output));
+}
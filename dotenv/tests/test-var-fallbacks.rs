@@ -0,0 +1,32 @@
+mod common;
+
+use dotenvy::*;
+use std::{env, error::Error, result::Result};
+
+use crate::common::*;
+
+#[test]
+fn test_var_fallbacks() -> Result<(), Box<dyn Error>> {
+    let dir = make_test_dotenv()?;
+
+    assert_eq!(var_opt("TESTKEY")?, Some("test_val".to_string()));
+    assert_eq!(var_opt("TESTKEY_MISSING")?, None);
+    assert!(matches!(
+        var("TESTKEY_MISSING"),
+        Err(dotenvy::Error::VarNotFound { key, .. }) if key == "TESTKEY_MISSING"
+    ));
+    assert_eq!(
+        var("TSETKEY").unwrap_err().to_string(),
+        "environment variable `TSETKEY` not found; did you mean `TESTKEY`?"
+    );
+    assert_eq!(var_or("TESTKEY", "default")?, "test_val");
+    assert_eq!(var_or("TESTKEY_MISSING", "default")?, "default");
+    assert_eq!(
+        var_or_else("TESTKEY_MISSING", || "computed".to_string())?,
+        "computed"
+    );
+
+    env::set_current_dir(dir.path().parent().unwrap())?;
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use std::{env, error::Error, result::Result};
+
+use dotenvy::*;
+
+use crate::common::*;
+
+#[test]
+fn test_vars_filtered() -> Result<(), Box<dyn Error>> {
+    let dir = make_test_dotenv()?;
+
+    let vars = vars_with_prefix("TESTK");
+    assert_eq!(vars.get("TESTKEY"), Some("test_val"));
+    assert!(!vars.contains_key("EXISTING"));
+
+    let vars = vars_filtered(|key| key == "EXISTING");
+    assert_eq!(vars.keys().collect::<Vec<_>>(), ["EXISTING"]);
+
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        env::set_var("NOT_UNICODE", OsStr::from_bytes(b"\xff"));
+        let vars = vars_with_prefix("TESTK");
+        assert_eq!(vars.keys().collect::<Vec<_>>(), ["TESTKEY"]);
+        assert!(!vars_filtered(|_| true).contains_key("NOT_UNICODE"));
+    }
+
+    #[cfg(feature = "regex")]
+    {
+        let pattern = regex::Regex::new("^TEST.EY$")?;
+        let vars = vars_matching(&pattern);
+        assert_eq!(vars.keys().collect::<Vec<_>>(), ["TESTKEY"]);
+    }
+
+    env::set_current_dir(dir.path().parent().unwrap())?;
+    dir.close()?;
+    Ok(())
+}
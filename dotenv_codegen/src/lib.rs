@@ -1,6 +1,7 @@
 #![forbid(unsafe_code)]
 
 use std::env::{self, VarError};
+use std::path::Path;
 
 use quote::quote;
 use syn::parse::Parser;
@@ -13,6 +14,81 @@ pub fn dotenv(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     dotenv_inner(input.into()).into()
 }
 
+/// Fails the build if an environment file does not declare all of the given keys.
+///
+/// This keeps a template such as *.env.example* in step with the variables the code requires.
+/// The path is relative to the directory of the crate's `Cargo.toml`, and the crate is rebuilt
+/// when the file changes. The macro can be used as an item or a statement, for example in a
+/// test or at the top of a module.
+///
+/// ```ignore
+/// dotenvy_macro::assert_keys!(".env.example", ["DATABASE_URL", "PORT"]);
+/// ```
+#[proc_macro]
+pub fn assert_keys(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match assert_keys_inner(input.into()) {
+        Ok(stream) => stream,
+        Err(e) => e.to_compile_error(),
+    }
+    .into()
+}
+
+struct AssertKeysInput {
+    path: syn::LitStr,
+    keys: Punctuated<syn::LitStr, Token![,]>,
+}
+
+impl syn::parse::Parse for AssertKeysInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let content;
+        syn::bracketed!(content in input);
+        let keys = content.parse_terminated(<syn::LitStr as syn::parse::Parse>::parse)?;
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        Ok(AssertKeysInput { path, keys })
+    }
+}
+
+fn assert_keys_inner(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenStream> {
+    let input: AssertKeysInput = syn::parse2(input)?;
+    let relative = input.path.value();
+    let path = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => Path::new(&dir).join(&relative),
+        None => Path::new(&relative).to_owned(),
+    };
+
+    let map = dotenvy::EnvLoader::new().path(&path).parse().map_err(|e| {
+        syn::Error::new(
+            input.path.span(),
+            format!("failed to read `{}`: {}", relative, e),
+        )
+    })?;
+
+    let mut errors = input
+        .keys
+        .iter()
+        .filter(|key| !map.contains_key(&key.value()))
+        .map(|key| {
+            syn::Error::new(
+                key.span(),
+                format!("`{}` is not declared in `{}`", key.value(), relative),
+            )
+        });
+    if let Some(mut error) = errors.next() {
+        error.extend(errors);
+        return Err(error);
+    }
+
+    // include the file so that the crate is rebuilt when it changes
+    let path = path.to_string_lossy().into_owned();
+    Ok(quote! {
+        const _: &str = include_str!(#path);
+    })
+}
+
 fn dotenv_inner(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
     if let Err(err) = dotenvy::dotenv() {
         let msg = format!("Error loading .env file: {}", err);
@@ -67,3 +143,49 @@ fn expand_env(input_raw: proc_macro2::TokenStream) -> syn::Result<proc_macro2::T
         )),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn messages(error: syn::Error) -> Vec<String> {
+        error.into_iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_assert_keys_passes_for_declared_keys() {
+        let input = quote!("../.env", ["CODEGEN_TEST_VAR1", "CODEGEN_TEST_VAR2"]);
+        assert!(assert_keys_inner(input).is_ok());
+    }
+
+    #[test]
+    fn test_assert_keys_reports_each_missing_key() {
+        let input = quote!(
+            "../.env",
+            ["CODEGEN_TEST_VAR1", "MISSING_ONE", "MISSING_TWO"]
+        );
+        let error = assert_keys_inner(input).unwrap_err();
+        assert_eq!(
+            messages(error),
+            [
+                "`MISSING_ONE` is not declared in `../.env`",
+                "`MISSING_TWO` is not declared in `../.env`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assert_keys_reports_unreadable_file() {
+        let input = quote!("../.env.does-not-exist", ["CODEGEN_TEST_VAR1"]);
+        let error = assert_keys_inner(input).unwrap_err();
+        let messages = messages(error);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].starts_with("failed to read `../.env.does-not-exist`: "));
+    }
+
+    #[test]
+    fn test_assert_keys_rejects_malformed_input() {
+        assert!(assert_keys_inner(quote!("../.env")).is_err());
+        assert!(assert_keys_inner(quote!("../.env", [KEY])).is_err());
+    }
+}
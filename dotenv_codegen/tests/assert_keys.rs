@@ -0,0 +1,6 @@
+dotenvy_macro::assert_keys!("../.env", ["CODEGEN_TEST_VAR1", "CODEGEN_TEST_VAR2"]);
+
+#[test]
+fn assert_keys_works_as_statement() {
+    dotenvy_macro::assert_keys!("../.env", ["CODEGEN_TEST_MULTILINE1",]);
+}